@@ -13,5 +13,16 @@ fn main() {
         println!("cargo:rustc-env=APP_ENDPOINT={}", app_endpoint);
     }
     
+    if let Ok(license_public_key) = std::env::var("LICENSE_PUBLIC_KEY") {
+        println!("cargo:rustc-env=LICENSE_PUBLIC_KEY={}", license_public_key);
+    }
+    
+    if let Ok(license_grace_days) = std::env::var("LICENSE_GRACE_DAYS") {
+        println!("cargo:rustc-env=LICENSE_GRACE_DAYS={}", license_grace_days);
+    }
+    
+    println!("cargo:rerun-if-env-changed=LICENSE_PUBLIC_KEY");
+    println!("cargo:rerun-if-env-changed=LICENSE_GRACE_DAYS");
+    
     tauri_build::build()
 }
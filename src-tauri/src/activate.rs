@@ -1,239 +1,108 @@
-use serde::{Deserialize, Serialize};
-use std::env;
-use std::fs;
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager};
-use uuid::Uuid;
-
-fn get_payment_endpoint() -> Result<String, String> {
-    if let Ok(endpoint) = env::var("PAYMENT_ENDPOINT") {
-        return Ok(endpoint);
-    }
-    
-    match option_env!("PAYMENT_ENDPOINT") {
-        Some(endpoint) => Ok(endpoint.to_string()),
-        None => Err("PAYMENT_ENDPOINT environment variable not set. Please ensure it's set during the build process.".to_string())
-    }
+use crate::backend::{
+    self, ActivationResponse, Backend, CheckoutResponse, DeactivationResponse, InstancesResponse,
+    ValidationResponse,
+};
+use crate::endpoints;
+use crate::error::AppError;
+use crate::http;
+use crate::license::{self, LicenseStatus, LicenseVerifier};
+use crate::models::now_secs;
+use crate::secret_store::SecretStore;
+
+fn backend_client(app: &AppHandle) -> Result<Backend, AppError> {
+    Ok(Backend::new(http::client(app), endpoints::backend_config(app)?))
 }
 
-fn get_api_access_key() -> Result<String, String> {
-     if let Ok(key) = env::var("API_ACCESS_KEY") {
-        return Ok(key);
-    }
-    
-     match option_env!("API_ACCESS_KEY") {
-        Some(key) => Ok(key.to_string()),
-        None => Err("API_ACCESS_KEY environment variable not set. Please ensure it's set during the build process.".to_string())
+// The saved license was issued through the built-in profile's payment service and is only
+// ever sent there
+fn license_client(app: &AppHandle) -> Result<Backend, AppError> {
+    let config = endpoints::backend_config(app)?;
+    if !config.sends_license {
+        return Err(AppError::Config(
+            "Licenses are managed with the default endpoint profile. Switch back to it first.".to_string(),
+        ));
     }
+    Ok(Backend::new(http::client(app), config))
 }
 
-// Secure storage functions using Tauri's app data directory
-fn get_secure_storage_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let app_data_dir = app.path().app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-    
-    // Create the directory if it doesn't exist
-    fs::create_dir_all(&app_data_dir)
-        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
-    
-    Ok(app_data_dir.join("secure_storage.json"))
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    app.path().app_data_dir()
+        .map_err(|e| AppError::Storage(format!("Failed to get app data directory: {}", e)))
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-struct SecureStorage {
-    license_key: Option<String>,
-    instance_id: Option<String>,
-    selected_pluely_model: Option<String>,
+// The key given, or the one activated here. Managing seats from a new device works before
+// activating it there.
+fn license_key_or_stored(app: &AppHandle, license_key: Option<String>) -> Result<String, AppError> {
+    match license_key {
+        Some(key) => Ok(key),
+        None => Ok(backend::load_credentials(&app.state::<SecretStore>())?.license_key),
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct StorageItem {
-    key: String,
-    value: String,
+#[tauri::command]
+pub async fn activate_license_api(app: AppHandle, license_key: String) -> Result<ActivationResponse, AppError> {
+    let install_id = license::install_id(&app_data_dir(&app)?)?;
+    let response = license_client(&app)?
+        .activate(license_key.clone(), license::instance_name(&install_id))
+        .await?;
+    if let (true, Some(instance)) = (response.activated, &response.instance) {
+        let store = app.state::<SecretStore>();
+        license::save_activation(&store, &LicenseVerifier::from_build(), &license_key, &instance.id, response.token.as_deref())?;
+    }
+    Ok(response)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct StorageResult {
+/// Frees a seat; this device's when `instance_id` is omitted. Deactivating this device also
+/// forgets the license here.
+#[tauri::command]
+pub async fn deactivate_license(
+    app: AppHandle,
     license_key: Option<String>,
     instance_id: Option<String>,
-    selected_pluely_model: Option<String>,
-}
-
-#[tauri::command]
-pub async fn secure_storage_save(app: AppHandle, items: Vec<StorageItem>) -> Result<(), String> {
-    let storage_path = get_secure_storage_path(&app)?;
-    
-    let mut storage = if storage_path.exists() {
-        let content = fs::read_to_string(&storage_path)
-            .map_err(|e| format!("Failed to read storage file: {}", e))?;
-        serde_json::from_str(&content).unwrap_or_default()
-    } else {
-        SecureStorage::default()
-    };
-    
-    for item in items {
-        match item.key.as_str() {
-            "pluely_license_key" => storage.license_key = Some(item.value),
-            "pluely_instance_id" => storage.instance_id = Some(item.value),
-            "selected_pluely_model" => storage.selected_pluely_model = Some(item.value),
-            _ => return Err(format!("Invalid storage key: {}", item.key)),
-        }
+) -> Result<DeactivationResponse, AppError> {
+    let local = backend::load_credentials(&app.state::<SecretStore>()).ok();
+    let license_key = license_key_or_stored(&app, license_key)?;
+    let instance_id = instance_id
+        .or_else(|| local.as_ref().map(|creds| creds.instance_id.clone()))
+        .ok_or(AppError::Config("No instance to deactivate".to_string()))?;
+
+    let response = license_client(&app)?.deactivate(license_key.clone(), instance_id.clone()).await?;
+    let is_local = local.is_some_and(|creds| creds.license_key == license_key && creds.instance_id == instance_id);
+    if response.deactivated && is_local {
+        backend::clear_credentials(&app.state::<SecretStore>())?;
     }
-    
-    let content = serde_json::to_string(&storage)
-        .map_err(|e| format!("Failed to serialize storage: {}", e))?;
-    
-    fs::write(&storage_path, content)
-        .map_err(|e| format!("Failed to write storage file: {}", e))?;
-    
-    Ok(())
+    Ok(response)
 }
 
 #[tauri::command]
-pub async fn secure_storage_get(app: AppHandle) -> Result<StorageResult, String> {
-    let storage_path = get_secure_storage_path(&app)?;
-    
-    if !storage_path.exists() {
-        return Ok(StorageResult {
-            license_key: None,
-            instance_id: None,
-            selected_pluely_model: None,
-        });
-    }
-    
-    let content = fs::read_to_string(&storage_path)
-        .map_err(|e| format!("Failed to read storage file: {}", e))?;
-    
-    let storage: SecureStorage = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse storage file: {}", e))?;
-    
-    Ok(StorageResult {
-        license_key: storage.license_key,
-        instance_id: storage.instance_id,
-        selected_pluely_model: storage.selected_pluely_model,
-    })
+pub async fn list_license_instances(app: AppHandle, license_key: Option<String>) -> Result<InstancesResponse, AppError> {
+    let license_key = license_key_or_stored(&app, license_key)?;
+    license_client(&app)?.list_instances(license_key).await
 }
 
+/// Asks the payment service whether the license activated here is still valid, keeping the
+/// fresh token it returns so the offline grace period starts over, or dropping the token when
+/// the license is refused
 #[tauri::command]
-pub async fn secure_storage_remove(app: AppHandle, keys: Vec<String>) -> Result<(), String> {
-    let storage_path = get_secure_storage_path(&app)?;
-    
-    if !storage_path.exists() {
-        return Ok(()); // Nothing to remove
+pub async fn validate_license(app: AppHandle) -> Result<ValidationResponse, AppError> {
+    let creds = backend::load_credentials(&app.state::<SecretStore>())?;
+    let response = license_client(&app)?.validate(creds.license_key, creds.instance_id).await?;
+    let store = app.state::<SecretStore>();
+    match (response.valid, &response.token) {
+        (true, Some(token)) => license::save_token(&store, &LicenseVerifier::from_build(), token)?,
+        (true, None) => {}
+        (false, _) => license::revoke(&store, response.error.as_deref())?,
     }
-    
-    let content = fs::read_to_string(&storage_path)
-        .map_err(|e| format!("Failed to read storage file: {}", e))?;
-    
-    let mut storage: SecureStorage = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse storage file: {}", e))?;
-    
-    for key in keys {
-        match key.as_str() {
-            "pluely_license_key" => storage.license_key = None,
-            "pluely_instance_id" => storage.instance_id = None,
-            "selected_pluely_model" => storage.selected_pluely_model = None,
-            _ => return Err(format!("Invalid storage key: {}", key)),
-        }
-    }
-    
-    let content = serde_json::to_string(&storage)
-        .map_err(|e| format!("Failed to serialize storage: {}", e))?;
-    
-    fs::write(&storage_path, content)
-        .map_err(|e| format!("Failed to write storage file: {}", e))?;
-    
-    Ok(())
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ActivationRequest {
-    license_key: String,
-    instance_name: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ActivationResponse {
-    activated: bool,
-    error: Option<String>,
-    license_key: Option<String>,
-    instance: Option<InstanceInfo>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct InstanceInfo {
-    id: String,
-    name: String,
-    created_at: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CheckoutResponse {
-    success: Option<bool>,
-    checkout_url: Option<String>,
-    error: Option<String>,
+    Ok(response)
 }
 
+/// Expiry, grace period and entitlements of the license activated here, from the stored token
+/// only; no network request
 #[tauri::command]
-pub async fn activate_license_api(license_key: String) -> Result<ActivationResponse, String> {
-    // Get payment endpoint and API access key from environment
-    let payment_endpoint = get_payment_endpoint()?;
-    let api_access_key = get_api_access_key()?;
-    
-    // Generate UUID for instance name
-    let instance_name = Uuid::new_v4().to_string();
-    
-    // Prepare activation request
-    let activation_request = ActivationRequest {
-        license_key: license_key.clone(),
-        instance_name: instance_name.clone(),
-    };
-    
-    // Make HTTP request to activation endpoint with authorization header
-    let client = reqwest::Client::new();
-    let url = format!("{}/activate", payment_endpoint);
-    
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_access_key))
-        .json(&activation_request)
-        .send()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make chat request: {}", parts[0])
-                } else {
-                    format!("Failed to make chat request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make chat request: {}", error_msg)
-            }
-        })?;
-    
-    let activation_response: ActivationResponse = response
-        .json()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make chat request: {}", parts[0])
-                } else {
-                    format!("Failed to make chat request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make chat request: {}", error_msg)
-            }
-        })?;
-    
-    Ok(activation_response)
+pub fn check_license_status(app: AppHandle) -> Result<LicenseStatus, AppError> {
+    license::status(&app.state::<SecretStore>(), &LicenseVerifier::from_build(), now_secs())
 }
 
 #[tauri::command]
@@ -250,53 +119,6 @@ pub fn mask_license_key_cmd(license_key: String) -> String {
 }
 
 #[tauri::command]
-pub async fn get_checkout_url() -> Result<CheckoutResponse, String> {
-    // Get payment endpoint and API access key from environment
-    let payment_endpoint = get_payment_endpoint()?;
-    let api_access_key = get_api_access_key()?;
-    
-    // Make HTTP request to checkout endpoint with authorization header
-    let client = reqwest::Client::new();
-    let url = format!("{}/checkout", payment_endpoint);
-    
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_access_key))
-        .json(&serde_json::json!({}))
-        .send()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make chat request: {}", parts[0])
-                } else {
-                    format!("Failed to make chat request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make chat request: {}", error_msg)
-            }
-        })?;
-    
-    let checkout_response: CheckoutResponse = response
-        .json()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make chat request: {}", parts[0])
-                } else {
-                    format!("Failed to make chat request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make chat request: {}", error_msg)
-            }
-        })?;    
-    Ok(checkout_response)
-}
\ No newline at end of file
+pub async fn get_checkout_url(app: AppHandle) -> Result<CheckoutResponse, AppError> {
+    backend_client(&app)?.checkout().await
+}
@@ -1,385 +1,381 @@
-use serde::{Deserialize, Serialize};
-use std::env;
 use tauri::{AppHandle, Manager, Emitter};
-use futures_util::StreamExt;
-use std::fs;
 use std::path::PathBuf;
+use crate::backend::{self, AudioResponse, Backend, ChatTurn, Credentials};
+use crate::endpoints;
+use crate::error::AppError;
+use crate::http;
+use crate::attachments::{self, RequestImages};
+use crate::chat::{history_tokens, request_tokens, Attachment, ChatMessage, Role, ToolMessage};
+use crate::retry::RetryEvent;
+use crate::secret_store::SecretStore;
+use crate::sse::{StreamOutput, TokenUsage};
+use crate::fallback::{self, LocalFallbackSettings, ResponseSource};
+use crate::models::{self, Model};
+use crate::providers::{self, ProviderRequest};
+use crate::streams::{emit_chunk, emit_fallback, emit_tool_call, request_id_or_new, run_cancellable};
+use crate::structured::OutputSchema;
+use crate::tools;
+use crate::usage::{self, Metering, UsagePeriod, UsageRecord, UsageSummary};
+use std::time::Instant;
 
-fn get_app_endpoint() -> Result<String, String> {
-    if let Ok(endpoint) = env::var("APP_ENDPOINT") {
-        return Ok(endpoint);
-    }
-    
-    match option_env!("APP_ENDPOINT") {
-        Some(endpoint) => Ok(endpoint.to_string()),
-        None => Err("APP_ENDPOINT environment variable not set. Please ensure it's set during the build process.".to_string())
-    }
+// Let the frontend show "retrying (2/4)..." instead of a frozen spinner
+fn emit_retry(app: &AppHandle, event: &RetryEvent) {
+    let _ = app.emit("request_retry", event);
 }
 
-fn get_api_access_key() -> Result<String, String> {
-    if let Ok(key) = env::var("API_ACCESS_KEY") {
-        return Ok(key);
-    }
-    
-    match option_env!("API_ACCESS_KEY") {
-        Some(key) => Ok(key.to_string()),
-        None => Err("API_ACCESS_KEY environment variable not set. Please ensure it's set during the build process.".to_string())
-    }
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    app.path().app_data_dir()
+        .map_err(|e| AppError::Storage(format!("Failed to get app data directory: {}", e)))
 }
 
-// Secure storage functions
-fn get_secure_storage_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let app_data_dir = app.path().app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-    
-    fs::create_dir_all(&app_data_dir)
-        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
-    
-    Ok(app_data_dir.join("secure_storage.json"))
+// The active profile's backend, and the license when that backend may see it
+fn licensed_backend(app: &AppHandle) -> Result<(Backend, Option<Credentials>), AppError> {
+    let config = endpoints::backend_config(app)?;
+    let credentials = if config.sends_license {
+        Some(backend::load_credentials(&app.state::<SecretStore>())?)
+    } else {
+        None
+    };
+    Ok((Backend::new(http::client(app), config), credentials))
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-struct SecureStorage {
-    license_key: Option<String>,
-    instance_id: Option<String>,
-    selected_pluely_model: Option<String>,
-}
+// Audio API Command
+#[tauri::command]
+pub async fn transcribe_audio(
+    app: AppHandle,
+    audio_base64: String,
+) -> Result<AudioResponse, AppError> {
+    let (backend, credentials) = licensed_backend(&app)?;
+    let audio_response = backend
+        .transcribe(credentials.as_ref(), audio_base64, &|event| emit_retry(&app, event))
+        .await?;
 
-async fn get_stored_credentials(app: &AppHandle) -> Result<(String, String, Option<Model>), String> {
-    let storage_path = get_secure_storage_path(app)?;
-    
-    if !storage_path.exists() {
-        return Err("No license found. Please activate your license first.".to_string());
+    if let Some(transcription) = audio_response.transcription.as_deref() {
+        tools::record_transcript(&app, transcription);
     }
-    
-    let content = fs::read_to_string(&storage_path)
-        .map_err(|e| format!("Failed to read storage file: {}", e))?;
-    
-    let storage: SecureStorage = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse storage file: {}", e))?;
-    
-    let license_key = storage.license_key.ok_or("License key not found".to_string())?;
-    let instance_id = storage.instance_id.ok_or("Instance ID not found".to_string())?;
-
-    let selected_model: Option<Model> = storage.selected_pluely_model
-        .and_then(|json_str| serde_json::from_str(&json_str).ok());
-    
-    Ok((license_key, instance_id, selected_model))
-}
-
-// Audio API Structs
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AudioRequest {
-    audio_base64: String,  
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AudioResponse {
-    success: bool,
-    transcription: Option<String>,
-    error: Option<String>,
+    Ok(audio_response)
 }
 
-// Chat API Structs
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ChatRequest {
+// Chat API Command with Streaming
+#[tauri::command]
+pub async fn chat_stream(
+    app: AppHandle,
+    request_id: Option<String>,
     user_message: String,
     system_prompt: Option<String>,
-    image_base64: Option<serde_json::Value>, // Can be string or array
-    history: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ChatResponse {
-    success: bool,
-    message: Option<String>,
-    error: Option<String>,
-}
-
-// Model API Structs
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Model {
-    provider: String,
-    name: String,
-    id: String,
-    model: String,
-    description: String,
-    modality: String,
-    #[serde(rename = "isAvailable")]
-    is_available: bool,
-}
+    attachments: Option<Vec<Attachment>>,
+    history: Option<Vec<ChatMessage>>,
+    conversation_id: Option<String>,
+) -> Result<String, AppError> {
+    let request_id = request_id_or_new(request_id);
+    let mut history = history.unwrap_or_default();
+    let (attachments, images) = attachments::validate_turn(attachments.unwrap_or_default(), &mut history)?;
+    let turn = ChatTurn { user_message, system_prompt, attachments, history, response_format: None };
+    let started = Instant::now();
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ModelsResponse {
-    models: Vec<Model>,
-}
+    let local_fallback = app.path().app_data_dir().ok()
+        .and_then(|dir| fallback::load_settings(&dir).ok())
+        .filter(|settings| settings.enabled);
 
+    let stream = async {
+        let mut received = false;
+        let mut on_delta = |content: &str| {
+            received = true;
+            emit_chunk(&app, &request_id, content, ResponseSource::Backend);
+        };
+        let result = stream_with_tools(&app, &request_id, conversation_id.as_deref(), &turn, images, &mut on_delta).await;
 
-// Audio API Command
-#[tauri::command]
-pub async fn transcribe_audio(
-    app: AppHandle,
-    audio_base64: String,
-) -> Result<AudioResponse, String> {
-    // Get environment variables
-    let app_endpoint = get_app_endpoint()?;
-    let api_access_key = get_api_access_key()?;
-    
-    // Get stored credentials
-    let (license_key, instance_id, _) = get_stored_credentials(&app).await?;
-    
-    // Prepare audio request
-    let audio_request = AudioRequest {
-        audio_base64,
-     
-    };
-    
-    // Make HTTP request to audio endpoint
-    let client = reqwest::Client::new();
-    let url = format!("{}/api/audio", app_endpoint);
-    
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_access_key))
-        .header("license_key", &license_key)
-        .header("instance", &instance_id)
-        .json(&audio_request)
-        .send()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make audio request: {}", parts[0])
-                } else {
-                    format!("Failed to make audio request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make audio request: {}", error_msg)
-            }
-        })?;
-    
-    // Check if the response is successful
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown server error".to_string());
-        
-        // Try to parse error as JSON to get a more specific error message
-        if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_text) {
-            if let Some(error_msg) = error_json.get("error").and_then(|e| e.as_str()) {
-                return Err(format!("Server error ({}): {}", status, error_msg));
-            } else if let Some(message) = error_json.get("message").and_then(|m| m.as_str()) {
-                return Err(format!("Server error ({}): {}", status, message));
+        match (result, local_fallback) {
+            (Ok(full_response), _) => Ok((full_response, ResponseSource::Backend)),
+            // Only before the first chunk, otherwise the answer would be stitched from two models
+            (Err(e), Some(settings)) if !received && fallback::should_fall_back(&e) => {
+                emit_fallback(&app, &request_id, &e);
+                let prompt_tokens = request_tokens(turn.system_prompt.as_deref(), &turn.user_message, turn.attachments.len()) + history_tokens(&turn.history);
+                let metering = Metering::local(&settings.model, prompt_tokens);
+                let client = http::client(&app);
+                let mut on_delta = |content: &str| emit_chunk(&app, &request_id, content, ResponseSource::LocalFallback);
+                let full_response = fallback::stream_local(
+                    &client,
+                    &settings,
+                    turn.user_message.clone(),
+                    turn.system_prompt.clone(),
+                    turn.attachments.clone(),
+                    turn.history.clone(),
+                    &mut on_delta,
+                )
+                .await?;
+                let record = metering.finish(&request_id, conversation_id, ResponseSource::LocalFallback, &full_response, TokenUsage::default(), started.elapsed());
+                record_usage(&app, record);
+                Ok((full_response, ResponseSource::LocalFallback))
             }
+            (Err(e), _) => Err(e),
         }
-        
-        return Err(format!("Server error ({}): {}", status, error_text));
-    }
-    
-    let audio_response: AudioResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse audio response: {}", e))?;
-    
-    Ok(audio_response)
+    };
+
+    run_cancellable(&app, &request_id, stream).await
 }
 
-// Chat API Command with Streaming
+// Chat API Command for JSON answers (meeting action items, interview scorecards...). The answer
+// is validated against `schema`; an invalid one gets a single repair round before giving up.
+// Chunks aren't emitted, `chat_stream_complete` carries the validated JSON.
 #[tauri::command]
-pub async fn chat_stream(
+pub async fn chat_structured(
     app: AppHandle,
+    request_id: Option<String>,
+    provider_id: Option<String>,
     user_message: String,
     system_prompt: Option<String>,
-    image_base64: Option<serde_json::Value>,
-    history: Option<String>,
-) -> Result<String, String> {
-    // Get environment variables
-    let app_endpoint = get_app_endpoint()?;
-    let api_access_key = get_api_access_key()?;
-    
-    // Get stored credentials
-    let (license_key, instance_id, selected_model) = get_stored_credentials(&app).await?;
-    let (provider, model) = selected_model.as_ref().map_or((None, None), |m| (Some(m.provider.clone()), Some(m.model.clone())));
-   
-    // Prepare chat request
-    let chat_request = ChatRequest {
+    attachments: Option<Vec<Attachment>>,
+    history: Option<Vec<ChatMessage>>,
+    conversation_id: Option<String>,
+    schema: serde_json::Value,
+    schema_name: Option<String>,
+) -> Result<serde_json::Value, AppError> {
+    let request_id = request_id_or_new(request_id);
+    let mut history = history.unwrap_or_default();
+    let (attachments, _) = attachments::validate_turn(attachments.unwrap_or_default(), &mut history)?;
+    let schema = OutputSchema::new(schema_name, schema)?;
+    let system_prompt = match system_prompt {
+        Some(prompt) => format!("{}\n\n{}", prompt, schema.instruction()),
+        None => schema.instruction(),
+    };
+    let turn = ChatTurn {
         user_message,
-        system_prompt,
-        image_base64,
-        history
+        system_prompt: Some(system_prompt),
+        attachments,
+        history,
+        response_format: Some(schema.response_format()),
     };
-    
-    // Make HTTP request to chat endpoint with streaming
-    let client = reqwest::Client::new();
-    let url = format!("{}/api/chat?stream=true", app_endpoint);
-    
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_access_key))
-        .header("license_key", &license_key)
-        .header("instance", &instance_id)
-        .header("provider", &provider.unwrap_or("None".to_string()))
-        .header("model", &model.unwrap_or("None".to_string()))
-        .json(&chat_request)
-        .send()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make chat request: {}", parts[0])
-                } else {
-                    format!("Failed to make chat request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make chat request: {}", error_msg)
-            }
-        })?;
-    
-    // Check if the response is successful
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown server error".to_string());
-        
-        // Try to parse error as JSON to get a more specific error message
-        if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_text) {
-            if let Some(error_msg) = error_json.get("error").and_then(|e| e.as_str()) {
-                return Err(format!("Server error ({}): {}", status, error_msg));
-            } else if let Some(message) = error_json.get("message").and_then(|m| m.as_str()) {
-                return Err(format!("Server error ({}): {}", status, message));
-            }
+
+    let (conversation_id, provider_id) = (conversation_id.as_deref(), provider_id.as_deref());
+    let source = if provider_id.is_some() { ResponseSource::Provider } else { ResponseSource::Backend };
+
+    let stream = async {
+        let answer = complete_structured(&app, &request_id, conversation_id, provider_id, &turn).await?;
+        let errors = match schema.validate(&answer) {
+            Ok(value) => return Ok((value.to_string(), source)),
+            Err(errors) => errors,
+        };
+
+        // The model only needs to fix the format, so the images aren't sent again
+        let mut history = turn.history.clone();
+        history.push(ChatMessage { role: Role::User, content: turn.user_message.clone(), attachments: Vec::new() });
+        history.push(ChatMessage { role: Role::Assistant, content: answer, attachments: Vec::new() });
+        let repair = ChatTurn {
+            user_message: schema.repair_prompt(&errors),
+            system_prompt: turn.system_prompt.clone(),
+            attachments: Vec::new(),
+            history,
+            response_format: turn.response_format.clone(),
+        };
+
+        let answer = complete_structured(&app, &request_id, conversation_id, provider_id, &repair).await?;
+        match schema.validate(&answer) {
+            Ok(value) => Ok((value.to_string(), source)),
+            Err(errors) => Err(AppError::InvalidOutput(format!(
+                "The answer did not match the requested format: {}",
+                errors.join("; ")
+            ))),
         }
-        
-        return Err(format!("Server error ({}): {}", status, error_text));
+    };
+
+    let json = run_cancellable(&app, &request_id, stream).await?;
+    serde_json::from_str(&json).map_err(|e| AppError::from(e).context("Failed to parse structured answer"))
+}
+
+// One request without tools, returning the whole answer
+async fn complete_turn(
+    app: &AppHandle,
+    request_id: &str,
+    conversation_id: Option<&str>,
+    turn: &ChatTurn,
+) -> Result<String, AppError> {
+    let started = Instant::now();
+    let (output, metering) = stream_chat_response(app, turn, &[], &[], &mut |_| {}).await?;
+    record_usage(app, metering.finish(
+        request_id,
+        conversation_id.map(str::to_string),
+        ResponseSource::Backend,
+        &output.text,
+        output.usage,
+        started.elapsed(),
+    ));
+    Ok(output.text)
+}
+
+// Structured requests go to the direct provider saved as `provider_id`, the backend otherwise
+async fn complete_structured(
+    app: &AppHandle,
+    request_id: &str,
+    conversation_id: Option<&str>,
+    provider_id: Option<&str>,
+    turn: &ChatTurn,
+) -> Result<String, AppError> {
+    match provider_id {
+        Some(provider_id) => complete_provider_turn(app, provider_id, turn).await,
+        None => complete_turn(app, request_id, conversation_id, turn).await,
     }
-    
-    // Handle streaming response
-    let mut stream = response.bytes_stream();
+}
+
+// One request to a direct provider, returning the whole answer
+async fn complete_provider_turn(app: &AppHandle, provider_id: &str, turn: &ChatTurn) -> Result<String, AppError> {
+    let dir = app_data_dir(app)?;
+    let provider = providers::provider_config(&dir, provider_id)?;
+    let api_key = providers::api_key_for(&dir, &app.state::<SecretStore>(), &provider.id, &provider.base_url)?;
+    let mut request = ProviderRequest::new(
+        &provider,
+        turn.user_message.clone(),
+        turn.system_prompt.clone(),
+        turn.attachments.clone(),
+        turn.history.clone(),
+    );
+    if let Some(response_format) = &turn.response_format {
+        request = request.with_response_format(response_format.clone());
+    }
+    providers::stream_chat(&http::client(app), &provider, api_key.as_deref(), &request, &mut |_| {}).await
+}
+
+// Streams the answer, running the tools the model asks for and sending their results back
+// until it answers in text. The last round offers no tools, so the loop always ends. Tool
+// images count against what the turn left of the request's image budget. All rounds are
+// recorded as one usage record, also when a later one fails.
+async fn stream_with_tools(
+    app: &AppHandle,
+    request_id: &str,
+    conversation_id: Option<&str>,
+    turn: &ChatTurn,
+    mut images: RequestImages,
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<String, AppError> {
+    let settings = tools::tool_settings(app);
+    let model = selected_model(app)?;
+    let definitions = settings.definitions(model.as_ref().is_none_or(Model::accepts_images));
+    let mut tool_messages = Vec::new();
     let mut full_response = String::new();
-    let mut buffer = String::new();
-    
-    while let Some(chunk) = stream.next().await {
-        match chunk {
-            Ok(bytes) => {
-                let chunk_str = String::from_utf8_lossy(&bytes);
-                buffer.push_str(&chunk_str);
-                
-                // Process complete lines
-                let lines: Vec<&str> = buffer.split('\n').collect();
-                let incomplete_line = lines.last().unwrap_or(&"").to_string();
-                
-                for line in &lines[..lines.len()-1] { // Process all but the last (potentially incomplete) line
-                    let trimmed_line = line.trim();
-                    
-                    if trimmed_line.starts_with("data: ") {
-                        let json_str = trimmed_line.strip_prefix("data: ").unwrap_or("");
-                        
-                        if json_str == "[DONE]" {
-                            break;
-                        }
-                        
-                        if !json_str.is_empty() {
-                            // Try to parse the JSON and extract content
-                            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str) {
-                                if let Some(choices) = parsed.get("choices").and_then(|c| c.as_array()) {
-                                    if let Some(first_choice) = choices.first() {
-                                        if let Some(delta) = first_choice.get("delta") {
-                                            if let Some(content) = delta.get("content").and_then(|c| c.as_str()) {
-                                                full_response.push_str(content);
-                                                // Emit just the content to frontend
-                                                let _ = app.emit("chat_stream_chunk", content);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                
-                // Update buffer with incomplete line
-                buffer = incomplete_line;
+    let mut usage: Option<UsageRecord> = None;
+
+    let result = async {
+        for round in 0..=tools::MAX_TOOL_ROUNDS {
+            let offered = if round < tools::MAX_TOOL_ROUNDS { definitions.as_slice() } else { &[] };
+            let round_started = Instant::now();
+            let (output, metering) = stream_chat_response(app, turn, offered, &tool_messages, on_delta).await?;
+            let record = metering.finish(
+                request_id,
+                conversation_id.map(str::to_string),
+                ResponseSource::Backend,
+                &output.text,
+                output.usage,
+                round_started.elapsed(),
+            );
+            match usage.as_mut() {
+                Some(total) => total.add_round(record),
+                None => usage = Some(record),
+            }
+            full_response.push_str(&output.text);
+
+            if output.tool_calls.is_empty() || offered.is_empty() {
+                break;
+            }
+
+            for call in &output.tool_calls {
+                emit_tool_call(app, request_id, &call.function.name);
             }
-            Err(e) => {
-                return Err(format!("Stream error: {}", e));
+            let mut results = Vec::with_capacity(output.tool_calls.len());
+            for call in &output.tool_calls {
+                let mut result = tools::run_tool(app, call, &settings).await;
+                images.validate_tool_result(&mut result);
+                results.push(result);
             }
+            tool_messages.push(ToolMessage::Assistant { content: output.text, tool_calls: output.tool_calls });
+            tool_messages.extend(results);
         }
+        Ok::<(), AppError>(())
+    }
+    .await;
+
+    if let Some(record) = usage {
+        record_usage(app, record);
     }
-    
-    // Emit completion event
-    let _ = app.emit("chat_stream_complete", &full_response);
-    
-    Ok(full_response)
+    result.map(|()| full_response)
 }
 
-// Models API Command
+async fn stream_chat_response(
+    app: &AppHandle,
+    turn: &ChatTurn,
+    tools: &[serde_json::Value],
+    tool_messages: &[ToolMessage],
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<(StreamOutput, Metering), AppError> {
+    let model = selected_model(app)?;
+    let (request, metering) = turn.request(model.as_ref(), tools, tool_messages)?;
+    let (backend, credentials) = licensed_backend(app)?;
+    let output = backend
+        .stream_chat(credentials.as_ref(), model.as_ref(), &request, &|event| emit_retry(app, event), on_delta)
+        .await?;
+    Ok((output, metering))
+}
+
+// The catalogue's copy of the model picked in settings
+fn selected_model(app: &AppHandle) -> Result<Option<Model>, AppError> {
+    let selected = backend::load_selected_model(&app.state::<SecretStore>())?;
+    Ok(backend::resolve_model(&app_data_dir(app)?, selected))
+}
+
+// Accounting must never fail the chat itself
+fn record_usage(app: &AppHandle, record: UsageRecord) {
+    let result = app_data_dir(app)
+        .and_then(|dir| usage::append_record(&dir, &record));
+    if let Err(e) = result {
+        eprintln!("Failed to record usage: {}", e);
+    }
+}
+
+// Models API Command. Serves the on-disk catalogue while it is fresh and falls back to a
+// stale copy when the backend can't be reached, so the model picker also works offline.
 #[tauri::command]
-pub async fn fetch_models() -> Result<Vec<Model>, String> {
-    // Get environment variables
-    let app_endpoint = get_app_endpoint()?;
-    let api_access_key = get_api_access_key()?;
-    
-    // Make HTTP request to models endpoint
-    let client = reqwest::Client::new();
-    let url = format!("{}/api/models", app_endpoint);
-    
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_access_key))
-        .send()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("{}", e);
-            if error_msg.contains("url (") {
-                // Remove the URL part from the error message
-                let parts: Vec<&str> = error_msg.split(" for url (").collect();
-                if parts.len() > 1 {
-                    format!("Failed to make models request: {}", parts[0])
-                } else {
-                    format!("Failed to make models request: {}", error_msg)
-                }
-            } else {
-                format!("Failed to make models request: {}", error_msg)
-            }
-        })?;
-        
-    // Check if the response is successful
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown server error".to_string());
-        
-        // Try to parse error as JSON to get a more specific error message
-        if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_text) {
-            if let Some(error_msg) = error_json.get("error").and_then(|e| e.as_str()) {
-                return Err(format!("Server error ({}): {}", status, error_msg));
-            } else if let Some(message) = error_json.get("message").and_then(|m| m.as_str()) {
-                return Err(format!("Server error ({}): {}", status, message));
+pub async fn fetch_models(app: AppHandle, force_refresh: Option<bool>) -> Result<Vec<Model>, AppError> {
+    let app_data_dir = app_data_dir(&app)?;
+    let cached = models::load_cache(&app_data_dir);
+
+    let fresh = cached.as_ref().filter(|c| !force_refresh.unwrap_or(false) && c.is_fresh(models::now_secs()));
+    if let Some(cache) = fresh {
+        return Ok(cache.models.clone());
+    }
+
+    let backend = Backend::new(http::client(&app), endpoints::backend_config(&app)?);
+    let refreshed = backend.refresh_models(cached.as_ref(), &|event| emit_retry(&app, event)).await;
+
+    match (refreshed, cached) {
+        (Ok(cache), _) => {
+            if let Err(e) = models::save_cache(&app_data_dir, &cache) {
+                eprintln!("Failed to save model cache: {}", e);
             }
+            Ok(cache.models)
+        }
+        (Err(e), Some(stale)) if fallback::should_fall_back(&e) => {
+            eprintln!("Using cached models, refresh failed: {}", e);
+            Ok(stale.models)
         }
-        
-        return Err(format!("Server error ({}): {}", status, error_text));
+        (Err(e), _) => Err(e),
     }
-    
-    let models_response: ModelsResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse models response: {}", e))?;
-        
-    Ok(models_response.models)
 }
 
-// Helper command to check if license is available
 #[tauri::command]
-pub async fn check_license_status(app: AppHandle) -> Result<bool, String> {
-    match get_stored_credentials(&app).await {
-        Ok(_) => Ok(true),
-        Err(_) => Ok(false),
-    }
+pub fn get_local_fallback_settings(app: AppHandle) -> Result<LocalFallbackSettings, AppError> {
+    let app_data_dir = app_data_dir(&app)?;
+    fallback::load_settings(&app_data_dir)
+}
+
+#[tauri::command]
+pub fn set_local_fallback_settings(app: AppHandle, settings: LocalFallbackSettings) -> Result<(), AppError> {
+    let app_data_dir = app_data_dir(&app)?;
+    fallback::save_settings(&app_data_dir, &settings)
+}
+
+#[tauri::command]
+pub fn get_usage_summary(app: AppHandle, period: UsagePeriod) -> Result<UsageSummary, AppError> {
+    let app_data_dir = app_data_dir(&app)?;
+    let records = usage::load_records(&app_data_dir)?;
+    Ok(usage::summarize(&records, period, models::now_secs()))
 }
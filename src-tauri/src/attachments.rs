@@ -0,0 +1,191 @@
+// Checks images before they leave the machine: decodable base64, a format every provider
+// accepts, and size limits well under what the backend and providers reject. Also fills in
+// the real format and dimensions, since the webview only knows what the file name claims.
+use base64::Engine;
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder, ImageFormat, ImageReader};
+use std::io::Cursor;
+
+use crate::chat::{Attachment, AttachmentSource, ChatMessage, ToolMessage};
+use crate::error::AppError;
+
+/// Images per message
+pub const MAX_ATTACHMENTS: usize = 6;
+/// Images in one request, counting history and tool results
+pub const MAX_IMAGES: usize = 20;
+/// Decoded bytes per image
+pub const MAX_ATTACHMENT_BYTES: usize = 10 * 1024 * 1024;
+/// Decoded bytes of all images in one request
+pub const MAX_TOTAL_BYTES: usize = 25 * 1024 * 1024;
+const MAX_CAPTION_CHARS: usize = 200;
+// Formats accepted by OpenAI, Anthropic and Gemini alike
+const SUPPORTED_FORMATS: [ImageFormat; 4] = [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP, ImageFormat::Gif];
+
+/// Validates and normalizes the images of a message sent on its own
+pub fn validate(attachments: Vec<Attachment>) -> Result<Vec<Attachment>, AppError> {
+    RequestImages::default().validate(attachments)
+}
+
+/// Validates the new message's images, then the history's against what is left of the request's
+/// budget. The returned budget is for the tool results still to come.
+pub fn validate_turn(
+    attachments: Vec<Attachment>,
+    history: &mut [ChatMessage],
+) -> Result<(Vec<Attachment>, RequestImages), AppError> {
+    let mut images = RequestImages::default();
+    let attachments = images.validate(attachments)?;
+    images.validate_history(history);
+    Ok((attachments, images))
+}
+
+/// Images already in one outgoing request, so the limits hold across the new message, the
+/// history and tool results rather than per message
+#[derive(Debug, Default)]
+pub struct RequestImages {
+    count: usize,
+    bytes: usize,
+}
+
+impl RequestImages {
+    /// Validates the images of the new message. Errors name the offending attachment by its
+    /// 1-based position so the user can tell which one to remove.
+    pub fn validate(&mut self, attachments: Vec<Attachment>) -> Result<Vec<Attachment>, AppError> {
+        if attachments.len() > MAX_ATTACHMENTS {
+            return Err(AppError::Unsupported(format!(
+                "Too many images: {} attached, at most {} per message",
+                attachments.len(),
+                MAX_ATTACHMENTS
+            )));
+        }
+
+        let mut validated = Vec::with_capacity(attachments.len());
+        for (index, attachment) in attachments.into_iter().enumerate() {
+            let (attachment, size) = validate_one(attachment)
+                .map_err(|reason| AppError::Unsupported(format!("Image {} can't be sent: {}", index + 1, reason)))?;
+            self.admit(size).map_err(AppError::Unsupported)?;
+            validated.push(attachment);
+        }
+        Ok(validated)
+    }
+
+    /// Validates history images newest first. Ones that are invalid or no longer fit are dropped
+    /// with their text kept, like turns trimmed from a long history.
+    pub fn validate_history(&mut self, history: &mut [ChatMessage]) {
+        for message in history.iter_mut().rev() {
+            let attachments = std::mem::take(&mut message.attachments);
+            message.attachments = attachments
+                .into_iter()
+                .filter_map(|attachment| self.admit_quietly(attachment))
+                .collect();
+        }
+    }
+
+    /// Validates the images a tool returned. When they can't be sent the model is told so in
+    /// the result instead, since failing the whole answer over a screenshot would be worse.
+    pub fn validate_tool_result(&mut self, message: &mut ToolMessage) {
+        let ToolMessage::Tool { content, attachments, .. } = message else {
+            return;
+        };
+        let mut validated = Vec::with_capacity(attachments.len());
+        for attachment in std::mem::take(attachments) {
+            match validate_one(attachment).and_then(|(attachment, size)| self.admit(size).map(|_| attachment)) {
+                Ok(attachment) => validated.push(attachment),
+                Err(reason) => {
+                    content.push_str(&format!("\n(An image could not be attached: {})", reason));
+                    break;
+                }
+            }
+        }
+        *attachments = validated;
+    }
+
+    fn admit_quietly(&mut self, attachment: Attachment) -> Option<Attachment> {
+        let result = validate_one(attachment).and_then(|(attachment, size)| self.admit(size).map(|_| attachment));
+        match result {
+            Ok(attachment) => Some(attachment),
+            Err(reason) => {
+                eprintln!("Dropping an image from the history: {}", reason);
+                None
+            }
+        }
+    }
+
+    fn admit(&mut self, size: usize) -> Result<(), String> {
+        if self.count >= MAX_IMAGES {
+            return Err(format!("Too many images, at most {} per request including earlier messages", MAX_IMAGES));
+        }
+        if self.bytes + size > MAX_TOTAL_BYTES {
+            return Err(format!(
+                "Images are too large together, at most {} MB per request",
+                MAX_TOTAL_BYTES / (1024 * 1024)
+            ));
+        }
+        self.count += 1;
+        self.bytes += size;
+        Ok(())
+    }
+}
+
+fn validate_one(mut attachment: Attachment) -> Result<(Attachment, usize), String> {
+    // The webview often hands over data URLs
+    if attachment.data.starts_with("data:") {
+        if let Some((_, data)) = attachment.data.split_once(";base64,") {
+            attachment.data = data.to_string();
+        }
+    }
+
+    // Base64 is 4 chars per 3 bytes; reject before decoding a huge string
+    if attachment.data.len() / 4 * 3 > MAX_ATTACHMENT_BYTES + 3 {
+        return Err(too_large());
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(attachment.data.trim())
+        .map_err(|_| "it is not valid base64".to_string())?;
+    if bytes.is_empty() {
+        return Err("it is empty".to_string());
+    }
+    if bytes.len() > MAX_ATTACHMENT_BYTES {
+        return Err(too_large());
+    }
+
+    let format = image::guess_format(&bytes)
+        .ok()
+        .filter(|f| SUPPORTED_FORMATS.contains(f))
+        .ok_or_else(|| "only PNG, JPEG, WebP and GIF images are supported".to_string())?;
+    let (width, height) = ImageReader::with_format(Cursor::new(&bytes), format)
+        .into_dimensions()
+        .map_err(|e| format!("it could not be read ({})", e))?;
+
+    attachment.mime_type = format.to_mime_type().to_string();
+    attachment.width = Some(width);
+    attachment.height = Some(height);
+    attachment.caption = attachment
+        .caption
+        .map(|c| c.trim().chars().take(MAX_CAPTION_CHARS).collect::<String>())
+        .filter(|c| !c.is_empty());
+    Ok((attachment, bytes.len()))
+}
+
+fn too_large() -> String {
+    format!("it is larger than {} MB", MAX_ATTACHMENT_BYTES / (1024 * 1024))
+}
+
+/// PNG attachment from raw RGBA pixels, as returned by screen capture
+pub fn encode_png(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    source: AttachmentSource,
+    caption: Option<String>,
+) -> Result<Attachment, String> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(rgba, width, height, ColorType::Rgba8.into())
+        .map_err(|e| format!("Failed to encode to PNG: {}", e))?;
+
+    let mut attachment = Attachment::new("image/png", base64::engine::general_purpose::STANDARD.encode(png), source);
+    attachment.width = Some(width);
+    attachment.height = Some(height);
+    attachment.caption = caption;
+    Ok(attachment)
+}
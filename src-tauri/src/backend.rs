@@ -0,0 +1,522 @@
+// Client for the Pluely backend (chat, transcription, models) and the payment service
+// (activation, instances, checkout). Nothing here needs an AppHandle: the commands in api.rs and
+// activate.rs resolve the app data directory and events, then call into this module, so the
+// same code runs headless against a mock server in the integration tests.
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::chat::{fit_history, history_tokens, request_tokens, tool_message_tokens, Attachment, ChatMessage, ToolMessage, DEFAULT_CONTEXT_WINDOW};
+use crate::error::AppError;
+use crate::models::{self, Model, ModelCache};
+use crate::retry::{send_with_retry, RetryEvent, RetryPolicy};
+use crate::secret_store::{
+    SecretStore, INSTANCE_ID, LICENSE, LICENSE_KEY, LICENSE_REVOKED, LICENSE_TOKEN, SELECTED_MODEL,
+};
+use crate::sse::{read_stream_with_usage, OpenAiExtractor, StreamOutput};
+use crate::trace;
+use crate::usage::Metering;
+
+/// How requests show the backend they come from a Pluely client
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <access key>`
+    #[default]
+    Bearer,
+    /// The access key as is, in a custom header such as `X-Api-Key`
+    Header { name: String },
+    /// No access key, for backends behind a VPN or an authenticating proxy
+    None,
+}
+
+/// Endpoints and access key, baked in at build time or from an endpoint profile
+#[derive(Debug, Clone, Default)]
+pub struct BackendConfig {
+    pub app_endpoint: Option<String>,
+    pub payment_endpoint: Option<String>,
+    pub api_access_key: Option<String>,
+    pub auth: AuthScheme,
+    /// Whether the license saved on this device may be sent here. It was issued through the
+    /// built-in profile's payment service, so other profiles never see it.
+    pub sends_license: bool,
+}
+
+impl BackendConfig {
+    /// Runtime environment first, then the values set during the build
+    pub fn from_env() -> Self {
+        Self {
+            app_endpoint: env::var("APP_ENDPOINT").ok().or(option_env!("APP_ENDPOINT").map(str::to_string)),
+            payment_endpoint: env::var("PAYMENT_ENDPOINT").ok().or(option_env!("PAYMENT_ENDPOINT").map(str::to_string)),
+            api_access_key: env::var("API_ACCESS_KEY").ok().or(option_env!("API_ACCESS_KEY").map(str::to_string)),
+            auth: AuthScheme::Bearer,
+            sends_license: true,
+        }
+    }
+
+    /// Header name and value carrying the access key, `None` when the scheme sends none
+    pub fn auth_header(&self) -> Result<Option<(String, String)>, AppError> {
+        Ok(match &self.auth {
+            AuthScheme::Bearer => Some(("Authorization".to_string(), format!("Bearer {}", self.api_access_key()?))),
+            AuthScheme::Header { name } => Some((name.clone(), self.api_access_key()?.to_string())),
+            AuthScheme::None => None,
+        })
+    }
+
+    fn app_endpoint(&self) -> Result<&str, AppError> {
+        required(&self.app_endpoint, "APP_ENDPOINT")
+    }
+
+    fn payment_endpoint(&self) -> Result<&str, AppError> {
+        required(&self.payment_endpoint, "PAYMENT_ENDPOINT")
+    }
+
+    fn api_access_key(&self) -> Result<&str, AppError> {
+        required(&self.api_access_key, "API_ACCESS_KEY")
+    }
+}
+
+/// Adds the header from `BackendConfig::auth_header`
+pub fn authorize(request: reqwest::RequestBuilder, auth: Option<&(String, String)>) -> reqwest::RequestBuilder {
+    match auth {
+        Some((name, value)) => request.header(name.as_str(), value.as_str()),
+        None => request,
+    }
+}
+
+fn required<'a>(value: &'a Option<String>, name: &str) -> Result<&'a str, AppError> {
+    value.as_deref().ok_or_else(|| {
+        AppError::Config(format!(
+            "{} environment variable not set. Please ensure it's set during the build process.",
+            name
+        ))
+    })
+}
+
+/// The activated license, sent with every backend request
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub license_key: String,
+    pub instance_id: String,
+    pub selected_model: Option<Model>,
+}
+
+/// Reads the activated license from the secret store
+pub fn load_credentials(store: &SecretStore) -> Result<Credentials, AppError> {
+    let license_key = store.get(LICENSE, LICENSE_KEY)?
+        .ok_or(AppError::Auth("No license found. Please activate your license first.".to_string()))?;
+    let instance_id = store.get(LICENSE, INSTANCE_ID)?
+        .ok_or(AppError::Auth("Instance ID not found".to_string()))?;
+    let selected_model = load_selected_model(store)?;
+
+    Ok(Credentials { license_key, instance_id, selected_model })
+}
+
+/// The model picked in settings, also set without a license when using a self-hosted profile
+pub fn load_selected_model(store: &SecretStore) -> Result<Option<Model>, AppError> {
+    Ok(store.get(LICENSE, SELECTED_MODEL)?.and_then(|json_str| serde_json::from_str(&json_str).ok()))
+}
+
+/// Forgets the license on this device, after its instance was deactivated
+pub fn clear_credentials(store: &SecretStore) -> Result<(), AppError> {
+    for key in [LICENSE_KEY, INSTANCE_ID, SELECTED_MODEL, LICENSE_TOKEN, LICENSE_REVOKED] {
+        store.delete(LICENSE, key)?;
+    }
+    Ok(())
+}
+
+/// The stored selection may predate capability metadata; prefer the catalogue's copy
+pub fn resolve_model(app_data_dir: &Path, selected: Option<Model>) -> Option<Model> {
+    let cached = models::load_cache(app_data_dir)
+        .zip(selected.as_ref())
+        .and_then(|(cache, selected)| cache.find(&selected.id).cloned());
+    cached.or(selected)
+}
+
+// Audio API Structs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AudioRequest {
+    pub audio_base64: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AudioResponse {
+    pub success: bool,
+    pub transcription: Option<String>,
+    pub error: Option<String>,
+}
+
+// Chat API Structs. Backends that predate typed history and attachments read `history` as a
+// JSON string and `image_base64`, so both are still sent alongside `messages` and `attachments`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatRequest {
+    pub user_message: String,
+    pub system_prompt: Option<String>,
+    /// Validated images for the new message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// Earlier turns, oldest first
+    #[serde(default)]
+    pub messages: Vec<ChatMessage>,
+    /// `messages` as text-only JSON, for older backends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<String>,
+    /// The data of `attachments`: a string for one image, an array for several; for older backends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_base64: Option<serde_json::Value>,
+    /// Function definitions the model may call
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<serde_json::Value>,
+    /// Tool calls made so far for this message and their results, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_messages: Vec<ToolMessage>,
+    /// Asks for JSON matching a schema, see `chat_structured`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+}
+
+/// What the user sent; reused unchanged for every tool round
+#[derive(Debug, Clone, Default)]
+pub struct ChatTurn {
+    pub user_message: String,
+    pub system_prompt: Option<String>,
+    pub attachments: Vec<Attachment>,
+    pub history: Vec<ChatMessage>,
+    pub response_format: Option<serde_json::Value>,
+}
+
+impl ChatTurn {
+    /// Request for one round, with the oldest turns trimmed so it fits the model's context
+    /// window. Fails before anything is sent when the model can't take an image in it, whether
+    /// attached to the new message, an earlier one or a tool result.
+    pub fn request(
+        &self,
+        model: Option<&Model>,
+        tools: &[serde_json::Value],
+        tool_messages: &[ToolMessage],
+    ) -> Result<(ChatRequest, Metering), AppError> {
+        let image_count = self.attachments.len();
+        let context_window = model.and_then(|m| m.context_window).unwrap_or(DEFAULT_CONTEXT_WINDOW);
+        let fixed_tokens = request_tokens(self.system_prompt.as_deref(), &self.user_message, image_count)
+            + tool_message_tokens(tool_messages);
+        let history = fit_history(self.history.clone(), context_window, fixed_tokens);
+
+        if let Some(model) = model.filter(|m| !m.accepts_images()) {
+            let earlier_images = history.iter().map(|m| m.attachments.len()).sum::<usize>()
+                + tool_messages
+                    .iter()
+                    .map(|m| match m {
+                        ToolMessage::Tool { attachments, .. } => attachments.len(),
+                        ToolMessage::Assistant { .. } => 0,
+                    })
+                    .sum::<usize>();
+            if image_count > 0 {
+                return Err(AppError::Unsupported(format!(
+                    "{} does not accept images. Choose a vision model or send text only.",
+                    model.name
+                )));
+            }
+            if earlier_images > 0 {
+                return Err(AppError::Unsupported(format!(
+                    "{} does not accept images, and earlier messages in this conversation have some. \
+                     Choose a vision model or start a new conversation.",
+                    model.name
+                )));
+            }
+        }
+
+        let metering = Metering::for_model(model, fixed_tokens + history_tokens(&history));
+
+        let request = ChatRequest {
+            user_message: self.user_message.clone(),
+            system_prompt: self.system_prompt.clone(),
+            attachments: self.attachments.clone(),
+            history: legacy_history(&history),
+            image_base64: legacy_images(&self.attachments),
+            messages: history,
+            tools: tools.to_vec(),
+            tool_messages: tool_messages.to_vec(),
+            response_format: self.response_format.clone(),
+        };
+        Ok((request, metering))
+    }
+}
+
+// The format the webview used to send: `[{"role", "content": [{"type": "text", "text"}]}]`
+fn legacy_history(history: &[ChatMessage]) -> Option<String> {
+    if history.is_empty() {
+        return None;
+    }
+    let messages: Vec<serde_json::Value> = history
+        .iter()
+        .map(|m| serde_json::json!({ "role": m.role, "content": [{ "type": "text", "text": m.content }] }))
+        .collect();
+    Some(serde_json::Value::Array(messages).to_string())
+}
+
+fn legacy_images(attachments: &[Attachment]) -> Option<serde_json::Value> {
+    match attachments {
+        [] => None,
+        [only] => Some(serde_json::Value::String(only.data.clone())),
+        several => Some(several.iter().map(|a| a.data.clone()).collect()),
+    }
+}
+
+// Payment API Structs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivationRequest {
+    pub license_key: String,
+    pub instance_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivationResponse {
+    pub activated: bool,
+    pub error: Option<String>,
+    pub license_key: Option<String>,
+    pub instance: Option<InstanceInfo>,
+    /// Signed license token, see `license::LicenseVerifier`
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstanceInfo {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+/// Names one activation of a license, for deactivation and validation
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstanceRequest {
+    pub license_key: String,
+    pub instance_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeactivationResponse {
+    pub deactivated: bool,
+    pub error: Option<String>,
+}
+
+/// State of a license as the payment service sees it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LicenseInfo {
+    /// `active`, `inactive`, `expired` or `disabled`
+    pub status: String,
+    /// Seats the license allows, `None` for unlimited
+    pub activation_limit: Option<u32>,
+    /// Seats in use
+    pub activation_usage: u32,
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidationResponse {
+    pub valid: bool,
+    pub error: Option<String>,
+    pub license: Option<LicenseInfo>,
+    /// The validated instance, `None` if it was deactivated
+    pub instance: Option<InstanceInfo>,
+    /// A fresh license token, restarting the offline grace period
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstancesRequest {
+    pub license_key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstancesResponse {
+    #[serde(default)]
+    pub instances: Vec<InstanceInfo>,
+    pub license: Option<LicenseInfo>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckoutResponse {
+    pub success: Option<bool>,
+    pub checkout_url: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Outcome of `Backend::check_health`
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub latency_ms: u64,
+    /// Models the backend offers
+    pub models: usize,
+}
+
+pub struct Backend {
+    client: reqwest::Client,
+    config: BackendConfig,
+    retry: RetryPolicy,
+}
+
+impl Backend {
+    pub fn new(client: reqwest::Client, config: BackendConfig) -> Self {
+        Self { client, config, retry: RetryPolicy::default() }
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub async fn transcribe(
+        &self,
+        credentials: Option<&Credentials>,
+        audio_base64: String,
+        on_retry: &(dyn Fn(&RetryEvent) + Send + Sync),
+    ) -> Result<AudioResponse, AppError> {
+        let url = format!("{}/api/audio", self.config.app_endpoint()?);
+        let auth = self.config.auth_header()?;
+        let audio_request = AudioRequest { audio_base64 };
+
+        let build_request = || {
+            let request = authorize(self.client.post(&url), auth.as_ref()).header("Content-Type", "application/json");
+            self.with_license(request, credentials).json(&audio_request)
+        };
+
+        let response = send_with_retry("transcription", &self.retry, build_request, on_retry)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to make audio request"))?;
+
+        if !response.status().is_success() {
+            return Err(AppError::from_failed_response(response).await);
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to parse audio response"))
+    }
+
+    /// Streams one chat round, passing text deltas to `on_delta` as they arrive
+    pub async fn stream_chat(
+        &self,
+        credentials: Option<&Credentials>,
+        model: Option<&Model>,
+        request: &ChatRequest,
+        on_retry: &(dyn Fn(&RetryEvent) + Send + Sync),
+        on_delta: &mut (dyn FnMut(&str) + Send),
+    ) -> Result<StreamOutput, AppError> {
+        let url = format!("{}/api/chat?stream=true", self.config.app_endpoint()?);
+        let auth = self.config.auth_header()?;
+        let provider = model.map_or("None", |m| m.provider.as_str());
+        let model = model.map_or("None", |m| m.model.as_str());
+
+        let build_request = || {
+            let builder = authorize(self.client.post(&url), auth.as_ref()).header("Content-Type", "application/json");
+            self.with_license(builder, credentials)
+                .header("provider", provider)
+                .header("model", model)
+                .json(request)
+        };
+
+        // Retries only cover getting the response started; once chunks flow, a failure is final
+        let response = send_with_retry("chat", &self.retry, build_request, on_retry)
+            .await
+            .map_err(|e| AppError::from(e).context("Failed to make chat request"))?;
+
+        if !response.status().is_success() {
+            return Err(AppError::from_failed_response(response).await);
+        }
+
+        read_stream_with_usage(response, &OpenAiExtractor, on_delta).await
+    }
+
+    // Profiles other than the built-in one get no license, even when one is passed
+    fn with_license(&self, request: reqwest::RequestBuilder, credentials: Option<&Credentials>) -> reqwest::RequestBuilder {
+        match credentials.filter(|_| self.config.sends_license) {
+            Some(credentials) => request
+                .header("license_key", &credentials.license_key)
+                .header("instance", &credentials.instance_id),
+            None => request,
+        }
+    }
+
+    /// Fetches the model catalogue, revalidating `cached` with its ETag
+    pub async fn refresh_models(
+        &self,
+        cached: Option<&ModelCache>,
+        on_retry: &(dyn Fn(&RetryEvent) + Send + Sync),
+    ) -> Result<ModelCache, AppError> {
+        let url = format!("{}/api/models", self.config.app_endpoint()?);
+        let auth = self.config.auth_header()?;
+        models::refresh_catalogue(&self.client, &url, auth.as_ref(), cached, &self.retry, on_retry).await
+    }
+
+    /// Lists the models once: a single request proves the URL, the TLS setup and the access key
+    pub async fn check_health(&self) -> Result<HealthReport, AppError> {
+        let started = Instant::now();
+        let catalogue = self.refresh_models(None, &|_| {}).await?;
+        Ok(HealthReport { latency_ms: started.elapsed().as_millis() as u64, models: catalogue.models.len() })
+    }
+
+    pub async fn activate(&self, license_key: String, instance_name: String) -> Result<ActivationResponse, AppError> {
+        let activation_request = ActivationRequest { license_key, instance_name };
+        self.post_payment("activation", "activate", &activation_request).await
+    }
+
+    /// Frees the seat used by `instance_id`, which may be another device's
+    pub async fn deactivate(&self, license_key: String, instance_id: String) -> Result<DeactivationResponse, AppError> {
+        let request = InstanceRequest { license_key, instance_id };
+        self.post_payment("deactivation", "deactivate", &request).await
+    }
+
+    pub async fn validate(&self, license_key: String, instance_id: String) -> Result<ValidationResponse, AppError> {
+        let request = InstanceRequest { license_key, instance_id };
+        self.post_payment("validation", "validate", &request).await
+    }
+
+    /// Every device the license is activated on
+    pub async fn list_instances(&self, license_key: String) -> Result<InstancesResponse, AppError> {
+        self.post_payment("instances", "instances", &InstancesRequest { license_key }).await
+    }
+
+    pub async fn checkout(&self) -> Result<CheckoutResponse, AppError> {
+        self.post_payment("checkout", "checkout", &serde_json::json!({})).await
+    }
+
+    // Payment calls aren't retried: activating twice would use up a second instance
+    async fn post_payment<T: DeserializeOwned>(
+        &self,
+        operation: &str,
+        route: &str,
+        body: &impl Serialize,
+    ) -> Result<T, AppError> {
+        let url = format!("{}/{}", self.config.payment_endpoint()?, route);
+        let request = authorize(self.client.post(&url), self.config.auth_header()?.as_ref())
+            .header("Content-Type", "application/json")
+            .json(body);
+        let response = trace::send(operation, request)
+            .await
+            .map_err(|e| AppError::from(e).context(&format!("Failed to make {} request", operation)))?;
+
+        // The payment service answers a rejected license with a 4xx and a normal body
+        // (`activated: false` and the reason), which the frontend shows as-is. A rejected
+        // access key or a server failure is an error like for every other backend call.
+        let status = response.status();
+        if status.is_server_error() || matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+            return Err(AppError::from_failed_response(response).await);
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|e| AppError::from(e).context(&format!("Failed to read {} response", operation)))?;
+        match serde_json::from_str(&body) {
+            Ok(parsed) => Ok(parsed),
+            Err(_) if !status.is_success() => Err(AppError::from_response(status, &body)),
+            Err(e) => Err(AppError::from(e).context(&format!("Failed to parse {} response", operation))),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+// Typed conversation history shared by the hosted backend and direct providers, plus
+// trimming so a long conversation still fits the selected model's context window.
+use serde::{Deserialize, Serialize};
+
+// Used when the selected model doesn't advertise its context window
+pub const DEFAULT_CONTEXT_WINDOW: u32 = 8192;
+// Tokens kept free for the model's answer
+const RESPONSE_TOKEN_RESERVE: usize = 1024;
+// Role markers and separators the chat templates add around every message
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+// Roughly what providers bill for a high-detail screenshot
+const IMAGE_TOKEN_ESTIMATE: usize = 765;
+// Length of the opening message quoted in the summary of dropped turns
+const SUMMARY_EXCERPT_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+/// Where an attached image came from
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentSource {
+    /// A whole monitor
+    Screen,
+    /// Part of a monitor selected by the user
+    Region,
+    #[default]
+    File,
+    Clipboard,
+}
+
+/// An image sent with a message; see `attachments::RequestImages` for the limits
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Attachment {
+    pub mime_type: String,
+    /// Base64 encoded content, without a `data:` prefix
+    pub data: String,
+    #[serde(default)]
+    pub source: AttachmentSource,
+    /// Pixels; filled in from the image itself during validation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Shown to the model next to the image, e.g. "Screen 2 of 2"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+}
+
+impl Attachment {
+    pub fn new(mime_type: impl Into<String>, data: impl Into<String>, source: AttachmentSource) -> Self {
+        Self { mime_type: mime_type.into(), data: data.into(), source, width: None, height: None, caption: None }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+/// A function call requested by the model, in OpenAI's wire format
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub function: FunctionCall,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FunctionCall {
+    pub name: String,
+    /// JSON-encoded arguments, exactly as the model produced them
+    pub arguments: String,
+}
+
+/// One step of a tool exchange, sent after the user message: the assistant turn that asked for
+/// tools, then one result per call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "role", rename_all = "lowercase")]
+pub enum ToolMessage {
+    Assistant {
+        content: String,
+        tool_calls: Vec<ToolCall>,
+    },
+    Tool {
+        tool_call_id: String,
+        name: String,
+        content: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        attachments: Vec<Attachment>,
+    },
+}
+
+impl ChatMessage {
+    fn system(content: String) -> Self {
+        Self { role: Role::System, content, attachments: Vec::new() }
+    }
+}
+
+/// Cheap token estimate (~4 characters per token for English text). Deliberately errs on the
+/// high side so we trim a little early rather than overflow the window.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn message_tokens(message: &ChatMessage) -> usize {
+    MESSAGE_OVERHEAD_TOKENS + estimate_tokens(&message.content) + message.attachments.len() * IMAGE_TOKEN_ESTIMATE
+}
+
+/// Drops the oldest turns of `history` until the whole request fits in `context_window`.
+/// Dropped turns are replaced by a short system note so the model knows context is missing.
+/// `fixed_tokens` is what the system prompt and the new user message already use.
+pub fn fit_history(history: Vec<ChatMessage>, context_window: u32, fixed_tokens: usize) -> Vec<ChatMessage> {
+    let budget = (context_window as usize)
+        .saturating_sub(RESPONSE_TOKEN_RESERVE)
+        .saturating_sub(fixed_tokens);
+
+    let total: usize = history.iter().map(message_tokens).sum();
+    if total <= budget {
+        return history;
+    }
+
+    // Keep the newest turns that fit, leaving room for the summary note
+    let mut kept_tokens = 0;
+    let mut first_kept = history.len();
+    for (index, message) in history.iter().enumerate().rev() {
+        let tokens = message_tokens(message);
+        if kept_tokens + tokens > budget {
+            break;
+        }
+        kept_tokens += tokens;
+        first_kept = index;
+    }
+
+    let mut kept = history;
+    let dropped: Vec<ChatMessage> = kept.drain(..first_kept).collect();
+
+    let summary = summarize_dropped(&dropped);
+    let summary_tokens = message_tokens(&summary);
+    while !kept.is_empty() && kept_tokens + summary_tokens > budget {
+        kept_tokens -= message_tokens(&kept.remove(0));
+    }
+    if kept_tokens + summary_tokens <= budget {
+        kept.insert(0, summary);
+    }
+
+    kept
+}
+
+fn summarize_dropped(dropped: &[ChatMessage]) -> ChatMessage {
+    let opening = dropped
+        .iter()
+        .find(|m| m.role == Role::User)
+        .map(|m| m.content.chars().take(SUMMARY_EXCERPT_CHARS).collect::<String>())
+        .unwrap_or_default();
+
+    let mut note = format!(
+        "{} earlier message(s) of this conversation were omitted to fit the model's context window.",
+        dropped.len()
+    );
+    if !opening.is_empty() {
+        note.push_str(&format!(" The conversation began with: \"{}\"", opening));
+    }
+
+    ChatMessage::system(note)
+}
+
+/// Estimated tokens of the history as it will be sent
+pub fn history_tokens(history: &[ChatMessage]) -> usize {
+    history.iter().map(message_tokens).sum()
+}
+
+/// Estimated tokens of the tool calls and results sent with a request
+pub fn tool_message_tokens(messages: &[ToolMessage]) -> usize {
+    messages
+        .iter()
+        .map(|message| match message {
+            ToolMessage::Assistant { content, tool_calls } => {
+                MESSAGE_OVERHEAD_TOKENS
+                    + estimate_tokens(content)
+                    + tool_calls
+                        .iter()
+                        .map(|c| estimate_tokens(&c.function.name) + estimate_tokens(&c.function.arguments))
+                        .sum::<usize>()
+            }
+            ToolMessage::Tool { content, attachments, .. } => {
+                MESSAGE_OVERHEAD_TOKENS + estimate_tokens(content) + attachments.len() * IMAGE_TOKEN_ESTIMATE
+            }
+        })
+        .sum()
+}
+
+/// Tokens used by the parts of a request that are never trimmed
+pub fn request_tokens(system_prompt: Option<&str>, user_message: &str, image_count: usize) -> usize {
+    let system = system_prompt.map(|s| MESSAGE_OVERHEAD_TOKENS + estimate_tokens(s)).unwrap_or(0);
+    system + MESSAGE_OVERHEAD_TOKENS + estimate_tokens(user_message) + image_count * IMAGE_TOKEN_ESTIMATE
+}
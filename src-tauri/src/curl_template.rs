@@ -0,0 +1,524 @@
+// Custom AI providers are described as curl commands with `{{VARIABLE}}` placeholders.
+// This parses those templates without a shell, fills in the variables (JSON-escaped in the body,
+// percent-encoded in the URL) and runs them, so API keys never have to reach the webview.
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::chat::ChatMessage;
+use crate::error::AppError;
+use crate::sse::{read_stream, DeltaExtractor, SseEvent, StreamSignal};
+use crate::trace;
+
+/// The parts of a curl command we understand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurlTemplate {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+/// Everything that can be substituted into a template
+#[derive(Debug, Clone, Default)]
+pub struct TemplateInput {
+    pub user_message: String,
+    pub system_prompt: Option<String>,
+    pub images_base64: Vec<String>,
+    pub history: Vec<ChatMessage>,
+    /// Provider variables such as `API_KEY` or `MODEL`, keys are matched case-insensitively
+    pub variables: HashMap<String, String>,
+}
+
+/// A template with every placeholder filled in, ready to send
+#[derive(Debug, Clone)]
+pub struct RenderedRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+// Flags that change nothing about the request we send
+const IGNORED_FLAGS: &[&str] = &[
+    "-s", "--silent", "-S", "--show-error", "-L", "--location", "-N", "--no-buffer",
+    "-v", "--verbose", "-i", "--include", "--compressed", "-f", "--fail",
+];
+
+// Filled in from the chat request rather than from provider settings
+const BUILT_IN_VARIABLES: &[&str] = &["TEXT", "IMAGE", "SYSTEM_PROMPT"];
+
+// Keys that hold the conversation in the body templates we've seen
+const MESSAGES_KEYS: &[&str] = &["messages", "contents", "conversation", "history"];
+
+/// Parses a curl command line. Supports line continuations, single and double quotes,
+/// `-X`, `-H`, `-d`/`--data*`, `--json`, `-u` and `--url`; anything else is rejected rather
+/// than silently dropped.
+pub fn parse_curl(curl: &str) -> Result<CurlTemplate, AppError> {
+    let tokens = tokenize(curl)?;
+    let mut tokens = tokens.into_iter();
+
+    if tokens.next().as_deref() != Some("curl") {
+        return Err(AppError::Config("Provider template must start with `curl`".to_string()));
+    }
+
+    let mut method: Option<Method> = None;
+    let mut url: Option<String> = None;
+    let mut headers = Vec::new();
+    let mut body: Option<String> = None;
+
+    while let Some(token) = tokens.next() {
+        let mut value_for = |flag: &str| {
+            tokens
+                .next()
+                .ok_or_else(|| AppError::Config(format!("Missing value for curl option {}", flag)))
+        };
+
+        match token.as_str() {
+            "-X" | "--request" => {
+                let value = value_for(&token)?;
+                method = Some(
+                    Method::from_bytes(value.to_uppercase().as_bytes())
+                        .map_err(|_| AppError::Config(format!("Invalid HTTP method: {}", value)))?,
+                );
+            }
+            "-H" | "--header" => {
+                let value = value_for(&token)?;
+                let (name, header_value) = value
+                    .split_once(':')
+                    .ok_or_else(|| AppError::Config(format!("Invalid header: {}", value)))?;
+                headers.push((name.trim().to_string(), header_value.trim().to_string()));
+            }
+            "-d" | "--data" | "--data-raw" | "--data-binary" => body = Some(value_for(&token)?),
+            "--json" => {
+                body = Some(value_for(&token)?);
+                headers.push(("Content-Type".to_string(), "application/json".to_string()));
+                headers.push(("Accept".to_string(), "application/json".to_string()));
+            }
+            "-u" | "--user" => {
+                use base64::Engine;
+                let credentials = base64::engine::general_purpose::STANDARD.encode(value_for(&token)?);
+                headers.push(("Authorization".to_string(), format!("Basic {}", credentials)));
+            }
+            "--url" => url = Some(value_for(&token)?),
+            flag if IGNORED_FLAGS.contains(&flag) => {}
+            flag if flag.starts_with('-') => {
+                return Err(AppError::Config(format!("Unsupported curl option: {}", flag)));
+            }
+            _ if url.is_none() => url = Some(token),
+            other => return Err(AppError::Config(format!("Unexpected curl argument: {}", other))),
+        }
+    }
+
+    let url = url.ok_or_else(|| AppError::Config("Provider template has no URL".to_string()))?;
+    let method = method.unwrap_or(if body.is_some() { Method::POST } else { Method::GET });
+
+    Ok(CurlTemplate { method, url, headers, body })
+}
+
+// POSIX-shell-like word splitting, without any expansion
+fn tokenize(input: &str) -> Result<Vec<String>, AppError> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // Line continuation
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(escaped) => {
+                    current.push(escaped);
+                    in_token = true;
+                }
+                None => return Err(AppError::Config("Template ends with a lone backslash".to_string())),
+            },
+            '\'' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(AppError::Config("Unterminated single quote in template".to_string())),
+                    }
+                }
+            }
+            '"' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err(AppError::Config("Unterminated double quote in template".to_string())),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(AppError::Config("Unterminated double quote in template".to_string())),
+                    }
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+
+    if in_token {
+        tokens.push(current);
+    }
+
+    Ok(tokens)
+}
+
+/// Fills in every placeholder. `{{TEXT}}` and `{{IMAGE}}` inside the messages array are expanded
+/// the same way the webview did it: history is inserted before the user message template, and
+/// the part holding `{{IMAGE}}` is repeated once per image (or dropped when there is none).
+pub fn render(template: &CurlTemplate, input: &TemplateInput, streaming: bool) -> Result<RenderedRequest, AppError> {
+    let mentions = |placeholder: &str| {
+        template.url.contains(placeholder)
+            || template.body.as_deref().is_some_and(|b| b.contains(placeholder))
+            || template.headers.iter().any(|(_, v)| v.contains(placeholder))
+    };
+    if !input.images_base64.is_empty() && !mentions("{{IMAGE}}") {
+        return Err(AppError::Config("This provider does not support image input".to_string()));
+    }
+
+    let variables = variables_for(input);
+    let missing = placeholders(template)
+        .into_iter()
+        .filter(|name| !BUILT_IN_VARIABLES.contains(&name.as_str()))
+        .find(|name| variables.get(name).is_none_or(|value| value.trim().is_empty()));
+    if let Some(name) = missing {
+        return Err(AppError::Config(format!(
+            "Missing required variable: {}. Please configure it in settings.",
+            name
+        )));
+    }
+
+    let url = replace_placeholders(&template.url, &variables, percent_encode);
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(AppError::Config("Provider URL must start with http:// or https://".to_string()));
+    }
+
+    let mut headers = Vec::with_capacity(template.headers.len());
+    for (name, value) in &template.headers {
+        let value = replace_placeholders(value, &variables, str::to_string);
+        if value.contains(['\r', '\n']) {
+            return Err(AppError::Config(format!("Header {} contains a line break", name)));
+        }
+        headers.push((name.clone(), value));
+    }
+
+    let body = match &template.body {
+        None => None,
+        Some(raw) => Some(match serde_json::from_str::<Value>(raw) {
+            Ok(mut json) => {
+                render_body(&mut json, input, &variables);
+                if streaming {
+                    set_stream_flag(&mut json);
+                }
+                serde_json::to_string(&json)?
+            }
+            // Not JSON: substitute textually, escaping values as JSON string content
+            Err(_) => replace_placeholders(raw, &variables, json_escape),
+        }),
+    };
+
+    Ok(RenderedRequest { method: template.method.clone(), url, headers, body })
+}
+
+/// Names of the `{{VARIABLE}}` placeholders in a template, in order of appearance
+pub fn placeholders(template: &CurlTemplate) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let texts = std::iter::once(template.url.as_str())
+        .chain(template.headers.iter().map(|(_, v)| v.as_str()))
+        .chain(template.body.as_deref());
+
+    for text in texts {
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            rest = &rest[start + 2..];
+            let Some(end) = rest.find("}}") else { break };
+            let name = &rest[..end];
+            if !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !names.iter().any(|n| n == name)
+            {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names
+}
+
+fn variables_for(input: &TemplateInput) -> HashMap<String, String> {
+    let mut variables: HashMap<String, String> = input
+        .variables
+        .iter()
+        .map(|(key, value)| (key.to_uppercase(), value.clone()))
+        .collect();
+
+    variables.insert("SYSTEM_PROMPT".to_string(), input.system_prompt.clone().unwrap_or_default());
+    variables.insert("TEXT".to_string(), input.user_message.clone());
+    variables
+        .entry("IMAGE".to_string())
+        .or_insert_with(|| input.images_base64.first().cloned().unwrap_or_default());
+    variables
+}
+
+// Single pass, so placeholders inside substituted values (e.g. the user typing `{{MODEL}}`) stay as typed
+fn replace_placeholders(text: &str, variables: &HashMap<String, String>, encode: fn(&str) -> String) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}").and_then(|end| Some((end, variables.get(&after[..end])?))) {
+            Some((end, value)) => {
+                result.push_str(&encode(value));
+                rest = &after[end + 2..];
+            }
+            None => {
+                result.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+fn replace_in_json(node: &mut Value, variables: &HashMap<String, String>) {
+    match node {
+        Value::String(s) => *s = replace_placeholders(s, variables, str::to_string),
+        Value::Array(items) => items.iter_mut().for_each(|item| replace_in_json(item, variables)),
+        Value::Object(map) => map.values_mut().for_each(|item| replace_in_json(item, variables)),
+        _ => {}
+    }
+}
+
+fn contains_placeholder(node: &Value, placeholder: &str) -> bool {
+    match node {
+        Value::String(s) => s.contains(placeholder),
+        Value::Array(items) => items.iter().any(|item| contains_placeholder(item, placeholder)),
+        Value::Object(map) => map.values().any(|item| contains_placeholder(item, placeholder)),
+        _ => false,
+    }
+}
+
+// Substitutes variables everywhere, and rebuilds the messages array around the user message template
+fn render_body(body: &mut Value, input: &TemplateInput, variables: &HashMap<String, String>) {
+    let Value::Object(map) = body else {
+        replace_in_json(body, variables);
+        return;
+    };
+
+    for (key, value) in map.iter_mut() {
+        match value {
+            Value::Array(messages) if MESSAGES_KEYS.contains(&key.as_str()) => {
+                build_messages(messages, input, variables)
+            }
+            _ => replace_in_json(value, variables),
+        }
+    }
+}
+
+fn build_messages(messages: &mut Vec<Value>, input: &TemplateInput, variables: &HashMap<String, String>) {
+    let text_index = messages.iter().position(|m| contains_placeholder(m, "{{TEXT}}"));
+    if let Some(index) = text_index {
+        expand_images(&mut messages[index], &input.images_base64);
+    }
+    // Substitute before inserting history, so earlier turns are sent exactly as they were
+    messages.iter_mut().for_each(|m| replace_in_json(m, variables));
+
+    // History is sent as plain text turns; attachment formats differ between providers
+    let history = input
+        .history
+        .iter()
+        .map(|m| serde_json::json!({ "role": m.role, "content": m.content }));
+
+    match text_index {
+        Some(index) => {
+            messages.splice(index..index, history);
+        }
+        None => {
+            let user_message = serde_json::json!({ "role": "user", "content": input.user_message });
+            messages.extend(history.chain(std::iter::once(user_message)));
+        }
+    }
+}
+
+// Repeat the array element holding `{{IMAGE}}` once per image
+fn expand_images(node: &mut Value, images: &[String]) {
+    match node {
+        Value::Array(items) => {
+            if let Some(index) = items.iter().position(|item| contains_placeholder(item, "{{IMAGE}}")) {
+                let template = items[index].clone();
+                let parts = images.iter().map(|image| {
+                    let mut part = template.clone();
+                    replace_in_json(&mut part, &HashMap::from([("IMAGE".to_string(), image.clone())]));
+                    part
+                });
+                items.splice(index..=index, parts.collect::<Vec<_>>());
+            }
+            items.iter_mut().for_each(|item| expand_images(item, images));
+        }
+        Value::Object(map) => map.values_mut().for_each(|item| expand_images(item, images)),
+        _ => {}
+    }
+}
+
+fn set_stream_flag(body: &mut Value) {
+    if let Some(map) = body.as_object_mut() {
+        let key = map
+            .keys()
+            .find(|k| k.eq_ignore_ascii_case("stream"))
+            .cloned()
+            .unwrap_or_else(|| "stream".to_string());
+        map.insert(key, Value::Bool(true));
+    }
+}
+
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Looks up `choices[0].message.content` style paths
+pub fn get_by_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.replace('[', ".")
+        .replace(']', "")
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |node, segment| match node {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => node.get(segment),
+        })
+}
+
+/// Finds the text in a streaming chunk, trying the usual delta locations before the
+/// provider's non-streaming `responseContentPath`
+pub fn streaming_content(chunk: &Value, content_path: &str) -> Option<String> {
+    let streaming_path = content_path.replace(".message.", ".delta.");
+    let candidates = [
+        streaming_path.as_str(),
+        "choices[0].delta.content",
+        "candidates[0].content.parts[0].text",
+        "delta.text",
+        "text",
+        content_path,
+    ];
+
+    candidates
+        .iter()
+        .filter(|path| !path.is_empty())
+        .filter_map(|path| get_by_path(chunk, path)?.as_str())
+        .find(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+struct PathExtractor<'a> {
+    content_path: &'a str,
+}
+
+impl DeltaExtractor for PathExtractor<'_> {
+    fn extract(&self, event: &SseEvent) -> Result<StreamSignal, String> {
+        let data = event.data.trim();
+        if data == "[DONE]" {
+            return Ok(StreamSignal::Done);
+        }
+        let Ok(json) = serde_json::from_str::<Value>(data) else {
+            return Ok(StreamSignal::Ignore);
+        };
+        if let Some(error) = json.get("error").filter(|e| !e.is_null()) {
+            let message = error.get("message").and_then(|m| m.as_str()).map(str::to_string);
+            return Err(message.unwrap_or_else(|| error.to_string()));
+        }
+        Ok(match streaming_content(&json, self.content_path) {
+            Some(text) => StreamSignal::Delta(text),
+            None => StreamSignal::Ignore,
+        })
+    }
+}
+
+/// Sends a rendered request. Streaming responses are read as SSE; otherwise the text at
+/// `content_path` is returned (and passed to `on_delta` once).
+pub async fn execute(
+    client: &reqwest::Client,
+    request: &RenderedRequest,
+    streaming: bool,
+    content_path: &str,
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<String, AppError> {
+    let mut builder = client.request(request.method.clone(), &request.url);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if let Some(body) = &request.body {
+        if request.method != Method::GET {
+            // curl sends `-d` bodies as form data unless told otherwise; providers expect JSON
+            let has_content_type = request.headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+            if !has_content_type && serde_json::from_str::<Value>(body).is_ok() {
+                builder = builder.header("Content-Type", "application/json");
+            }
+            builder = builder.body(body.clone());
+        }
+    }
+
+    let response = trace::send("custom_provider", builder)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to reach provider"))?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_failed_response(response).await);
+    }
+
+    if streaming {
+        return read_stream(response, &PathExtractor { content_path }, on_delta).await;
+    }
+
+    let json: Value = response
+        .json()
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to parse provider response"))?;
+    let content = match get_by_path(&json, content_path) {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    };
+    on_delta(&content);
+
+    Ok(content)
+}
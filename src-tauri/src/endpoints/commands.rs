@@ -0,0 +1,84 @@
+// Tauri commands behind the endpoint profile settings, and the lookup every backend command uses
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+
+use super::{EndpointProfile, ProfileInfo};
+use crate::backend::{Backend, BackendConfig, HealthReport};
+use crate::error::AppError;
+use crate::http;
+use crate::models;
+use crate::secret_store::SecretStore;
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    app.path().app_data_dir()
+        .map_err(|e| AppError::Storage(format!("Failed to get app data directory: {}", e)))
+}
+
+/// Backend settings of the active profile
+pub fn backend_config(app: &AppHandle) -> Result<BackendConfig, AppError> {
+    super::active_config(&app_data_dir(app)?, &app.state::<SecretStore>())
+}
+
+async fn check(app: &AppHandle, name: &str) -> Result<HealthReport, AppError> {
+    let config = super::config_for(&app_data_dir(app)?, &app.state::<SecretStore>(), name)?;
+    Backend::new(http::client(app), config)
+        .check_health()
+        .await
+        .map_err(|e| e.context(&format!("Endpoint profile {} failed its health check", name)))
+}
+
+#[tauri::command]
+pub fn list_endpoint_profiles(app: AppHandle, store: State<'_, SecretStore>) -> Result<Vec<ProfileInfo>, AppError> {
+    super::list_profiles(&app_data_dir(&app)?, &store)
+}
+
+#[tauri::command]
+pub fn save_endpoint_profile(
+    app: AppHandle,
+    store: State<'_, SecretStore>,
+    profile: EndpointProfile,
+    access_key: Option<String>,
+) -> Result<Vec<ProfileInfo>, AppError> {
+    let dir = app_data_dir(&app)?;
+    let settings = super::load_settings(&dir)?;
+    let active = settings.active.as_deref().and_then(|name| settings.profiles.iter().find(|p| p.name == name));
+    // The catalogue came from the old URL, as when switching profiles
+    let moves_active = active.is_some_and(|active| active.name == profile.name && active.base_url != profile.base_url);
+
+    super::save_profile(&dir, &store, profile, access_key.as_deref().filter(|key| !key.is_empty()))?;
+    if moves_active {
+        models::clear_cache(&dir)?;
+    }
+    super::list_profiles(&dir, &store)
+}
+
+#[tauri::command]
+pub fn remove_endpoint_profile(
+    app: AppHandle,
+    store: State<'_, SecretStore>,
+    name: String,
+) -> Result<Vec<ProfileInfo>, AppError> {
+    let dir = app_data_dir(&app)?;
+    let was_active = super::load_settings(&dir)?.active.as_deref() == Some(name.as_str());
+    super::remove_profile(&dir, &store, &name)?;
+    if was_active {
+        models::clear_cache(&dir)?;
+    }
+    super::list_profiles(&dir, &store)
+}
+
+#[tauri::command]
+pub async fn check_endpoint_profile(app: AppHandle, name: String) -> Result<HealthReport, AppError> {
+    check(&app, &name).await
+}
+
+/// Switches only once the profile passed its health check, so a typo in a URL never takes the
+/// app offline. The model catalogue belongs to the previous backend and is dropped.
+#[tauri::command]
+pub async fn set_active_endpoint_profile(app: AppHandle, name: String) -> Result<HealthReport, AppError> {
+    let report = check(&app, &name).await?;
+    let dir = app_data_dir(&app)?;
+    super::set_active(&dir, &name)?;
+    models::clear_cache(&dir)?;
+    Ok(report)
+}
@@ -0,0 +1,198 @@
+// Named backend deployments the app can switch between at runtime: the hosted Pluely backend,
+// built in as the `default` profile from the build-time values, and self-hosted ones added in
+// settings. Profiles are saved in `endpoint_profiles.json`; their access keys go to the secret
+// store, never to the file. The license saved on this device belongs to the built-in profile
+// and is never sent to the others.
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::backend::{AuthScheme, BackendConfig};
+use crate::error::AppError;
+use crate::persist;
+use crate::secret_store::{self, SecretStore, ENDPOINT_KEYS};
+
+mod commands;
+
+pub use commands::*;
+
+/// The build-time endpoints
+pub const DEFAULT_PROFILE: &str = "default";
+const PROFILES_FILE: &str = "endpoint_profiles.json";
+const WHAT: &str = "endpoint profiles";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EndpointProfile {
+    /// 1 to 128 letters, digits, `_`, `-`, `.` or `:`; also names the access key
+    pub name: String,
+    /// Backend serving `/api/chat`, `/api/audio` and `/api/models`
+    pub base_url: String,
+    /// Payment service for license activation, if the deployment has one
+    #[serde(default)]
+    pub payment_url: Option<String>,
+    #[serde(default)]
+    pub auth: AuthScheme,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EndpointSettings {
+    /// `None` for the default profile
+    pub active: Option<String>,
+    pub profiles: Vec<EndpointProfile>,
+}
+
+/// A profile as shown in settings, without its access key
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct ProfileInfo {
+    pub name: String,
+    /// `None` for the default profile, whose endpoints stay out of the UI
+    pub base_url: Option<String>,
+    pub payment_url: Option<String>,
+    pub auth: AuthScheme,
+    pub has_access_key: bool,
+    pub active: bool,
+    /// The default profile can't be edited or removed
+    pub built_in: bool,
+}
+
+fn settings_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(PROFILES_FILE)
+}
+
+pub fn load_settings(app_data_dir: &Path) -> Result<EndpointSettings, AppError> {
+    Ok(persist::read_json(&settings_path(app_data_dir), WHAT)?.unwrap_or_default())
+}
+
+/// The default profile first, then the saved ones in the order they were added
+pub fn list_profiles(app_data_dir: &Path, store: &SecretStore) -> Result<Vec<ProfileInfo>, AppError> {
+    let settings = load_settings(app_data_dir)?;
+    let mut profiles = vec![ProfileInfo {
+        name: DEFAULT_PROFILE.to_string(),
+        base_url: None,
+        payment_url: None,
+        auth: AuthScheme::Bearer,
+        has_access_key: BackendConfig::from_env().api_access_key.is_some(),
+        active: settings.active.is_none(),
+        built_in: true,
+    }];
+    for profile in settings.profiles {
+        profiles.push(ProfileInfo {
+            has_access_key: store.get(ENDPOINT_KEYS, &profile.name)?.is_some(),
+            active: settings.active.as_deref() == Some(profile.name.as_str()),
+            built_in: false,
+            name: profile.name,
+            base_url: Some(profile.base_url),
+            payment_url: profile.payment_url,
+            auth: profile.auth,
+        });
+    }
+    Ok(profiles)
+}
+
+/// Adds or replaces a profile. The access key is kept when `access_key` is `None`, so editing
+/// the URL doesn't require typing the key again.
+pub fn save_profile(
+    app_data_dir: &Path,
+    store: &SecretStore,
+    profile: EndpointProfile,
+    access_key: Option<&str>,
+) -> Result<(), AppError> {
+    let profile = validate(profile)?;
+    if let Some(access_key) = access_key {
+        store.set(ENDPOINT_KEYS, &profile.name, access_key)?;
+    }
+    persist::update_json(&settings_path(app_data_dir), WHAT, |settings: &mut EndpointSettings| {
+        match settings.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => settings.profiles.push(profile),
+        }
+    })
+}
+
+/// Removing the active profile switches back to the default one
+pub fn remove_profile(app_data_dir: &Path, store: &SecretStore, name: &str) -> Result<(), AppError> {
+    if name == DEFAULT_PROFILE {
+        return Err(AppError::Config("The default endpoint profile can't be removed".to_string()));
+    }
+    let removed = persist::update_json(&settings_path(app_data_dir), WHAT, |settings: &mut EndpointSettings| {
+        let before = settings.profiles.len();
+        settings.profiles.retain(|p| p.name != name);
+        if settings.active.as_deref() == Some(name) {
+            settings.active = None;
+        }
+        settings.profiles.len() != before
+    })?;
+    if !removed {
+        return Err(unknown_profile(name));
+    }
+    store.delete(ENDPOINT_KEYS, name)
+}
+
+pub fn set_active(app_data_dir: &Path, name: &str) -> Result<(), AppError> {
+    persist::update_json(&settings_path(app_data_dir), WHAT, |settings: &mut EndpointSettings| {
+        if name == DEFAULT_PROFILE {
+            settings.active = None;
+        } else if settings.profiles.iter().any(|p| p.name == name) {
+            settings.active = Some(name.to_string());
+        } else {
+            return Err(unknown_profile(name));
+        }
+        Ok(())
+    })?
+}
+
+/// Backend settings of the profile `name`, with its access key from the store
+pub fn config_for(app_data_dir: &Path, store: &SecretStore, name: &str) -> Result<BackendConfig, AppError> {
+    if name == DEFAULT_PROFILE {
+        return Ok(BackendConfig::from_env());
+    }
+    let profile = load_settings(app_data_dir)?
+        .profiles
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| unknown_profile(name))?;
+    Ok(BackendConfig {
+        app_endpoint: Some(profile.base_url),
+        payment_endpoint: profile.payment_url,
+        api_access_key: store.get(ENDPOINT_KEYS, name)?,
+        auth: profile.auth,
+        sends_license: false,
+    })
+}
+
+/// Backend settings of the active profile
+pub fn active_config(app_data_dir: &Path, store: &SecretStore) -> Result<BackendConfig, AppError> {
+    let active = load_settings(app_data_dir)?.active;
+    config_for(app_data_dir, store, active.as_deref().unwrap_or(DEFAULT_PROFILE))
+}
+
+fn unknown_profile(name: &str) -> AppError {
+    AppError::Config(format!("Unknown endpoint profile: {}", name))
+}
+
+// URLs are stored without a trailing slash, routes are appended with one
+fn validate(mut profile: EndpointProfile) -> Result<EndpointProfile, AppError> {
+    if profile.name == DEFAULT_PROFILE {
+        return Err(AppError::Config("The default endpoint profile can't be changed".to_string()));
+    }
+    secret_store::namespace(ENDPOINT_KEYS)?.check_key(&profile.name)?;
+
+    profile.base_url = normalize_url(&profile.base_url, "base URL")?;
+    profile.payment_url = match profile.payment_url.as_deref().map(str::trim).filter(|url| !url.is_empty()) {
+        Some(url) => Some(normalize_url(url, "payment URL")?),
+        None => None,
+    };
+    if let AuthScheme::Header { name } = &profile.auth {
+        reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| AppError::Config(format!("Invalid auth header name: {}", name)))?;
+    }
+    Ok(profile)
+}
+
+fn normalize_url(url: &str, what: &str) -> Result<String, AppError> {
+    let url = url.trim().trim_end_matches('/');
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(url.to_string()),
+        _ => Err(AppError::Config(format!("Invalid {}, expected an http(s) URL", what))),
+    }
+}
@@ -0,0 +1,179 @@
+// Error type returned by every command that talks to the network, storage or audio devices.
+// Serialized as `{ "code": "...", "message": "..." }` so the frontend can branch on `code`;
+// messages never contain URLs, license keys or request bodies.
+use reqwest::StatusCode;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+use crate::trace;
+
+/// Longest server message shown to the user; the rest is usually a stack trace or HTML
+const MAX_SERVER_MESSAGE_CHARS: usize = 300;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// Connection refused, DNS failure, timeout, dropped stream...
+    Network(String),
+    /// Missing, invalid or expired license, or rejected credentials
+    Auth(String),
+    /// Rate limited or out of credits
+    Quota(String),
+    /// The server answered with an error, `status` is `None` for errors reported mid-stream
+    Server { status: Option<u16>, message: String },
+    /// A response or stored file couldn't be understood
+    Parse(String),
+    /// Reading or writing local state failed
+    Storage(String),
+    /// Required build-time or user configuration is missing or invalid
+    Config(String),
+    /// No usable microphone or system audio device
+    AudioDevice(String),
+    /// The OS denied access (screen recording, microphone, keychain...)
+    Permission(String),
+    /// The request can't be sent as is, e.g. images for a text-only model or an oversized image
+    Unsupported(String),
+    /// The model's answer didn't match the requested output schema, even after a repair attempt
+    InvalidOutput(String),
+    /// The request was cancelled by the user
+    Cancelled,
+}
+
+impl AppError {
+    /// Stable identifier for the frontend and for support triage
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Network(_) => "network_error",
+            AppError::Auth(_) => "auth_error",
+            AppError::Quota(_) => "quota_exceeded",
+            AppError::Server { .. } => "server_error",
+            AppError::Parse(_) => "parse_error",
+            AppError::Storage(_) => "storage_error",
+            AppError::Config(_) => "config_error",
+            AppError::AudioDevice(_) => "audio_device_error",
+            AppError::Permission(_) => "permission_denied",
+            AppError::Unsupported(_) => "unsupported_input",
+            AppError::InvalidOutput(_) => "invalid_output",
+            AppError::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            AppError::Network(m)
+            | AppError::Auth(m)
+            | AppError::Quota(m)
+            | AppError::Parse(m)
+            | AppError::Storage(m)
+            | AppError::Config(m)
+            | AppError::AudioDevice(m)
+            | AppError::Permission(m)
+            | AppError::Unsupported(m)
+            | AppError::InvalidOutput(m) => m.clone(),
+            AppError::Server { status: Some(status), message } => format!("Server error ({}): {}", status, message),
+            AppError::Server { status: None, message } => format!("Server error: {}", message),
+            AppError::Cancelled => "Request cancelled".to_string(),
+        }
+    }
+
+    /// Prefixes the message with what we were doing, e.g. "Failed to make chat request"
+    pub fn context(self, context: &str) -> Self {
+        let with_context = |m: String| format!("{}: {}", context, m);
+        match self {
+            AppError::Network(m) => AppError::Network(with_context(m)),
+            AppError::Parse(m) => AppError::Parse(with_context(m)),
+            AppError::Storage(m) => AppError::Storage(with_context(m)),
+            other => other,
+        }
+    }
+
+    /// Maps a non-success HTTP response to the matching variant, using the server's
+    /// `error` or `message` field when the body is JSON, redacted and shortened. Any other
+    /// body (HTML error pages, proxies echoing the request) is only named by its status.
+    pub fn from_response(status: StatusCode, body: &str) -> Self {
+        let message = server_message(body).map(|message| shorten(trace::redact_body(message.as_bytes())));
+        let generic = || format!("Server error ({})", status.as_u16());
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => AppError::Auth(message.unwrap_or_else(generic)),
+            StatusCode::PAYMENT_REQUIRED | StatusCode::TOO_MANY_REQUESTS => {
+                AppError::Quota(message.unwrap_or_else(generic))
+            }
+            // `message()` already names the status
+            _ => AppError::Server {
+                status: Some(status.as_u16()),
+                message: message.unwrap_or_else(|| status.canonical_reason().unwrap_or("Unknown error").to_string()),
+            },
+        }
+    }
+
+    /// Reads the body of a failed response and maps it with [`AppError::from_response`]
+    pub async fn from_failed_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_else(|_| "Unknown server error".to_string());
+        AppError::from_response(status, &body)
+    }
+}
+
+fn server_message(body: &str) -> Option<String> {
+    let json = serde_json::from_str::<serde_json::Value>(body).ok()?;
+    let error = json.get("error");
+    error
+        .and_then(|e| e.as_str())
+        .or_else(|| error.and_then(|e| e.get("message")).and_then(|m| m.as_str()))
+        .or_else(|| json.get("message").and_then(|m| m.as_str()))
+        .map(str::to_string)
+}
+
+fn shorten(message: String) -> String {
+    if message.chars().count() <= MAX_SERVER_MESSAGE_CHARS {
+        return message;
+    }
+    let kept: String = message.chars().take(MAX_SERVER_MESSAGE_CHARS).collect();
+    format!("{}…", kept.trim_end())
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.code(), self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.message())?;
+        state.end()
+    }
+}
+
+// reqwest includes the full request URL in its messages; drop it before it reaches the UI or logs
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        let e = e.without_url();
+        if e.is_decode() {
+            AppError::Parse(e.to_string())
+        } else if let Some(status) = e.status() {
+            AppError::from_response(status, &e.to_string())
+        } else {
+            AppError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Parse(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::PermissionDenied => AppError::Permission(e.to_string()),
+            _ => AppError::Storage(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+// Offline answers: when the hosted backend can't be reached, chat falls back to a local
+// OpenAI-compatible runtime (llama.cpp server, Ollama, LM Studio...) if the user enabled it.
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::chat::{Attachment, ChatMessage};
+use crate::error::AppError;
+use crate::persist;
+use crate::providers::{stream_chat, ProviderConfig, ProviderKind, ProviderRequest};
+
+const SETTINGS_FILE: &str = "local_fallback.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LocalFallbackSettings {
+    pub enabled: bool,
+    /// Base URL of the local server including `/v1`, e.g. `http://127.0.0.1:8080/v1` for llama.cpp
+    pub base_url: String,
+    /// Model name as the local server knows it; llama.cpp ignores it, Ollama requires it
+    pub model: String,
+    pub context_window: Option<u32>,
+}
+
+impl Default for LocalFallbackSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            base_url: "http://127.0.0.1:11434/v1".to_string(),
+            model: "llama3.2".to_string(),
+            context_window: None,
+        }
+    }
+}
+
+impl LocalFallbackSettings {
+    fn provider_config(&self) -> ProviderConfig {
+        ProviderConfig {
+            id: "local_fallback".to_string(),
+            kind: ProviderKind::OpenAiCompatible,
+            base_url: self.base_url.clone(),
+            model: self.model.clone(),
+            max_tokens: None,
+            context_window: self.context_window,
+        }
+    }
+}
+
+/// Where a streamed answer came from; sent with every chat stream event
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseSource {
+    Backend,
+    Provider,
+    LocalFallback,
+}
+
+/// Whether a failed backend request should be retried against the local runtime: the backend
+/// was unreachable, its gateway was down, or no endpoint is configured at all. Auth and quota
+/// errors are real answers from the backend and are never masked by a local reply.
+pub fn should_fall_back(error: &AppError) -> bool {
+    match error {
+        AppError::Network(_) | AppError::Config(_) => true,
+        AppError::Server { status: Some(status), .. } => matches!(status, 502..=504),
+        _ => false,
+    }
+}
+
+/// Streams an answer from the local runtime
+pub async fn stream_local(
+    client: &reqwest::Client,
+    settings: &LocalFallbackSettings,
+    user_message: String,
+    system_prompt: Option<String>,
+    attachments: Vec<Attachment>,
+    history: Vec<ChatMessage>,
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<String, AppError> {
+    let config = settings.provider_config();
+    let request = ProviderRequest::new(&config, user_message, system_prompt, attachments, history);
+
+    stream_chat(client, &config, None, &request, on_delta)
+        .await
+        .map_err(|e| e.context("Local model unavailable"))
+}
+
+fn settings_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(SETTINGS_FILE)
+}
+
+pub fn load_settings(app_data_dir: &Path) -> Result<LocalFallbackSettings, AppError> {
+    Ok(persist::read_json(&settings_path(app_data_dir), "local fallback settings")?.unwrap_or_default())
+}
+
+pub fn save_settings(app_data_dir: &Path, settings: &LocalFallbackSettings) -> Result<(), AppError> {
+    persist::write_json(&settings_path(app_data_dir), settings, "local fallback settings")
+}
@@ -0,0 +1,139 @@
+// One shared HTTP client for every network call, built from user settings
+// (timeouts, HTTP(S)/SOCKS proxy, extra root certificates for TLS-inspecting proxies).
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::persist;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HttpSettings {
+    pub connect_timeout_secs: u64,
+    /// Maximum time between two reads; applies per chunk, so long streams aren't cut off
+    pub read_timeout_secs: u64,
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy URL, credentials allowed
+    pub proxy_url: Option<String>,
+    /// Comma separated hosts that bypass the proxy, same syntax as `NO_PROXY`
+    pub no_proxy: Option<String>,
+    /// PEM files with extra root certificates, e.g. a corporate CA
+    pub ca_bundle_paths: Vec<String>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 60,
+            proxy_url: None,
+            no_proxy: None,
+            ca_bundle_paths: Vec::new(),
+        }
+    }
+}
+
+pub struct HttpClient {
+    client: RwLock<reqwest::Client>,
+    settings: RwLock<HttpSettings>,
+}
+
+impl HttpClient {
+    /// Builds the client from saved settings, falling back to defaults if they can't be used
+    pub fn load(app: &AppHandle) -> Self {
+        let settings = read_settings(app).unwrap_or_else(|e| {
+            eprintln!("Failed to load HTTP settings, using defaults: {}", e);
+            HttpSettings::default()
+        });
+
+        let (client, settings) = match build_client(&settings) {
+            Ok(client) => (client, settings),
+            Err(e) => {
+                eprintln!("Failed to apply HTTP settings, using defaults: {}", e);
+                let defaults = HttpSettings::default();
+                (build_client(&defaults).unwrap_or_default(), defaults)
+            }
+        };
+
+        Self {
+            client: RwLock::new(client),
+            settings: RwLock::new(settings),
+        }
+    }
+
+    /// Returns the shared client. Cloning is cheap and keeps the connection pool.
+    pub fn get(&self) -> reqwest::Client {
+        self.client.read().unwrap().clone()
+    }
+}
+
+/// Shortcut for commands: the managed client
+pub fn client(app: &AppHandle) -> reqwest::Client {
+    app.state::<HttpClient>().get()
+}
+
+pub fn build_client(settings: &HttpSettings) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(settings.read_timeout_secs))
+        .user_agent(concat!("pluely/", env!("CARGO_PKG_VERSION")));
+
+    if let Some(proxy_url) = settings.proxy_url.as_deref().filter(|p| !p.trim().is_empty()) {
+        let no_proxy = settings.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string);
+        let proxy = reqwest::Proxy::all(proxy_url.trim())
+            .map_err(|e| format!("Invalid proxy URL: {}", e))?
+            .no_proxy(no_proxy);
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &settings.ca_bundle_paths {
+        let pem = fs::read(path).map_err(|e| format!("Failed to read CA bundle {}: {}", path, e))?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA bundle {}: {}", path, e))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder.build().map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app.path().app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    fs::create_dir_all(&app_data_dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+
+    Ok(app_data_dir.join("http_settings.json"))
+}
+
+fn read_settings(app: &AppHandle) -> Result<HttpSettings, String> {
+    let path = get_settings_path(app)?;
+    persist::read_json(&path, "HTTP settings")
+        .map(Option::unwrap_or_default)
+        .map_err(|e| e.message())
+}
+
+#[tauri::command]
+pub fn get_http_settings(app: AppHandle) -> HttpSettings {
+    app.state::<HttpClient>().settings.read().unwrap().clone()
+}
+
+// Validate by building a client first, so a bad proxy or CA path never replaces a working client
+#[tauri::command]
+pub fn set_http_settings(app: AppHandle, settings: HttpSettings) -> Result<(), AppError> {
+    let client = build_client(&settings).map_err(AppError::Config)?;
+
+    let path = get_settings_path(&app).map_err(AppError::Storage)?;
+    persist::write_json(&path, &settings, "HTTP settings")?;
+
+    let state = app.state::<HttpClient>();
+    *state.client.write().unwrap() = client;
+    *state.settings.write().unwrap() = settings;
+
+    Ok(())
+}
@@ -1,17 +1,37 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 mod window;
-mod shortcuts;
 mod activate;
 mod api;
+pub mod attachments;
+pub mod backend;
+pub mod chat;
+pub mod curl_template;
+pub mod endpoints;
+pub mod error;
+pub mod fallback;
+mod http;
+pub mod license;
+pub mod models;
+pub mod persist;
+pub mod push_to_talk;
+pub mod retry;
+pub mod secret_store;
+pub mod providers;
+pub mod shortcuts;
+pub mod sse;
+mod streams;
+pub mod structured;
+pub mod tools;
+pub mod trace;
+pub mod usage;
 mod vosk_local;
 
 #[cfg(target_os = "macos")]
 use tauri_plugin_macos_permissions;
 use xcap::Monitor;
-use base64::Engine;
-use image::codecs::png::PngEncoder;
-use image::{ColorType, ImageEncoder};
 use tauri_plugin_http;
+use tauri::Manager;
+use chat::{Attachment, AttachmentSource};
 
 use std::sync::{Arc, Mutex};
 use tokio::task::JoinHandle;
@@ -50,6 +70,11 @@ fn set_window_height(window: tauri::WebviewWindow, height: u32) -> Result<(), St
     }
 }
 
+fn capture_monitor(monitor: &Monitor, caption: Option<String>) -> Result<Attachment, String> {
+    let image = monitor.capture_image().map_err(|e| format!("Failed to capture image: {}", e))?;
+    attachments::encode_png(image.as_raw(), image.width(), image.height(), AttachmentSource::Screen, caption)
+}
+
 #[tauri::command]
 fn capture_to_base64() -> Result<String, String> {
     let monitors = Monitor::all().map_err(|e| format!("Failed to get monitors: {}", e))?;
@@ -58,21 +83,42 @@ fn capture_to_base64() -> Result<String, String> {
         .find(|m| m.is_primary())
         .ok_or("No primary monitor found".to_string())?;
 
-    let image = primary_monitor.capture_image().map_err(|e| format!("Failed to capture image: {}", e))?;
-    let mut png_buffer = Vec::new();
-    PngEncoder::new(&mut png_buffer)
-        .write_image(image.as_raw(), image.width(), image.height(), ColorType::Rgba8.into())
-        .map_err(|e| format!("Failed to encode to PNG: {}", e))?;
-    let base64_str = base64::engine::general_purpose::STANDARD.encode(png_buffer);
+    Ok(capture_monitor(&primary_monitor, None)?.data)
+}
+
+// Every monitor, primary first, captioned so the model can tell them apart
+fn capture_all_monitors() -> Result<Vec<Attachment>, String> {
+    let mut monitors = Monitor::all().map_err(|e| format!("Failed to get monitors: {}", e))?;
+    monitors.sort_by_key(|m| !m.is_primary());
+    let count = monitors.len();
 
-    Ok(base64_str)
+    monitors
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| {
+            let primary = if monitor.is_primary() { " (primary)" } else { "" };
+            capture_monitor(monitor, Some(format!("Screen {} of {}{}", index + 1, count, primary)))
+        })
+        .collect()
+}
+
+// Capturing several monitors takes long enough to stall the UI on the main thread
+#[tauri::command]
+async fn capture_monitors() -> Result<Vec<Attachment>, String> {
+    tokio::task::spawn_blocking(capture_all_monitors)
+        .await
+        .map_err(|e| format!("Screen capture task failed: {}", e))?
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let builder = tauri::Builder::default()
         .manage(AudioState::default())
+        .manage(streams::ChatStreams::default())
+        .manage(tools::TranscriptLog::default())
         .manage(shortcuts::WindowVisibility(Mutex::new(false)))
+        .manage(shortcuts::ShortcutRegistry::default())
+        .manage(push_to_talk::PushToTalkState::default())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_http::init())
@@ -84,20 +130,59 @@ pub fn run() {
             get_app_version,
             set_window_height,
             capture_to_base64,
+            capture_monitors,
             shortcuts::get_shortcuts,
             shortcuts::check_shortcuts_registered,
+            shortcuts::set_shortcut,
+            shortcuts::get_shortcut_status,
+            shortcuts::get_push_to_talk_source,
+            shortcuts::set_push_to_talk_source,
             shortcuts::set_app_icon_visibility,
             shortcuts::set_always_on_top,
             activate::activate_license_api,
+            activate::deactivate_license,
+            activate::list_license_instances,
+            activate::validate_license,
+            activate::check_license_status,
             activate::mask_license_key_cmd,
             activate::get_checkout_url,
-            activate::secure_storage_save,
-            activate::secure_storage_get,
-            activate::secure_storage_remove,
+            secret_store::secure_storage_save,
+            secret_store::secure_storage_get,
+            secret_store::secure_storage_remove,
+            secret_store::secure_storage_list,
+            secret_store::get_secure_storage_schema,
+            secret_store::get_secure_storage_kind,
             api::transcribe_audio,
             api::chat_stream,
+            api::chat_structured,
+            streams::cancel_chat_stream,
             api::fetch_models,
-            api::check_license_status,
+            api::get_local_fallback_settings,
+            api::set_local_fallback_settings,
+            api::get_usage_summary,
+            endpoints::list_endpoint_profiles,
+            endpoints::save_endpoint_profile,
+            endpoints::remove_endpoint_profile,
+            endpoints::check_endpoint_profile,
+            endpoints::set_active_endpoint_profile,
+            tools::get_tool_settings,
+            tools::set_tool_allowed,
+            tools::append_transcript,
+            tools::sync_conversations,
+            http::get_http_settings,
+            http::set_http_settings,
+            trace::get_trace_settings,
+            trace::set_trace_settings,
+            trace::clear_debug_log,
+            trace::export_debug_log,
+            providers::provider_chat_stream,
+            providers::custom_provider_chat_stream,
+            providers::save_provider_api_key,
+            providers::remove_provider_api_key,
+            providers::has_provider_api_key,
+            providers::list_provider_configs,
+            providers::save_provider_config,
+            providers::remove_provider_config,
             speaker::start_system_audio_capture,
             speaker::stop_system_audio_capture,
             speaker::check_system_audio_access,
@@ -106,6 +191,17 @@ pub fn run() {
             vosk_local::transcribe_audio_vosk
         ])
         .setup(|app| {
+            // Shared HTTP client, built from the saved network settings
+            app.manage(http::HttpClient::load(app.handle()));
+
+            // License credentials in the OS keychain, or an encrypted file without one
+            secret_store::setup(app.handle());
+
+            // Opt-in HTTP trace log, off unless the user enabled it
+            if let Err(e) = trace::setup(app.handle()) {
+                eprintln!("Failed to set up HTTP tracing: {}", e);
+            }
+
             // Setup main window positioning
             window::setup_main_window(app).expect("Failed to setup main window");
             
@@ -0,0 +1,274 @@
+// Identity of this installation towards the payment service, and the license status it checks
+// without a network round-trip.
+//
+// Every activation from the same install uses the same instance name, so the instance list
+// shows one entry per device and a reactivation can be told apart from a new device.
+//
+// Activation and validation return a token signed by the payment service with Ed25519:
+// `v1.<claims>.<signature>`, both parts base64url without padding, the signature covering
+// `v1.<claims>`. It's checked against the public key compiled into the app, so the status
+// (expiry, entitlements) can be trusted offline. A token stays good for a grace period after it
+// was issued; past that the license has to be validated online again to get a fresh one.
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::persist;
+use crate::secret_store::{SecretStore, INSTANCE_ID, LICENSE, LICENSE_KEY, LICENSE_REVOKED, LICENSE_TOKEN};
+
+const INSTALL_FILE: &str = "install.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Install {
+    id: String,
+}
+
+fn install_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(INSTALL_FILE)
+}
+
+/// Random id created on first use and kept for the life of the app data directory
+pub fn install_id(app_data_dir: &Path) -> Result<String, AppError> {
+    let path = install_path(app_data_dir);
+    if let Some(install) = persist::read_json::<Install>(&path, "install id")? {
+        return Ok(install.id);
+    }
+
+    // Checked again under the lock, a second instance may have created it meanwhile
+    let locked = persist::lock(&path, "install id")?;
+    if let Some(install) = locked.read_json::<Install>("install id")? {
+        return Ok(install.id);
+    }
+    let install = Install { id: Uuid::new_v4().to_string() };
+    locked.write(&serde_json::to_vec_pretty(&install)?, "install id")?;
+    Ok(install.id)
+}
+
+/// Name sent when activating a license on this install
+pub fn instance_name(install_id: &str) -> String {
+    format!("pluely-{}", install_id)
+}
+
+const TOKEN_VERSION: &str = "v1";
+const DAY: u64 = 24 * 60 * 60;
+const DEFAULT_GRACE_DAYS: u64 = 7;
+
+/// What the payment service vouches for in a license token
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LicenseClaims {
+    /// The activation the token was issued for
+    pub instance_id: String,
+    /// Unix seconds; the offline grace period counts from here
+    pub issued_at: u64,
+    /// Unix seconds, `None` for a license that doesn't expire
+    pub expires_at: Option<u64>,
+    /// Overrides the grace period set in the build for this license
+    #[serde(default)]
+    pub grace_days: Option<u64>,
+    /// Feature flags, e.g. `{"chat": true, "transcription": true}`
+    #[serde(default)]
+    pub entitlements: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseState {
+    /// No license activated here
+    Missing,
+    /// Activated before tokens existed, or in a build without a public key; the backend still
+    /// checks the license on every request
+    Unverified,
+    Active,
+    Expired,
+    /// The grace period is over, the license has to be validated online
+    NeedsValidation,
+    /// The token doesn't verify or belongs to another instance
+    Invalid,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct LicenseStatus {
+    pub state: LicenseState,
+    /// Whether the Pluely API may be used
+    pub active: bool,
+    /// Whole days until the license expires, `None` if it doesn't or isn't verified
+    pub days_remaining: Option<u64>,
+    /// Whole days until the license has to be validated online again
+    pub offline_days_remaining: Option<u64>,
+    pub expires_at: Option<u64>,
+    pub entitlements: BTreeMap<String, bool>,
+    /// Why the token was rejected
+    pub error: Option<String>,
+}
+
+impl LicenseStatus {
+    fn new(state: LicenseState) -> Self {
+        Self {
+            state,
+            active: matches!(state, LicenseState::Unverified | LicenseState::Active),
+            days_remaining: None,
+            offline_days_remaining: None,
+            expires_at: None,
+            entitlements: BTreeMap::new(),
+            error: None,
+        }
+    }
+}
+
+pub struct LicenseVerifier {
+    /// `None` in builds without a public key, where tokens can't be checked
+    key: Option<VerifyingKey>,
+    grace_days: u64,
+}
+
+impl LicenseVerifier {
+    pub fn new(public_key: &[u8; 32], grace_days: u64) -> Result<Self, AppError> {
+        let key = VerifyingKey::from_bytes(public_key)
+            .map_err(|e| AppError::Config(format!("Invalid license public key: {}", e)))?;
+        Ok(Self { key: Some(key), grace_days })
+    }
+
+    /// `LICENSE_PUBLIC_KEY` (base64) and `LICENSE_GRACE_DAYS` as set during the build. Unlike
+    /// the endpoints there's no runtime override: whoever sets the key can sign licenses.
+    pub fn from_build() -> Self {
+        let grace_days = option_env!("LICENSE_GRACE_DAYS")
+            .and_then(|days| days.parse().ok())
+            .unwrap_or(DEFAULT_GRACE_DAYS);
+        let key = option_env!("LICENSE_PUBLIC_KEY").and_then(|encoded| {
+            let bytes = base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+            let verifier = Self::new(&bytes.try_into().ok()?, grace_days);
+            if let Err(e) = &verifier {
+                eprintln!("{}", e);
+            }
+            verifier.ok()?.key
+        });
+        Self { key, grace_days }
+    }
+
+    /// Claims of a token issued for `instance_id`, if its signature checks out
+    pub fn verify(&self, token: &str, instance_id: &str) -> Result<LicenseClaims, AppError> {
+        let key = self
+            .key
+            .as_ref()
+            .ok_or_else(|| AppError::Config("This build has no license public key".to_string()))?;
+        let malformed = || AppError::Auth("License token is malformed".to_string());
+
+        let (signed, signature) = token.rsplit_once('.').ok_or_else(malformed)?;
+        let (version, claims) = signed.split_once('.').ok_or_else(malformed)?;
+        if version != TOKEN_VERSION {
+            return Err(AppError::Auth(format!("Unsupported license token version: {}", version)));
+        }
+
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let signature = engine
+            .decode(signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(malformed)?;
+        key.verify_strict(signed.as_bytes(), &signature)
+            .map_err(|_| AppError::Auth("License token signature is invalid".to_string()))?;
+
+        let claims: LicenseClaims = engine
+            .decode(claims)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(malformed)?;
+        if claims.instance_id != instance_id {
+            return Err(AppError::Auth("License token was issued for another instance".to_string()));
+        }
+        Ok(claims)
+    }
+
+    // Builds without a public key take tokens on trust, the backend checks the license anyway
+    fn check(&self, token: &str, instance_id: &str) -> Result<(), AppError> {
+        match self.key {
+            Some(_) => self.verify(token, instance_id).map(drop),
+            None => Ok(()),
+        }
+    }
+
+    /// Status of the license activated as `instance_id` with `token`, at `now` (Unix seconds)
+    pub fn status(&self, instance_id: Option<&str>, token: Option<&str>, now: u64) -> LicenseStatus {
+        let Some(instance_id) = instance_id else {
+            return LicenseStatus::new(LicenseState::Missing);
+        };
+        let (Some(token), Some(_)) = (token, &self.key) else {
+            return LicenseStatus::new(LicenseState::Unverified);
+        };
+        let claims = match self.verify(token, instance_id) {
+            Ok(claims) => claims,
+            Err(e) => {
+                return LicenseStatus { error: Some(e.message()), ..LicenseStatus::new(LicenseState::Invalid) };
+            }
+        };
+
+        let validate_by = claims.issued_at + claims.grace_days.unwrap_or(self.grace_days) * DAY;
+        let state = if claims.expires_at.is_some_and(|expires_at| now >= expires_at) {
+            LicenseState::Expired
+        } else if now >= validate_by {
+            LicenseState::NeedsValidation
+        } else {
+            LicenseState::Active
+        };
+        LicenseStatus {
+            days_remaining: claims.expires_at.map(|expires_at| expires_at.saturating_sub(now) / DAY),
+            offline_days_remaining: Some(validate_by.saturating_sub(now) / DAY),
+            expires_at: claims.expires_at,
+            entitlements: claims.entitlements,
+            ..LicenseStatus::new(state)
+        }
+    }
+}
+
+/// Status of the license activated here, from the secret store only
+pub fn status(store: &SecretStore, verifier: &LicenseVerifier, now: u64) -> Result<LicenseStatus, AppError> {
+    let instance_id = store.get(LICENSE, INSTANCE_ID)?;
+    if let (Some(_), Some(reason)) = (&instance_id, store.get(LICENSE, LICENSE_REVOKED)?) {
+        return Ok(LicenseStatus { error: Some(reason), ..LicenseStatus::new(LicenseState::Invalid) });
+    }
+    let token = store.get(LICENSE, LICENSE_TOKEN)?;
+    Ok(verifier.status(instance_id.as_deref(), token.as_deref(), now))
+}
+
+/// Keeps a successful activation, refusing a token that doesn't verify
+pub fn save_activation(
+    store: &SecretStore,
+    verifier: &LicenseVerifier,
+    license_key: &str,
+    instance_id: &str,
+    token: Option<&str>,
+) -> Result<(), AppError> {
+    if let Some(token) = token {
+        verifier.check(token, instance_id)?;
+    }
+    store.set(LICENSE, LICENSE_KEY, license_key)?;
+    store.set(LICENSE, INSTANCE_ID, instance_id)?;
+    store.delete(LICENSE, LICENSE_REVOKED)?;
+    match token {
+        Some(token) => store.set(LICENSE, LICENSE_TOKEN, token),
+        // A token from an earlier activation belongs to another instance
+        None => store.delete(LICENSE, LICENSE_TOKEN),
+    }
+}
+
+/// Replaces the token of the license activated here with a fresh one from validation
+pub fn save_token(store: &SecretStore, verifier: &LicenseVerifier, token: &str) -> Result<(), AppError> {
+    let instance_id = store
+        .get(LICENSE, INSTANCE_ID)?
+        .ok_or(AppError::Auth("No license found. Please activate your license first.".to_string()))?;
+    verifier.check(token, &instance_id)?;
+    store.set(LICENSE, LICENSE_TOKEN, token)?;
+    store.delete(LICENSE, LICENSE_REVOKED)
+}
+
+/// Drops the token of a license the payment service no longer validates. Without a token it
+/// would pass for one activated before tokens existed, so the refusal is kept until the next
+/// activation or successful validation.
+pub fn revoke(store: &SecretStore, reason: Option<&str>) -> Result<(), AppError> {
+    store.delete(LICENSE, LICENSE_TOKEN)?;
+    store.set(LICENSE, LICENSE_REVOKED, reason.unwrap_or("The license is no longer valid"))
+}
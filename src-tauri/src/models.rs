@@ -0,0 +1,153 @@
+// Model catalogue from the backend, cached on disk so the model picker and capability checks
+// keep working offline. Refreshed after `CACHE_TTL`, revalidated with the backend's ETag.
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::backend;
+use crate::error::AppError;
+use crate::persist;
+use crate::retry::{send_with_retry, RetryEvent, RetryPolicy};
+
+const CACHE_FILE: &str = "models_cache.json";
+
+/// How long a cached catalogue is used without asking the backend
+pub const CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Model {
+    pub provider: String,
+    pub name: String,
+    pub id: String,
+    pub model: String,
+    pub description: String,
+    pub modality: String,
+    #[serde(rename = "isAvailable")]
+    pub is_available: bool,
+    #[serde(rename = "contextWindow", default)]
+    pub context_window: Option<u32>,
+    /// Whether the model accepts images; older backends only send `modality`
+    #[serde(rename = "supportsVision", default)]
+    pub supports_vision: Option<bool>,
+    /// USD per prompt token
+    #[serde(rename = "inputPricePerToken", default)]
+    pub input_price_per_token: Option<f64>,
+    /// USD per completion token
+    #[serde(rename = "outputPricePerToken", default)]
+    pub output_price_per_token: Option<f64>,
+}
+
+impl Model {
+    /// Vision support, falling back to the modality when the backend doesn't say
+    pub fn accepts_images(&self) -> bool {
+        self.supports_vision.unwrap_or_else(|| {
+            let modality = self.modality.to_lowercase();
+            !matches!(modality.trim(), "text" | "text-only" | "text only")
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModelsResponse {
+    pub models: Vec<Model>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ModelCache {
+    /// Unix seconds of the last successful fetch or revalidation
+    pub fetched_at: u64,
+    pub etag: Option<String>,
+    pub models: Vec<Model>,
+}
+
+impl ModelCache {
+    pub fn is_fresh(&self, now: u64) -> bool {
+        now.saturating_sub(self.fetched_at) < CACHE_TTL.as_secs()
+    }
+
+    pub fn find(&self, id: &str) -> Option<&Model> {
+        self.models.iter().find(|m| m.id == id)
+    }
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Fetches the catalogue, sending the cached ETag so an unchanged catalogue costs a 304
+pub async fn refresh_catalogue(
+    client: &reqwest::Client,
+    url: &str,
+    auth: Option<&(String, String)>,
+    cached: Option<&ModelCache>,
+    retry: &RetryPolicy,
+    on_retry: &(dyn Fn(&RetryEvent) + Send + Sync),
+) -> Result<ModelCache, AppError> {
+    let etag = cached.and_then(|c| c.etag.clone());
+    let build_request = || {
+        let request = backend::authorize(client.post(url), auth).header("Content-Type", "application/json");
+        match &etag {
+            Some(etag) => request.header(IF_NONE_MATCH, etag),
+            None => request,
+        }
+    };
+
+    let response = send_with_retry("models", retry, build_request, on_retry)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to make models request"))?;
+
+    if let (StatusCode::NOT_MODIFIED, Some(cached)) = (response.status(), cached) {
+        return Ok(ModelCache { fetched_at: now_secs(), ..cached.clone() });
+    }
+
+    if !response.status().is_success() {
+        return Err(AppError::from_failed_response(response).await);
+    }
+
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let models_response: ModelsResponse = response
+        .json()
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to parse models response"))?;
+
+    Ok(ModelCache { fetched_at: now_secs(), etag, models: models_response.models })
+}
+
+fn cache_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(CACHE_FILE)
+}
+
+/// The cached catalogue, or `None` if there is none or it can't be read
+pub fn load_cache(app_data_dir: &Path) -> Option<ModelCache> {
+    match persist::read_json(&cache_path(app_data_dir), "model cache") {
+        Ok(cache) => cache,
+        Err(e) => {
+            eprintln!("Ignoring unreadable model cache: {}", e);
+            None
+        }
+    }
+}
+
+/// Drops the catalogue, e.g. after switching to another backend
+pub fn clear_cache(app_data_dir: &Path) -> Result<(), AppError> {
+    match std::fs::remove_file(cache_path(app_data_dir)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(AppError::from(e).context("Failed to delete model cache"))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn save_cache(app_data_dir: &Path, cache: &ModelCache) -> Result<(), AppError> {
+    let content = serde_json::to_vec(cache)?;
+    persist::lock(&cache_path(app_data_dir), "model cache")?.write(&content, "model cache")
+}
@@ -0,0 +1,198 @@
+// Every state file in the app data directory is read and written through here, so a crash, a
+// full disk or two app instances saving at once can't leave one half written.
+//
+// - A write goes to `<file>.tmp`, is flushed to disk, then renamed over the file: readers see
+//   the old content or the new one, never a mix.
+// - The version being replaced is kept as `<file>.bak` as long as it still parses. A file that
+//   no longer parses is restored from it on the next read.
+// - Writers hold an exclusive lock on `<file>.lock`, which serializes them across threads and
+//   processes. Readers don't need it, the rename is atomic.
+//
+// `what` names the file in error messages, e.g. "tool settings" gives
+// "Failed to parse tool settings: ...".
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+
+/// Exclusive lock on a state file, released when dropped. Locks are not reentrant: taking a
+/// second one on the same file from the same thread blocks forever.
+pub struct Locked {
+    path: PathBuf,
+    _file: File,
+}
+
+pub fn lock(path: &Path, what: &str) -> Result<Locked, AppError> {
+    create_parent(path)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(sibling(path, "lock"))
+        .and_then(|file| file.lock().map(|()| file))
+        .map_err(|e| AppError::from(e).context(&format!("Failed to lock {}", what)))?;
+    Ok(Locked { path: path.to_path_buf(), _file: file })
+}
+
+impl Locked {
+    /// Like `read_json`, restoring from the backup without taking the lock again
+    pub fn read_json<T: DeserializeOwned>(&self, what: &str) -> Result<Option<T>, AppError> {
+        parse(&self.path, what).or_else(|e| recover(&self.path, what, e))
+    }
+
+    pub fn write(&self, content: &[u8], what: &str) -> Result<(), AppError> {
+        self.replace(content, false)
+            .map_err(|e| AppError::from(e).context(&format!("Failed to write {}", what)))
+    }
+
+    /// Readable and writable by the user only (on Unix), for keys and secrets
+    pub fn write_private(&self, content: &[u8], what: &str) -> Result<(), AppError> {
+        self.replace(content, true)
+            .map_err(|e| AppError::from(e).context(&format!("Failed to write {}", what)))
+    }
+
+    /// Appends to a log file; no backup, a line cut short by a crash is the reader's problem
+    pub fn append(&self, content: &[u8], what: &str) -> Result<(), AppError> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(content))
+            .map_err(|e| AppError::from(e).context(&format!("Failed to write {}", what)))
+    }
+
+    fn replace(&self, content: &[u8], private: bool) -> io::Result<()> {
+        // A file that no longer parses is not worth keeping over the backup it should be restored from
+        if let Ok(current) = fs::read(&self.path) {
+            if serde_json::from_slice::<serde::de::IgnoredAny>(&current).is_ok() {
+                write_atomic(&sibling(&self.path, "bak"), &current, private)?;
+            }
+        }
+        write_atomic(&self.path, content, private)
+    }
+}
+
+/// The parsed file, `None` if it doesn't exist. A file that doesn't parse is replaced by its
+/// backup, if that one does.
+pub fn read_json<T: DeserializeOwned>(path: &Path, what: &str) -> Result<Option<T>, AppError> {
+    match parse(path, what) {
+        Err(e @ AppError::Parse(_)) => lock(path, what)?.read_json(what).map_err(|_| e),
+        result => result,
+    }
+}
+
+/// Pretty-printed, like every settings file the user may want to read
+pub fn write_json<T: Serialize>(path: &Path, value: &T, what: &str) -> Result<(), AppError> {
+    let content = serde_json::to_vec_pretty(value)?;
+    lock(path, what)?.write(&content, what)
+}
+
+/// Read-modify-write under the lock, so concurrent updates don't overwrite each other. A missing
+/// file starts from the default value.
+pub fn update_json<T, R>(path: &Path, what: &str, update: impl FnOnce(&mut T) -> R) -> Result<R, AppError>
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    let locked = lock(path, what)?;
+    let mut value = locked.read_json(what)?.unwrap_or_default();
+    let result = update(&mut value);
+    locked.write(&serde_json::to_vec_pretty(&value)?, what)?;
+    Ok(result)
+}
+
+/// Appends one line to a JSON Lines log
+pub fn append_line(path: &Path, line: &str, what: &str) -> Result<(), AppError> {
+    let mut content = line.to_string();
+    content.push('\n');
+    lock(path, what)?.append(content.as_bytes(), what)
+}
+
+fn parse<T: DeserializeOwned>(path: &Path, what: &str) -> Result<Option<T>, AppError> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(AppError::from(e).context(&format!("Failed to read {}", what))),
+    };
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| AppError::from(e).context(&format!("Failed to parse {}", what)))
+}
+
+// Called with the lock held, after `error` reading the file
+fn recover<T: DeserializeOwned>(path: &Path, what: &str, error: AppError) -> Result<Option<T>, AppError> {
+    if !matches!(error, AppError::Parse(_)) {
+        return Err(error);
+    }
+    // Another writer may have replaced the file since
+    if let Ok(Some(value)) = parse(path, what) {
+        return Ok(Some(value));
+    }
+
+    let backup = sibling(path, "bak");
+    let Ok(Some(value)) = parse(&backup, what) else {
+        return Err(error);
+    };
+    // Copying keeps the backup's permissions, private files stay private
+    let tmp = sibling(path, "tmp");
+    fs::copy(&backup, &tmp)
+        .and_then(|_| File::open(&tmp)?.sync_all())
+        .and_then(|()| fs::rename(&tmp, path))
+        .map_err(|e| AppError::from(e).context(&format!("Failed to restore {} from backup", what)))?;
+    sync_dir(path);
+    eprintln!("Restored {} from backup: {}", what, error);
+    Ok(Some(value))
+}
+
+fn write_atomic(path: &Path, content: &[u8], private: bool) -> io::Result<()> {
+    let tmp = sibling(path, "tmp");
+    // A temporary file left by a crash may have other permissions, creating it anew sets ours
+    let _ = fs::remove_file(&tmp);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    let mut file = options.open(&tmp)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp, path)?;
+    sync_dir(path);
+    Ok(())
+}
+
+// Makes the rename itself durable; only possible (and needed) on Unix
+fn sync_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let _ = File::open(parent).and_then(|dir| dir.sync_all());
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+fn create_parent(path: &Path) -> Result<(), AppError> {
+    match path.parent() {
+        Some(parent) => fs::create_dir_all(parent)
+            .map_err(|e| AppError::from(e).context("Failed to create app data directory")),
+        None => Ok(()),
+    }
+}
+
+/// `settings.json` -> `settings.json.bak`
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
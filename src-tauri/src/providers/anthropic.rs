@@ -0,0 +1,100 @@
+// Anthropic Messages API client (`/v1/messages`)
+use serde_json::{json, Value};
+
+use super::{ProviderConfig, ProviderRequest};
+use crate::chat::{Attachment, Role};
+use crate::error::AppError;
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+// The Messages API requires max_tokens on every request
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+pub fn build_request(
+    client: &reqwest::Client,
+    config: &ProviderConfig,
+    api_key: Option<&str>,
+    request: &ProviderRequest,
+) -> Result<reqwest::RequestBuilder, AppError> {
+    let api_key = api_key.ok_or(AppError::Auth("Anthropic API key not found in keychain".to_string()))?;
+    let url = format!("{}/messages", config.base_url.trim_end_matches('/'));
+
+    Ok(client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("x-api-key", api_key)
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .json(&build_body(config, request)))
+}
+
+fn build_body(config: &ProviderConfig, request: &ProviderRequest) -> Value {
+    let mut messages = Vec::new();
+
+    // Anthropic only accepts user/assistant turns; system prompts go in a top-level field,
+    // so system notes in the history (e.g. the trimming summary) are folded into it below
+    let mut system_notes = Vec::new();
+    for message in &request.history {
+        if message.role == Role::System {
+            system_notes.push(message.content.as_str());
+            continue;
+        }
+        messages.push(message_json(message.role, &message.content, &message.attachments));
+    }
+
+    messages.push(message_json(Role::User, &request.user_message, &request.attachments));
+
+    let mut body = json!({
+        "model": config.model,
+        "max_tokens": config.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        "messages": messages,
+        "stream": true,
+    });
+
+    let system: Vec<&str> = request
+        .system_prompt
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .into_iter()
+        .chain(system_notes)
+        .collect();
+    if !system.is_empty() {
+        body["system"] = json!(system.join("\n\n"));
+    }
+
+    if let Some((tool, tool_choice)) = request.response_format.as_ref().and_then(forced_tool) {
+        body["tools"] = json!([tool]);
+        body["tool_choice"] = tool_choice;
+    }
+
+    body
+}
+
+// There's no `response_format` here; forcing a call to a tool whose input schema is the wanted
+// schema gets the same result, streamed as the tool's input. Tool input must be an object, so
+// other schemas rely on the instruction in the system prompt alone.
+fn forced_tool(response_format: &Value) -> Option<(Value, Value)> {
+    let format = response_format.get("json_schema")?;
+    let schema = format
+        .get("schema")
+        .filter(|schema| schema.get("type").and_then(Value::as_str) == Some("object"))?;
+    let name = format.get("name").and_then(Value::as_str).unwrap_or("response");
+    let tool = json!({
+        "name": name,
+        "description": "Give your answer in the required format",
+        "input_schema": schema,
+    });
+    Some((tool, json!({ "type": "tool", "name": name })))
+}
+
+fn message_json(role: Role, text: &str, attachments: &[Attachment]) -> Value {
+    let mut content = vec![json!({ "type": "text", "text": text })];
+    for attachment in attachments {
+        if let Some(caption) = &attachment.caption {
+            content.push(json!({ "type": "text", "text": caption }));
+        }
+        content.push(json!({
+            "type": "image",
+            "source": { "type": "base64", "media_type": attachment.mime_type, "data": attachment.data }
+        }));
+    }
+    json!({ "role": role, "content": content })
+}
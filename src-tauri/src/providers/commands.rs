@@ -0,0 +1,149 @@
+// Tauri commands for talking to providers directly, without the hosted backend
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+
+use super::{
+    api_key_for, delete_api_key, load_api_key, provider_config, store_api_key, stream_chat, ProviderConfig,
+    ProviderRequest,
+};
+use crate::attachments;
+use crate::chat::{Attachment, ChatMessage};
+use crate::curl_template::{self, TemplateInput};
+use crate::error::AppError;
+use crate::fallback::ResponseSource;
+use crate::http;
+use crate::secret_store::SecretStore;
+use crate::streams::{emit_chunk, request_id_or_new, run_cancellable};
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    app.path().app_data_dir()
+        .map_err(|e| AppError::Storage(format!("Failed to get app data directory: {}", e)))
+}
+
+// Chat with a saved provider directly. Emits the same request-scoped `chat_stream_chunk` /
+// `chat_stream_complete` events as `api::chat_stream` so the frontend can treat both paths the same way.
+// Only the id comes from the webview; URL and key are both resolved here.
+#[tauri::command]
+pub async fn provider_chat_stream(
+    app: AppHandle,
+    request_id: Option<String>,
+    provider_id: String,
+    user_message: String,
+    system_prompt: Option<String>,
+    attachments: Option<Vec<Attachment>>,
+    history: Option<Vec<ChatMessage>>,
+) -> Result<String, AppError> {
+    let request_id = request_id_or_new(request_id);
+    let mut history = history.unwrap_or_default();
+    let (attachments, _) = attachments::validate_turn(attachments.unwrap_or_default(), &mut history)?;
+    let dir = app_data_dir(&app)?;
+    let provider = provider_config(&dir, &provider_id)?;
+    let api_key = api_key_for(&dir, &app.state::<SecretStore>(), &provider.id, &provider.base_url)?;
+    let request = ProviderRequest::new(&provider, user_message, system_prompt, attachments, history);
+
+    let client = http::client(&app);
+    let mut on_delta = |content: &str| emit_chunk(&app, &request_id, content, ResponseSource::Provider);
+    let stream = async {
+        let full_response = stream_chat(&client, &provider, api_key.as_deref(), &request, &mut on_delta).await?;
+        Ok((full_response, ResponseSource::Provider))
+    };
+
+    run_cancellable(&app, &request_id, stream).await
+}
+
+// Chat with a user-defined curl-template provider. `{{API_KEY}}` comes from the secret store entry
+// saved for `provider_id`, and only when the template's host is the one the key was saved for;
+// every other variable is passed in by the frontend.
+#[tauri::command]
+pub async fn custom_provider_chat_stream(
+    app: AppHandle,
+    request_id: Option<String>,
+    provider_id: String,
+    curl: String,
+    response_content_path: Option<String>,
+    streaming: bool,
+    variables: Option<HashMap<String, String>>,
+    user_message: String,
+    system_prompt: Option<String>,
+    attachments: Option<Vec<Attachment>>,
+    history: Option<Vec<ChatMessage>>,
+) -> Result<String, AppError> {
+    let request_id = request_id_or_new(request_id);
+    let mut history = history.unwrap_or_default();
+    let (attachments, _) = attachments::validate_turn(attachments.unwrap_or_default(), &mut history)?;
+    let template = curl_template::parse_curl(&curl)?;
+
+    let mut variables = variables.unwrap_or_default();
+    variables.retain(|key, _| !key.eq_ignore_ascii_case("API_KEY"));
+    if curl_template::placeholders(&template).iter().any(|name| name == "API_KEY") {
+        let store = app.state::<SecretStore>();
+        if let Some(api_key) = api_key_for(&app_data_dir(&app)?, &store, &provider_id, &template.url)? {
+            variables.insert("API_KEY".to_string(), api_key);
+        }
+    }
+
+    let input = TemplateInput {
+        user_message,
+        system_prompt,
+        // Templates only have `{{IMAGE}}`, so just the image data is used
+        images_base64: attachments.into_iter().map(|a| a.data).collect(),
+        history,
+        variables,
+    };
+    let request = curl_template::render(&template, &input, streaming)?;
+    let content_path = response_content_path.unwrap_or_default();
+
+    let client = http::client(&app);
+    let mut on_delta = |content: &str| emit_chunk(&app, &request_id, content, ResponseSource::Provider);
+    let stream = async {
+        let full_response = curl_template::execute(&client, &request, streaming, &content_path, &mut on_delta).await?;
+        Ok((full_response, ResponseSource::Provider))
+    };
+
+    run_cancellable(&app, &request_id, stream).await
+}
+
+/// Saves the key for the host of the provider's `curl` template, or of the saved direct provider
+/// when there's none; that host is the only one it's sent to
+#[tauri::command]
+pub fn save_provider_api_key(
+    app: AppHandle,
+    store: State<'_, SecretStore>,
+    provider_id: String,
+    api_key: String,
+    curl: Option<String>,
+) -> Result<(), AppError> {
+    let dir = app_data_dir(&app)?;
+    let url = match curl {
+        Some(curl) => curl_template::parse_curl(&curl)?.url,
+        None => provider_config(&dir, &provider_id)?.base_url,
+    };
+    store_api_key(&dir, &store, &provider_id, &api_key, &url)
+}
+
+#[tauri::command]
+pub fn list_provider_configs(app: AppHandle) -> Result<Vec<ProviderConfig>, AppError> {
+    Ok(super::load_settings(&app_data_dir(&app)?)?.providers.into_values().collect())
+}
+
+#[tauri::command]
+pub fn save_provider_config(app: AppHandle, provider: ProviderConfig) -> Result<(), AppError> {
+    super::save_provider(&app_data_dir(&app)?, provider)
+}
+
+#[tauri::command]
+pub fn remove_provider_config(app: AppHandle, store: State<'_, SecretStore>, provider_id: String) -> Result<(), AppError> {
+    super::remove_provider(&app_data_dir(&app)?, &store, &provider_id)
+}
+
+#[tauri::command]
+pub fn remove_provider_api_key(app: AppHandle, store: State<'_, SecretStore>, provider_id: String) -> Result<(), AppError> {
+    delete_api_key(&app_data_dir(&app)?, &store, &provider_id)
+}
+
+// Lets the settings UI show whether a key is configured without ever sending it to the webview
+#[tauri::command]
+pub fn has_provider_api_key(store: State<'_, SecretStore>, provider_id: String) -> Result<bool, AppError> {
+    Ok(load_api_key(&store, &provider_id)?.is_some())
+}
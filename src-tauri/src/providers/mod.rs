@@ -0,0 +1,263 @@
+// Direct provider clients, used when the hosted Pluely backend is not an option
+// (self-hosted models, Ollama, LM Studio, or a team's own OpenAI/Anthropic keys).
+//
+// Direct provider configs are saved in `providers.json` and requests name them by id, so the URL a
+// key goes to is never taken from the webview. Curl templates do come from it, so an API key is
+// also tied to the origin it was saved for and only ever sent there; without that any script in
+// the webview could send a saved key to a host of its choosing.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::chat::{fit_history, request_tokens, Attachment, ChatMessage, DEFAULT_CONTEXT_WINDOW};
+use crate::persist;
+use crate::sse::{read_stream, AnthropicExtractor, DeltaExtractor, OpenAiExtractor};
+use crate::secret_store::{SecretStore, KEYCHAIN_SERVICE, PROVIDER_KEYS};
+use crate::trace;
+
+mod anthropic;
+mod commands;
+mod openai;
+
+pub use commands::*;
+
+const PROVIDERS_FILE: &str = "providers.json";
+const WHAT: &str = "provider settings";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    /// Anything speaking `/v1/chat/completions`: OpenAI, Ollama, LM Studio, vLLM, llama.cpp server...
+    OpenAiCompatible,
+    /// Anthropic `/v1/messages`
+    Anthropic,
+}
+
+impl ProviderKind {
+    fn extractor(self) -> &'static dyn DeltaExtractor {
+        match self {
+            ProviderKind::OpenAiCompatible => &OpenAiExtractor,
+            ProviderKind::Anthropic => &AnthropicExtractor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    /// Stable identifier, also used to look up the API key in the keychain
+    pub id: String,
+    pub kind: ProviderKind,
+    /// Base URL including the version segment, e.g. `https://api.openai.com/v1`
+    /// or `http://localhost:11434/v1`
+    pub base_url: String,
+    pub model: String,
+    pub max_tokens: Option<u32>,
+    pub context_window: Option<u32>,
+}
+
+/// What the webview can't choose per request
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderSettings {
+    /// Direct providers, by id
+    pub providers: BTreeMap<String, ProviderConfig>,
+    /// Origin (`scheme://host:port`) each saved API key may be sent to, by provider id
+    pub key_origins: BTreeMap<String, String>,
+}
+
+fn settings_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(PROVIDERS_FILE)
+}
+
+pub fn load_settings(app_data_dir: &Path) -> Result<ProviderSettings, AppError> {
+    Ok(persist::read_json(&settings_path(app_data_dir), WHAT)?.unwrap_or_default())
+}
+
+/// The saved direct provider `id`
+pub fn provider_config(app_data_dir: &Path, id: &str) -> Result<ProviderConfig, AppError> {
+    load_settings(app_data_dir)?
+        .providers
+        .remove(id)
+        .ok_or_else(|| AppError::Config(format!("Unknown provider: {}", id)))
+}
+
+/// Adds or replaces a direct provider. Its key stays tied to the old origin, so pointing the
+/// provider elsewhere means saving the key again.
+pub fn save_provider(app_data_dir: &Path, mut config: ProviderConfig) -> Result<(), AppError> {
+    crate::secret_store::namespace(PROVIDER_KEYS)?.check_key(&config.id)?;
+    origin_of(&config.base_url)?;
+    config.base_url = config.base_url.trim().trim_end_matches('/').to_string();
+    persist::update_json(&settings_path(app_data_dir), WHAT, |settings: &mut ProviderSettings| {
+        settings.providers.insert(config.id.clone(), config);
+    })
+}
+
+/// Removes a direct provider and its key
+pub fn remove_provider(app_data_dir: &Path, store: &SecretStore, id: &str) -> Result<(), AppError> {
+    delete_api_key(app_data_dir, store, id)?;
+    persist::update_json(&settings_path(app_data_dir), WHAT, |settings: &mut ProviderSettings| {
+        settings.providers.remove(id);
+    })
+}
+
+/// `scheme://host:port` of an http(s) URL. A placeholder in the host is refused: the key would
+/// go wherever that variable points.
+pub fn origin_of(url: &str) -> Result<String, AppError> {
+    let invalid = || AppError::Config("Provider URL must be an http(s) URL with a fixed host".to_string());
+    let url = url.trim();
+    let (_, rest) = url.split_once("://").ok_or_else(invalid)?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    if authority.contains("{{") {
+        return Err(invalid());
+    }
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.host_str().is_some() => {
+            Ok(parsed.origin().ascii_serialization())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Everything a provider needs to build one chat request
+#[derive(Debug, Clone, Default)]
+pub struct ProviderRequest {
+    pub system_prompt: Option<String>,
+    pub user_message: String,
+    /// Validated images for the new message
+    pub attachments: Vec<Attachment>,
+    pub history: Vec<ChatMessage>,
+    /// OpenAI-style `response_format` asking for JSON, see `structured::OutputSchema`
+    pub response_format: Option<serde_json::Value>,
+}
+
+impl ProviderRequest {
+    /// Builds a request, trimming `history` to fit the provider's context window
+    pub fn new(
+        config: &ProviderConfig,
+        user_message: String,
+        system_prompt: Option<String>,
+        attachments: Vec<Attachment>,
+        history: Vec<ChatMessage>,
+    ) -> Self {
+        let fixed_tokens = request_tokens(system_prompt.as_deref(), &user_message, attachments.len());
+        let context_window = config.context_window.unwrap_or(DEFAULT_CONTEXT_WINDOW);
+
+        Self {
+            history: fit_history(history, context_window, fixed_tokens),
+            system_prompt,
+            user_message,
+            attachments,
+            response_format: None,
+        }
+    }
+
+    /// Asks for structured output; each client maps it to its provider's own option
+    pub fn with_response_format(mut self, response_format: serde_json::Value) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+}
+
+/// Streams a chat completion from the configured provider, calling `on_delta` for every text
+/// fragment as it arrives. Returns the full response text.
+pub async fn stream_chat(
+    client: &reqwest::Client,
+    config: &ProviderConfig,
+    api_key: Option<&str>,
+    request: &ProviderRequest,
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<String, AppError> {
+    let builder = match config.kind {
+        ProviderKind::OpenAiCompatible => openai::build_request(client, config, api_key, request),
+        ProviderKind::Anthropic => anthropic::build_request(client, config, api_key, request)?,
+    };
+
+    let response = trace::send("provider", builder)
+        .await
+        .map_err(|e| AppError::from(e).context("Failed to reach provider"))?;
+
+    if !response.status().is_success() {
+        return Err(AppError::from_failed_response(response).await);
+    }
+
+    read_stream(response, config.kind.extractor(), on_delta).await
+}
+
+// Keys saved before the secret store existed, one keychain entry per provider
+fn legacy_keychain_entry(provider_id: &str) -> Result<keyring::Entry, keyring::Error> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, &format!("provider_api_key:{}", provider_id))
+}
+
+/// Reads a provider API key from the secret store. Local runtimes such as Ollama
+/// don't need one, so a missing entry is not an error.
+pub fn load_api_key(store: &SecretStore, provider_id: &str) -> Result<Option<String>, AppError> {
+    if let Some(api_key) = store.get(PROVIDER_KEYS, provider_id)? {
+        return Ok(Some(api_key));
+    }
+
+    // The keychain can't list entries, so older keys move over the first time they're used
+    let Ok(entry) = legacy_keychain_entry(provider_id) else {
+        return Ok(None);
+    };
+    match entry.get_password() {
+        Ok(api_key) => {
+            store.set(PROVIDER_KEYS, provider_id, &api_key)?;
+            if let Err(e) = entry.delete_credential() {
+                eprintln!("Failed to remove migrated API key from keychain: {}", e);
+            }
+            Ok(Some(api_key))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// The key saved for `provider_id`, if `url` goes to the origin it was saved for. Keys saved
+/// before they were tied to an origin have to be saved again.
+pub fn api_key_for(
+    app_data_dir: &Path,
+    store: &SecretStore,
+    provider_id: &str,
+    url: &str,
+) -> Result<Option<String>, AppError> {
+    let Some(api_key) = load_api_key(store, provider_id)? else {
+        return Ok(None);
+    };
+    let origin = origin_of(url)?;
+    match load_settings(app_data_dir)?.key_origins.remove(provider_id) {
+        Some(saved) if saved == origin => Ok(Some(api_key)),
+        Some(saved) => Err(AppError::Auth(format!(
+            "The API key of {} is only sent to {}; save it again to use it with {}",
+            provider_id, saved, origin
+        ))),
+        None => Err(AppError::Auth(format!(
+            "The API key of {} isn't tied to a host yet; please save it again in settings",
+            provider_id
+        ))),
+    }
+}
+
+/// Saves the key along with the origin of `url`, the only one it will be sent to
+pub fn store_api_key(
+    app_data_dir: &Path,
+    store: &SecretStore,
+    provider_id: &str,
+    api_key: &str,
+    url: &str,
+) -> Result<(), AppError> {
+    let origin = origin_of(url)?;
+    store.set(PROVIDER_KEYS, provider_id, api_key)?;
+    persist::update_json(&settings_path(app_data_dir), WHAT, |settings: &mut ProviderSettings| {
+        settings.key_origins.insert(provider_id.to_string(), origin);
+    })
+}
+
+pub fn delete_api_key(app_data_dir: &Path, store: &SecretStore, provider_id: &str) -> Result<(), AppError> {
+    store.delete(PROVIDER_KEYS, provider_id)?;
+    if let Ok(entry) = legacy_keychain_entry(provider_id) {
+        let _ = entry.delete_credential();
+    }
+    persist::update_json(&settings_path(app_data_dir), WHAT, |settings: &mut ProviderSettings| {
+        settings.key_origins.remove(provider_id);
+    })
+}
@@ -0,0 +1,74 @@
+// OpenAI-compatible `/chat/completions` client (OpenAI, Ollama, LM Studio, llama.cpp server, vLLM)
+use serde_json::{json, Value};
+
+use super::{ProviderConfig, ProviderRequest};
+use crate::chat::{Attachment, Role};
+
+pub fn build_request(
+    client: &reqwest::Client,
+    config: &ProviderConfig,
+    api_key: Option<&str>,
+    request: &ProviderRequest,
+) -> reqwest::RequestBuilder {
+    let url = format!("{}/chat/completions", config.base_url.trim_end_matches('/'));
+
+    let mut builder = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .json(&build_body(config, request));
+
+    if let Some(key) = api_key {
+        builder = builder.bearer_auth(key);
+    }
+
+    builder
+}
+
+fn build_body(config: &ProviderConfig, request: &ProviderRequest) -> Value {
+    let mut messages = Vec::new();
+
+    if let Some(system_prompt) = request.system_prompt.as_deref().filter(|s| !s.is_empty()) {
+        messages.push(json!({ "role": "system", "content": system_prompt }));
+    }
+
+    for message in &request.history {
+        messages.push(message_json(message.role, &message.content, &message.attachments));
+    }
+
+    messages.push(message_json(Role::User, &request.user_message, &request.attachments));
+
+    let mut body = json!({
+        "model": config.model,
+        "messages": messages,
+        "stream": true,
+    });
+
+    if let Some(max_tokens) = config.max_tokens {
+        body["max_tokens"] = json!(max_tokens);
+    }
+
+    if let Some(response_format) = &request.response_format {
+        body["response_format"] = response_format.clone();
+    }
+
+    body
+}
+
+fn message_json(role: Role, text: &str, attachments: &[Attachment]) -> Value {
+    // Plain string content when there are no images keeps text-only local models happy
+    if attachments.is_empty() {
+        return json!({ "role": role, "content": text });
+    }
+
+    let mut content = vec![json!({ "type": "text", "text": text })];
+    for attachment in attachments {
+        if let Some(caption) = &attachment.caption {
+            content.push(json!({ "type": "text", "text": caption }));
+        }
+        content.push(json!({
+            "type": "image_url",
+            "image_url": { "url": format!("data:{};base64,{}", attachment.mime_type, attachment.data) }
+        }));
+    }
+    json!({ "role": role, "content": content })
+}
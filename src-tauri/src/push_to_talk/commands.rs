@@ -0,0 +1,191 @@
+// Tauri side of push-to-talk: captures from the chosen source between the shortcut's press and
+// release, then hands the segment to the frontend in a `push-to-talk-end` event
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SampleFormat, SizedSample};
+use futures_util::StreamExt;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use tauri::async_runtime::{self, JoinHandle};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tokio::sync::oneshot;
+
+use super::{PushToTalkSegment, PushToTalkSource, Recording};
+use crate::error::AppError;
+use crate::speaker::SpeakerInput;
+
+enum Capture {
+    /// cpal streams aren't `Send`, so the microphone is kept by a thread of its own
+    Microphone { stop: mpsc::Sender<()>, thread: thread::JoinHandle<Recording> },
+    System { stop: oneshot::Sender<()>, task: JoinHandle<Recording> },
+}
+
+struct Active {
+    source: PushToTalkSource,
+    capture: Capture,
+}
+
+/// The capture running while the push-to-talk key is held
+#[derive(Default)]
+pub struct PushToTalkState(Mutex<Option<Active>>);
+
+/// Starts recording from `source`; a failure is reported as an empty segment
+pub fn start_push_to_talk<R: Runtime>(app: &AppHandle<R>, source: PushToTalkSource) {
+    let state = app.state::<PushToTalkState>();
+    let mut active = state.0.lock().unwrap();
+    // A held key repeats its press on some platforms
+    if active.is_some() {
+        return;
+    }
+
+    let capture = match source {
+        PushToTalkSource::Microphone => start_microphone(),
+        PushToTalkSource::System => start_system(app),
+    };
+    match capture {
+        Ok(capture) => {
+            *active = Some(Active { source, capture });
+            if let Err(e) = app.emit("push-to-talk-start", source) {
+                eprintln!("Failed to emit push-to-talk start event: {}", e);
+            }
+        }
+        Err(e) => emit_end(app, failed(source, e)),
+    }
+}
+
+/// Stops the recording and sends it on, if one is running
+pub fn stop_push_to_talk<R: Runtime>(app: &AppHandle<R>) {
+    let Some(Active { source, capture }) = app.state::<PushToTalkState>().0.lock().unwrap().take() else {
+        return;
+    };
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let segment = finish(capture).await.and_then(|recording| {
+            let duration_ms = recording.duration_ms();
+            let audio_base64 = recording.into_wav_base64()?;
+            Ok(PushToTalkSegment { source, audio_base64, duration_ms, error: None })
+        });
+        emit_end(&app, segment.unwrap_or_else(|e| failed(source, e)));
+    });
+}
+
+fn failed(source: PushToTalkSource, error: AppError) -> PushToTalkSegment {
+    PushToTalkSegment { source, audio_base64: None, duration_ms: 0, error: Some(error.message()) }
+}
+
+fn emit_end<R: Runtime>(app: &AppHandle<R>, segment: PushToTalkSegment) {
+    if let Err(e) = app.emit("push-to-talk-end", segment) {
+        eprintln!("Failed to emit push-to-talk end event: {}", e);
+    }
+}
+
+async fn finish(capture: Capture) -> Result<Recording, AppError> {
+    match capture {
+        Capture::Microphone { stop, thread } => {
+            let _ = stop.send(());
+            async_runtime::spawn_blocking(move || thread.join())
+                .await
+                .ok()
+                .and_then(Result::ok)
+                .ok_or_else(|| AppError::AudioDevice("Microphone capture stopped unexpectedly".to_string()))
+        }
+        Capture::System { stop, task } => {
+            let _ = stop.send(());
+            task.await
+                .map_err(|e| AppError::AudioDevice(format!("System audio capture stopped unexpectedly: {}", e)))
+        }
+    }
+}
+
+fn start_microphone() -> Result<Capture, AppError> {
+    let (stop, stopped) = mpsc::channel();
+    let (ready, opened) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        let (stream, recording) = match open_microphone() {
+            Ok(opened) => opened,
+            Err(e) => {
+                let _ = ready.send(Err(e));
+                return Recording::new(0);
+            }
+        };
+        let _ = ready.send(Ok(()));
+        let _ = stopped.recv();
+        drop(stream);
+        let mut recording = recording.lock().unwrap();
+        std::mem::replace(&mut *recording, Recording::new(0))
+    });
+
+    opened
+        .recv()
+        .unwrap_or_else(|_| Err(AppError::AudioDevice("Microphone capture stopped unexpectedly".to_string())))?;
+    Ok(Capture::Microphone { stop, thread })
+}
+
+fn open_microphone() -> Result<(cpal::Stream, Arc<Mutex<Recording>>), AppError> {
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or_else(|| AppError::AudioDevice("No microphone found".to_string()))?;
+    let config = device
+        .default_input_config()
+        .map_err(|e| AppError::AudioDevice(format!("Failed to read microphone settings: {}", e)))?;
+    let channels = config.channels();
+    let recording = Arc::new(Mutex::new(Recording::new(config.sample_rate().0)));
+
+    let stream = match config.sample_format() {
+        SampleFormat::F32 => build_input::<f32>(&device, &config.into(), channels, recording.clone()),
+        SampleFormat::I16 => build_input::<i16>(&device, &config.into(), channels, recording.clone()),
+        SampleFormat::U16 => build_input::<u16>(&device, &config.into(), channels, recording.clone()),
+        format => Err(AppError::AudioDevice(format!("Unsupported microphone sample format: {:?}", format))),
+    }?;
+    stream
+        .play()
+        .map_err(|e| AppError::AudioDevice(format!("Failed to start microphone: {}", e)))?;
+    Ok((stream, recording))
+}
+
+fn build_input<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: u16,
+    recording: Arc<Mutex<Recording>>,
+) -> Result<cpal::Stream, AppError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let samples: Vec<f32> = data.iter().map(|sample| sample.to_sample::<f32>()).collect();
+                recording.lock().unwrap().push_interleaved(&samples, channels);
+            },
+            |e| eprintln!("Microphone stream error: {}", e),
+            None,
+        )
+        .map_err(|e| AppError::AudioDevice(format!("Failed to open microphone: {}", e)))
+}
+
+fn start_system<R: Runtime>(app: &AppHandle<R>) -> Result<Capture, AppError> {
+    // Both would read the same device, and the running capture already sends what it hears
+    if app.state::<crate::AudioState>().stream_task.lock().unwrap().is_some() {
+        return Err(AppError::AudioDevice("System audio capture is already running".to_string()));
+    }
+
+    let mut stream = SpeakerInput::new().map_err(|e| AppError::AudioDevice(e.to_string()))?.stream();
+    let mut recording = Recording::new(stream.sample_rate());
+    let (stop, mut stopped) = oneshot::channel();
+    let task = async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = &mut stopped => break,
+                sample = stream.next() => match sample {
+                    Some(sample) => recording.push(sample),
+                    None => break,
+                },
+            }
+        }
+        recording
+    });
+    Ok(Capture::System { stop, task })
+}
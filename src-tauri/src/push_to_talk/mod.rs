@@ -0,0 +1,98 @@
+// Push-to-talk: while the shortcut is held, audio from the microphone or the system output is
+// recorded as is, without speech detection, and on release the whole segment goes to
+// transcription and chat. Noisy rooms keep automatic detection from ever ending a segment; here
+// the key decides. Capturing happens in the commands, this part collects and encodes samples.
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+use crate::error::AppError;
+
+mod commands;
+
+pub use commands::*;
+
+/// Shorter segments are taken for an accidental tap and dropped
+pub const MIN_SEGMENT_MS: u64 = 300;
+/// Recording stops growing past this, a key stuck down shouldn't fill memory
+pub const MAX_SEGMENT_SECS: u64 = 120;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PushToTalkSource {
+    #[default]
+    Microphone,
+    /// What the speakers play, e.g. the other side of a call
+    System,
+}
+
+/// Mono samples recorded while the key is held
+#[derive(Debug)]
+pub struct Recording {
+    sample_rate: u32,
+    samples: Vec<f32>,
+}
+
+impl Recording {
+    pub fn new(sample_rate: u32) -> Self {
+        Self { sample_rate, samples: Vec::new() }
+    }
+
+    fn is_full(&self) -> bool {
+        self.samples.len() as u64 >= self.sample_rate as u64 * MAX_SEGMENT_SECS
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        if !self.is_full() {
+            self.samples.push(sample);
+        }
+    }
+
+    /// Appends interleaved frames of `channels` channels, averaged down to mono
+    pub fn push_interleaved(&mut self, data: &[f32], channels: u16) {
+        let channels = channels.max(1) as usize;
+        for frame in data.chunks_exact(channels) {
+            self.push(frame.iter().sum::<f32>() / channels as f32);
+        }
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        if self.sample_rate == 0 {
+            return 0;
+        }
+        self.samples.len() as u64 * 1000 / self.sample_rate as u64
+    }
+
+    /// The segment as a base64 WAV, `None` for a tap too short to hold speech
+    pub fn into_wav_base64(self) -> Result<Option<String>, AppError> {
+        if self.duration_ms() < MIN_SEGMENT_MS {
+            return Ok(None);
+        }
+        wav_base64(self.sample_rate, &self.samples).map(Some)
+    }
+}
+
+/// 16-bit mono WAV of `samples`, base64 encoded as the transcription commands take it
+pub fn wav_base64(sample_rate: u32, samples: &[f32]) -> Result<String, AppError> {
+    let encode = |e: hound::Error| AppError::AudioDevice(format!("Failed to encode audio: {}", e));
+    let spec = WavSpec { channels: 1, sample_rate, bits_per_sample: 16, sample_format: SampleFormat::Int };
+
+    let mut cursor = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut cursor, spec).map_err(encode)?;
+    for &sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).map_err(encode)?;
+    }
+    writer.finalize().map_err(encode)?;
+    Ok(B64.encode(cursor.into_inner()))
+}
+
+/// Payload of the `push-to-talk-end` event
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct PushToTalkSegment {
+    pub source: PushToTalkSource,
+    /// `None` when the key was only tapped or capture failed
+    pub audio_base64: Option<String>,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
@@ -0,0 +1,132 @@
+// Retries for backend calls: exponential backoff with full jitter, honouring `Retry-After`
+// on 429/503 responses. Only use this for requests that are safe to send twice, or, for chat,
+// for the part before the response body starts streaming.
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+
+use crate::trace;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Retry-After values above this are treated as "give up now" rather than waited out
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            max_retry_after: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Payload of the `request_retry` event
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryEvent<'a> {
+    pub operation: &'a str,
+    /// The attempt that is about to be made, starting at 2
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay_ms: u64,
+    pub reason: String,
+}
+
+/// Sends the request built by `build`, retrying on connection failures, timeouts and
+/// 408/429/502/503/504 responses. `on_retry` is called before every wait.
+///
+/// The last response is returned as-is once attempts run out, so callers keep their
+/// usual handling of non-success statuses.
+pub async fn send_with_retry<F>(
+    operation: &str,
+    policy: &RetryPolicy,
+    build: F,
+    on_retry: &(dyn Fn(&RetryEvent) + Send + Sync),
+) -> Result<Response, reqwest::Error>
+where
+    F: Fn() -> RequestBuilder,
+{
+    let mut attempt = 1;
+
+    loop {
+        let result = trace::send(operation, build()).await;
+        let last_attempt = attempt >= policy.max_attempts;
+
+        let (delay, reason) = match &result {
+            Ok(response) if is_retryable_status(response.status()) && !last_attempt => {
+                let backoff = backoff_delay(policy, attempt);
+                match retry_after(response) {
+                    Some(wait) if wait > policy.max_retry_after => return result,
+                    Some(wait) => (wait, format!("Server responded {}", response.status())),
+                    None => (backoff, format!("Server responded {}", response.status())),
+                }
+            }
+            Err(e) if is_retryable_error(e) && !last_attempt => {
+                (backoff_delay(policy, attempt), describe_error(e))
+            }
+            _ => return result,
+        };
+
+        attempt += 1;
+        on_retry(&RetryEvent {
+            operation,
+            attempt,
+            max_attempts: policy.max_attempts,
+            delay_ms: delay.as_millis() as u64,
+            reason,
+        });
+        tokio::time::sleep(delay).await;
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+// Connection failures and timeouts only; other request errors (a body that failed to build, a
+// bad redirect) would fail the same way again
+fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout()
+}
+
+fn describe_error(error: &reqwest::Error) -> String {
+    if error.is_timeout() {
+        "Request timed out".to_string()
+    } else {
+        "Connection failed".to_string()
+    }
+}
+
+// Full jitter: a random delay between zero and the exponential cap
+fn backoff_delay(policy: &RetryPolicy, attempt: u32) -> Duration {
+    let exponential = policy.base_delay.saturating_mul(2u32.saturating_pow(attempt - 1));
+    let cap = exponential.min(policy.max_delay).as_millis() as u64;
+    Duration::from_millis(rand::thread_rng().gen_range(0..=cap))
+}
+
+// `Retry-After` is either a number of seconds or an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+}
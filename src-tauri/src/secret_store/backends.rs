@@ -0,0 +1,217 @@
+// Where secrets are kept: the OS credential store, an encrypted file, or memory. Backends only
+// see flat keys; namespaces, validation and the index live in SecretStore.
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::StoreKind;
+use crate::error::AppError;
+use crate::persist::{self, Locked};
+
+// Service name used for every entry this app stores in the OS keychain
+pub(crate) const KEYCHAIN_SERVICE: &str = "pluely";
+const ENCRYPTED_FILE: &str = "secure_storage.enc";
+const KEY_FILE: &str = "secure_storage.key";
+const WHAT: &str = "encrypted storage";
+
+pub trait SecretBackend: Send + Sync {
+    fn kind(&self) -> StoreKind;
+    fn get(&self, key: &str) -> Result<Option<String>, AppError>;
+    fn set(&self, key: &str, value: &str) -> Result<(), AppError>;
+    fn delete(&self, key: &str) -> Result<(), AppError>;
+}
+
+fn keychain_error(context: &str, e: keyring::Error) -> AppError {
+    match e {
+        keyring::Error::NoStorageAccess(_) => AppError::Permission(format!("{}: {}", context, e)),
+        _ => AppError::Storage(format!("{}: {}", context, e)),
+    }
+}
+
+/// The OS credential store, one entry per key
+pub struct Keychain;
+
+impl Keychain {
+    fn entry(key: &str) -> Result<keyring::Entry, AppError> {
+        keyring::Entry::new(KEYCHAIN_SERVICE, &format!("secure_storage:{}", key))
+            .map_err(|e| keychain_error("Failed to open keychain entry", e))
+    }
+
+    /// Whether the credential store answers at all; a missing entry means it does
+    pub fn is_available() -> bool {
+        match Self::entry("probe").map(|entry| entry.get_password()) {
+            Ok(Ok(_)) | Ok(Err(keyring::Error::NoEntry)) => true,
+            Ok(Err(e)) => {
+                eprintln!("OS credential store unavailable: {}", e);
+                false
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+impl SecretBackend for Keychain {
+    fn kind(&self) -> StoreKind {
+        StoreKind::Keychain
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        match Self::entry(key)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keychain_error("Failed to read from keychain", e)),
+        }
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), AppError> {
+        Self::entry(key)?
+            .set_password(value)
+            .map_err(|e| keychain_error("Failed to store in keychain", e))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), AppError> {
+        match Self::entry(key)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(keychain_error("Failed to remove from keychain", e)),
+        }
+    }
+}
+
+/// On-disk layout of the encrypted store
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedEnvelope {
+    version: u32,
+    /// Base64 XChaCha20-Poly1305 nonce, fresh for every write
+    nonce: String,
+    /// Base64 ciphertext of the JSON object holding every value
+    ciphertext: String,
+}
+
+/// Values encrypted with XChaCha20-Poly1305 under a random key generated on first write
+pub struct EncryptedFile {
+    path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl EncryptedFile {
+    pub fn new(app_data_dir: &Path) -> Self {
+        Self {
+            path: app_data_dir.join(ENCRYPTED_FILE),
+            key_path: app_data_dir.join(KEY_FILE),
+        }
+    }
+
+    fn cipher(&self, create: bool) -> Result<Option<XChaCha20Poly1305>, AppError> {
+        if self.key_path.exists() {
+            let encoded = fs::read_to_string(&self.key_path)
+                .map_err(|e| AppError::from(e).context("Failed to read storage key"))?;
+            let key = base64::engine::general_purpose::STANDARD
+                .decode(encoded.trim())
+                .ok()
+                .filter(|key| key.len() == 32)
+                .ok_or_else(|| AppError::Storage("Storage key is corrupted".to_string()))?;
+            return Ok(Some(XChaCha20Poly1305::new(Key::from_slice(&key))));
+        }
+        if !create {
+            return Ok(None);
+        }
+
+        let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+        persist::lock(&self.key_path, "storage key")?
+            .write_private(base64::engine::general_purpose::STANDARD.encode(key).as_bytes(), "storage key")?;
+        Ok(Some(XChaCha20Poly1305::new(&key)))
+    }
+
+    fn decrypt(&self, envelope: Option<EncryptedEnvelope>) -> Result<BTreeMap<String, String>, AppError> {
+        let Some(envelope) = envelope else {
+            return Ok(BTreeMap::new());
+        };
+        let cipher = self
+            .cipher(false)?
+            .ok_or_else(|| AppError::Storage("Storage key is missing, stored credentials can't be read".to_string()))?;
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let nonce = engine.decode(&envelope.nonce).ok().filter(|n| n.len() == 24);
+        let ciphertext = engine.decode(&envelope.ciphertext).ok();
+        let plaintext = nonce
+            .zip(ciphertext)
+            .and_then(|(nonce, ciphertext)| cipher.decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice()).ok())
+            .ok_or_else(|| AppError::Storage("Encrypted storage is corrupted or was written with another key".to_string()))?;
+
+        serde_json::from_slice(&plaintext).map_err(|e| AppError::from(e).context("Failed to parse encrypted storage"))
+    }
+
+    // Only with the file locked, the key is created under the same lock
+    fn write(&self, locked: &Locked, values: &BTreeMap<String, String>) -> Result<(), AppError> {
+        let cipher = self.cipher(true)?.expect("cipher is created on write");
+        let plaintext = serde_json::to_vec(values)
+            .map_err(|e| AppError::from(e).context("Failed to serialize storage"))?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| AppError::Storage("Failed to encrypt storage".to_string()))?;
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let envelope = EncryptedEnvelope { version: 1, nonce: engine.encode(nonce), ciphertext: engine.encode(ciphertext) };
+        let content = serde_json::to_vec(&envelope)
+            .map_err(|e| AppError::from(e).context("Failed to serialize storage"))?;
+        locked.write_private(&content, WHAT)
+    }
+}
+
+impl SecretBackend for EncryptedFile {
+    fn kind(&self) -> StoreKind {
+        StoreKind::EncryptedFile
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        Ok(self.decrypt(persist::read_json(&self.path, WHAT)?)?.remove(key))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), AppError> {
+        let locked = persist::lock(&self.path, WHAT)?;
+        let mut values = self.decrypt(locked.read_json(WHAT)?)?;
+        values.insert(key.to_string(), value.to_string());
+        self.write(&locked, &values)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), AppError> {
+        let locked = persist::lock(&self.path, WHAT)?;
+        let mut values = self.decrypt(locked.read_json(WHAT)?)?;
+        if values.remove(key).is_some() {
+            self.write(&locked, &values)?;
+        }
+        Ok(())
+    }
+}
+
+/// Values kept in process memory only
+#[derive(Default)]
+pub struct MemoryBackend {
+    values: Mutex<BTreeMap<String, String>>,
+}
+
+impl SecretBackend for MemoryBackend {
+    fn kind(&self) -> StoreKind {
+        StoreKind::Memory
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), AppError> {
+        self.values.lock().unwrap().insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), AppError> {
+        self.values.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
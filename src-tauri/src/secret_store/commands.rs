@@ -0,0 +1,91 @@
+// Tauri commands behind the frontend's secure storage, backed by the managed SecretStore
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Manager, State};
+
+use super::{Namespace, SecretStore, StoreKind, NAMESPACES};
+use crate::error::AppError;
+
+/// Opens the store and manages it. Without an app data directory the store lives in memory,
+/// so the app still starts and the user is only asked to activate again.
+pub fn setup(app: &AppHandle) {
+    let store = match app.path().app_data_dir() {
+        Ok(dir) => SecretStore::open(&dir),
+        Err(e) => {
+            eprintln!("Failed to get app data directory, credentials won't be saved: {}", e);
+            SecretStore::in_memory()
+        }
+    };
+    app.manage(store);
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StorageItem {
+    key: String,
+    value: String,
+}
+
+// Every item is validated before anything is written, so a bad key doesn't leave half a save
+#[tauri::command]
+pub fn secure_storage_save(store: State<'_, SecretStore>, namespace: String, items: Vec<StorageItem>) -> Result<(), AppError> {
+    let schema = super::namespace(&namespace)?;
+    for item in &items {
+        schema.check_key(&item.key)?;
+        schema.check_value(&item.value)?;
+    }
+    for item in items {
+        store.set(&namespace, &item.key, &item.value)?;
+    }
+    Ok(())
+}
+
+/// Values of `keys` that are set; every key of the namespace when `keys` is omitted. Only for
+/// readable namespaces, API keys never go back to the webview.
+#[tauri::command]
+pub fn secure_storage_get(
+    store: State<'_, SecretStore>,
+    namespace: String,
+    keys: Option<Vec<String>>,
+) -> Result<BTreeMap<String, String>, AppError> {
+    super::namespace(&namespace)?.check_readable()?;
+    let keys = match keys {
+        Some(keys) => keys,
+        None => store.list(&namespace)?,
+    };
+    let mut values = BTreeMap::new();
+    for key in keys {
+        if let Some(value) = store.get(&namespace, &key)? {
+            values.insert(key, value);
+        }
+    }
+    Ok(values)
+}
+
+#[tauri::command]
+pub fn secure_storage_remove(store: State<'_, SecretStore>, namespace: String, keys: Vec<String>) -> Result<(), AppError> {
+    let schema = super::namespace(&namespace)?;
+    for key in &keys {
+        schema.check_key(key)?;
+    }
+    for key in keys {
+        store.delete(&namespace, &key)?;
+    }
+    Ok(())
+}
+
+/// Key names only, values stay in the store
+#[tauri::command]
+pub fn secure_storage_list(store: State<'_, SecretStore>, namespace: String) -> Result<Vec<String>, AppError> {
+    store.list(&namespace)
+}
+
+#[tauri::command]
+pub fn get_secure_storage_schema() -> &'static [Namespace] {
+    &NAMESPACES
+}
+
+/// Lets settings tell the user whether credentials are in the OS store or the encrypted file
+#[tauri::command]
+pub fn get_secure_storage_kind(store: State<'_, SecretStore>) -> StoreKind {
+    store.kind()
+}
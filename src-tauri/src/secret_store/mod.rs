@@ -0,0 +1,336 @@
+// The credentials the app uses from Rust: the Pluely license, API keys of AI providers, access
+// keys of self-hosted backends. Speech-to-text requests are still sent by the webview with keys
+// from its own settings, so there is no namespace for them. Values are grouped in namespaces,
+// each with a schema saying which keys it takes, so a typo in the frontend fails loudly instead
+// of writing a secret nobody reads.
+//
+// Values go to the OS credential store (Keychain, Credential Manager, Secret Service) when
+// there is one; on systems without it (a Linux session with no Secret Service running) they go
+// to a file encrypted with a per-install key. The key sits next to the file, readable by the
+// user only, so the file mainly keeps secrets out of backups and plain-text searches.
+//
+// Credential stores can't enumerate their entries, so the store keeps an index of key names
+// (never values) next to them. The index also records the schema version; `MIGRATIONS` bring
+// stores written by older versions up to date when the store opens.
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::error::AppError;
+
+mod backends;
+mod commands;
+
+pub use backends::{EncryptedFile, Keychain, MemoryBackend, SecretBackend};
+pub(crate) use backends::KEYCHAIN_SERVICE;
+pub use commands::*;
+
+const PLAINTEXT_FILE: &str = "secure_storage.json";
+// Backend entry holding the index; namespaced entries always contain a '/'
+const INDEX_KEY: &str = "_index";
+
+/// The activated Pluely license and the model picked for it
+pub const LICENSE: &str = "license";
+pub const LICENSE_KEY: &str = "license_key";
+pub const INSTANCE_ID: &str = "instance_id";
+pub const SELECTED_MODEL: &str = "selected_model";
+pub const LICENSE_TOKEN: &str = "license_token";
+/// Why the payment service last refused to validate the license, until it's activated again
+pub const LICENSE_REVOKED: &str = "license_revoked";
+/// API keys of AI providers, by provider id
+pub const PROVIDER_KEYS: &str = "provider_keys";
+/// Access keys of self-hosted backends, by endpoint profile name
+pub const ENDPOINT_KEYS: &str = "endpoint_keys";
+
+/// Where a store keeps its values
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreKind {
+    Keychain,
+    EncryptedFile,
+    /// Process memory only, when there is nowhere to save
+    Memory,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "type", content = "names", rename_all = "snake_case")]
+pub enum KeyRule {
+    /// Only these keys
+    Fixed(&'static [&'static str]),
+    /// Any id of 1 to 128 letters, digits, `_`, `-`, `.` or `:`
+    Identifier,
+}
+
+/// Schema of one namespace
+#[derive(Debug, Serialize)]
+pub struct Namespace {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub keys: KeyRule,
+    pub max_value_bytes: usize,
+    /// Whether the frontend may read values back. API keys are only ever used from Rust, the
+    /// webview can just ask which ones are set.
+    pub readable: bool,
+}
+
+pub static NAMESPACES: [Namespace; 3] = [
+    Namespace {
+        name: LICENSE,
+        description: "Pluely license, its signed token and the selected Pluely model",
+        keys: KeyRule::Fixed(&[LICENSE_KEY, INSTANCE_ID, SELECTED_MODEL, LICENSE_TOKEN, LICENSE_REVOKED]),
+        max_value_bytes: 16 * 1024,
+        readable: true,
+    },
+    Namespace {
+        name: PROVIDER_KEYS,
+        description: "API keys of AI providers",
+        keys: KeyRule::Identifier,
+        max_value_bytes: 4 * 1024,
+        readable: false,
+    },
+    Namespace {
+        name: ENDPOINT_KEYS,
+        description: "Access keys of self-hosted backends",
+        keys: KeyRule::Identifier,
+        max_value_bytes: 4 * 1024,
+        readable: false,
+    },
+];
+
+/// Looks up a namespace by name
+pub fn namespace(name: &str) -> Result<&'static Namespace, AppError> {
+    NAMESPACES
+        .iter()
+        .find(|n| n.name == name)
+        .ok_or_else(|| AppError::Config(format!("Unknown secret namespace: {}", name)))
+}
+
+impl Namespace {
+    pub fn check_key(&self, key: &str) -> Result<(), AppError> {
+        let valid = match self.keys {
+            KeyRule::Fixed(names) => names.contains(&key),
+            KeyRule::Identifier => {
+                (1..=128).contains(&key.len())
+                    && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(AppError::Config(format!("Invalid key for {}: {}", self.name, key)))
+        }
+    }
+
+    pub fn check_readable(&self) -> Result<(), AppError> {
+        if self.readable {
+            Ok(())
+        } else {
+            Err(AppError::Config(format!("Values of {} can't be read back, only listed", self.name)))
+        }
+    }
+
+    pub fn check_value(&self, value: &str) -> Result<(), AppError> {
+        if value.is_empty() {
+            return Err(AppError::Config(format!("Empty value for {}", self.name)));
+        }
+        if value.len() > self.max_value_bytes {
+            return Err(AppError::Config(format!(
+                "Value for {} is too long, at most {} bytes",
+                self.name, self.max_value_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Index {
+    /// Number of `MIGRATIONS` applied
+    version: u32,
+    keys: BTreeMap<String, BTreeSet<String>>,
+}
+
+pub struct SecretStore {
+    backend: Box<dyn SecretBackend>,
+    // Serializes index updates between commands
+    index_lock: Mutex<()>,
+}
+
+impl SecretStore {
+    /// The OS credential store if it answers, the encrypted file otherwise
+    pub fn open(app_data_dir: &Path) -> Self {
+        let backend: Box<dyn SecretBackend> = if Keychain::is_available() {
+            Box::new(Keychain)
+        } else {
+            eprintln!("No OS credential store available, using an encrypted file");
+            Box::new(EncryptedFile::new(app_data_dir))
+        };
+        Self::with_backend(backend, app_data_dir)
+    }
+
+    /// Runs pending migrations; a failed one is logged and retried on the next start
+    pub fn with_backend(backend: Box<dyn SecretBackend>, app_data_dir: &Path) -> Self {
+        let store = Self { backend, index_lock: Mutex::new(()) };
+        if let Err(e) = migrate(&store, app_data_dir) {
+            eprintln!("Failed to migrate secret store: {}", e);
+        }
+        store
+    }
+
+    /// Store that forgets everything on exit
+    pub fn in_memory() -> Self {
+        Self { backend: Box::new(MemoryBackend::default()), index_lock: Mutex::new(()) }
+    }
+
+    pub fn kind(&self) -> StoreKind {
+        self.backend.kind()
+    }
+
+    pub fn get(&self, namespace_name: &str, key: &str) -> Result<Option<String>, AppError> {
+        namespace(namespace_name)?.check_key(key)?;
+        self.backend.get(&entry_key(namespace_name, key))
+    }
+
+    pub fn set(&self, namespace_name: &str, key: &str, value: &str) -> Result<(), AppError> {
+        let namespace = namespace(namespace_name)?;
+        namespace.check_key(key)?;
+        namespace.check_value(value)?;
+
+        self.backend.set(&entry_key(namespace_name, key), value)?;
+        self.update_index(|index| {
+            index.keys.entry(namespace_name.to_string()).or_default().insert(key.to_string())
+        })?;
+        Ok(())
+    }
+
+    /// Removing a missing key is not an error
+    pub fn delete(&self, namespace_name: &str, key: &str) -> Result<(), AppError> {
+        namespace(namespace_name)?.check_key(key)?;
+        self.backend.delete(&entry_key(namespace_name, key))?;
+        self.update_index(|index| {
+            let removed = index.keys.get_mut(namespace_name).is_some_and(|keys| keys.remove(key));
+            index.keys.retain(|_, keys| !keys.is_empty());
+            removed
+        })?;
+        Ok(())
+    }
+
+    /// Names of the keys set in a namespace, sorted; never the values
+    pub fn list(&self, namespace_name: &str) -> Result<Vec<String>, AppError> {
+        namespace(namespace_name)?;
+        let index = self.read_index()?;
+        Ok(index.keys.get(namespace_name).map(|keys| keys.iter().cloned().collect()).unwrap_or_default())
+    }
+
+    /// Number of migrations this store has been through
+    pub fn schema_version(&self) -> Result<u32, AppError> {
+        Ok(self.read_index()?.version)
+    }
+
+    fn read_index(&self) -> Result<Index, AppError> {
+        match self.backend.get(INDEX_KEY)? {
+            Some(json) => serde_json::from_str(&json).map_err(|e| AppError::from(e).context("Failed to parse secret index")),
+            None => Ok(Index::default()),
+        }
+    }
+
+    // `update` returns whether it changed anything, to skip the write otherwise
+    fn update_index(&self, update: impl FnOnce(&mut Index) -> bool) -> Result<(), AppError> {
+        let _guard = self.index_lock.lock().unwrap();
+        let mut index = self.read_index()?;
+        if !update(&mut index) {
+            return Ok(());
+        }
+        let json = serde_json::to_string(&index).map_err(|e| AppError::from(e).context("Failed to serialize secret index"))?;
+        self.backend.set(INDEX_KEY, &json)
+    }
+}
+
+fn entry_key(namespace: &str, key: &str) -> String {
+    format!("{}/{}", namespace, key)
+}
+
+type Migration = fn(&SecretStore, &Path) -> Result<(), AppError>;
+
+/// Applied in order, each exactly once per store. Append only: a store's version is the
+/// number of entries it has been through.
+const MIGRATIONS: [Migration; 2] = [
+    // 1: license values were stored under flat keys before namespaces existed
+    migrate_flat_license_keys,
+    // 2: before the store, the license was a plain JSON file in the app data directory
+    migrate_plaintext_file,
+];
+
+/// The version of a store with every migration applied
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Brings the store to `SCHEMA_VERSION`, recording progress after every step so a failure
+/// resumes where it stopped. Returns how many migrations ran.
+pub fn migrate(store: &SecretStore, app_data_dir: &Path) -> Result<u32, AppError> {
+    let start = store.schema_version()?;
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(start as usize) {
+        migration(store, app_data_dir)?;
+        store.update_index(|index| {
+            index.version = version as u32 + 1;
+            true
+        })?;
+    }
+    Ok(SCHEMA_VERSION.saturating_sub(start))
+}
+
+// Values written after the old copy win, so a migration never overwrites
+fn move_value(store: &SecretStore, namespace: &str, key: &str, value: String) -> Result<(), AppError> {
+    if store.get(namespace, key)?.is_none() {
+        store.set(namespace, key, &value)?;
+    }
+    Ok(())
+}
+
+fn migrate_flat_license_keys(store: &SecretStore, _app_data_dir: &Path) -> Result<(), AppError> {
+    for (old_key, key) in [
+        ("pluely_license_key", LICENSE_KEY),
+        ("pluely_instance_id", INSTANCE_ID),
+        ("selected_pluely_model", SELECTED_MODEL),
+    ] {
+        if let Some(value) = store.backend.get(old_key)? {
+            move_value(store, LICENSE, key, value)?;
+            store.backend.delete(old_key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Layout of `secure_storage.json` before credentials moved to the store
+#[derive(Debug, Deserialize, Default)]
+struct PlaintextStorage {
+    license_key: Option<String>,
+    instance_id: Option<String>,
+    selected_pluely_model: Option<String>,
+}
+
+fn migrate_plaintext_file(store: &SecretStore, app_data_dir: &Path) -> Result<(), AppError> {
+    let path = app_data_dir.join(PLAINTEXT_FILE);
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| AppError::from(e).context("Failed to read storage file"))?;
+    let plaintext: PlaintextStorage = serde_json::from_str(&content)
+        .map_err(|e| AppError::from(e).context("Failed to parse storage file"))?;
+
+    for (key, value) in [
+        (LICENSE_KEY, plaintext.license_key),
+        (INSTANCE_ID, plaintext.instance_id),
+        (SELECTED_MODEL, plaintext.selected_pluely_model),
+    ] {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            move_value(store, LICENSE, key, value)?;
+        }
+    }
+
+    // Only once every value is safely stored
+    fs::remove_file(&path).map_err(|e| AppError::from(e).context("Failed to remove plaintext storage file"))
+}
@@ -0,0 +1,438 @@
+// Tauri side of the global shortcuts: registering the bound accelerators with the OS and
+// dispatching presses to the main window
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, Runtime, Emitter};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use serde_json::json;
+use std::sync::Mutex;
+
+use super::{RegistrationState, ShortcutAction, ShortcutSettings, ShortcutStatus};
+use crate::error::AppError;
+use crate::push_to_talk::{self, PushToTalkSource};
+
+// State for window visibility
+pub struct WindowVisibility(pub Mutex<bool>);
+
+#[derive(Default)]
+pub struct Registrations {
+    /// The shortcut each action is registered with
+    pub shortcuts: BTreeMap<ShortcutAction, Shortcut>,
+    /// How each action's saved binding fared, set at startup and on every rebind
+    pub status: BTreeMap<ShortcutAction, ShortcutStatus>,
+}
+
+/// The lock also keeps two rebinds from interleaving
+#[derive(Default)]
+pub struct ShortcutRegistry(pub Mutex<Registrations>);
+
+fn app_data_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, AppError> {
+    app.path().app_data_dir()
+        .map_err(|e| AppError::Storage(format!("Failed to get app data directory: {}", e)))
+}
+
+/// Initialize global shortcuts for the application, with the bindings saved in settings. A
+/// binding that can't be registered doesn't keep the others from working: every action gets a
+/// status, and the failures are returned together at the end.
+pub fn setup_global_shortcuts<R: Runtime>(app: &AppHandle<R>) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = match app_data_dir(app).and_then(|dir| super::load_settings(&dir)) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}, using the default shortcuts", e);
+            ShortcutSettings::default()
+        }
+    };
+    let registry = app.state::<ShortcutRegistry>();
+    let mut registrations = registry.0.lock().unwrap();
+    let mut failures = Vec::new();
+
+    for (action, binding) in super::resolve_bindings(&settings) {
+        let accelerator = settings.accelerator(action);
+        let status = match binding {
+            Err(status) => status,
+            Ok(shortcut) => match register(app, action, shortcut) {
+                Ok(()) => {
+                    registrations.shortcuts.insert(action, shortcut);
+                    ShortcutStatus::registered(accelerator)
+                }
+                Err(e) => ShortcutStatus::failed(accelerator, RegistrationState::Taken, &e),
+            },
+        };
+        if let Some(error) = &status.error {
+            failures.push(format!("{} shortcut: {}", action.label(), error));
+        }
+        registrations.status.insert(action, status);
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; ").into())
+    }
+}
+
+fn register<R: Runtime>(app: &AppHandle<R>, action: ShortcutAction, shortcut: Shortcut) -> Result<(), AppError> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _shortcut, event| match (action, event.state()) {
+            // The only action that also cares when the key goes up
+            (ShortcutAction::PushToTalk, ShortcutState::Released) => push_to_talk::stop_push_to_talk(app),
+            (_, ShortcutState::Pressed) => handle_action(app, action),
+            _ => {}
+        })
+        .map_err(|e| AppError::Config(format!("Failed to register {} shortcut {}: {}", action.label(), shortcut, e)))
+}
+
+fn handle_action<R: Runtime>(app: &AppHandle<R>, action: ShortcutAction) {
+    match action {
+        ShortcutAction::Toggle => handle_toggle_window(app),
+        ShortcutAction::Audio => handle_audio_shortcut(app),
+        ShortcutAction::Screenshot => handle_screenshot_shortcut(app),
+        ShortcutAction::SystemAudio => handle_system_audio_shortcut(app),
+        ShortcutAction::PushToTalk => handle_push_to_talk_shortcut(app),
+    }
+}
+
+/// Binds `action` to `accelerator`: the old shortcut is released, the new one registered and
+/// the choice saved. If the new one can't be registered (another app may own it) the old one is
+/// restored and the error names the action. Actions left unregistered by a conflict or taken
+/// keys get another try, since the rebind may have freed them. Returns the bindings of every
+/// action.
+#[tauri::command]
+pub fn set_shortcut<R: Runtime>(
+    app: AppHandle<R>,
+    action: ShortcutAction,
+    accelerator: String,
+) -> Result<BTreeMap<ShortcutAction, String>, AppError> {
+    let dir = app_data_dir(&app)?;
+    let registry = app.state::<ShortcutRegistry>();
+    let mut guard = registry.0.lock().unwrap();
+    let Registrations { shortcuts: registered, status } = &mut *guard;
+
+    let settings = super::load_settings(&dir)?;
+    let shortcut = super::check_binding(&settings, action, &accelerator)?;
+    let previous = registered.get(&action).copied();
+
+    if previous != Some(shortcut) {
+        if let Some(previous) = previous {
+            app.global_shortcut().unregister(previous).map_err(|e| {
+                AppError::Config(format!("Failed to unregister {} shortcut {}: {}", action.label(), previous, e))
+            })?;
+            registered.remove(&action);
+        }
+        if let Err(e) = register(&app, action, shortcut) {
+            restore(&app, registered, status, &settings, action, previous);
+            return Err(e);
+        }
+        registered.insert(action, shortcut);
+    }
+
+    if let Err(e) = super::save_binding(&dir, action, &accelerator) {
+        if previous != Some(shortcut) {
+            let _ = app.global_shortcut().unregister(shortcut);
+            registered.remove(&action);
+            restore(&app, registered, status, &settings, action, previous);
+        }
+        return Err(e);
+    }
+    status.insert(action, ShortcutStatus::registered(accelerator.trim()));
+
+    let settings = super::load_settings(&dir)?;
+    retry_pending(&app, &settings, registered, status);
+    Ok(settings.bindings())
+}
+
+// Puts back the shortcut a failed rebind released. When that fails too the action is left
+// without one, and its status says so.
+fn restore<R: Runtime>(
+    app: &AppHandle<R>,
+    registered: &mut BTreeMap<ShortcutAction, Shortcut>,
+    status: &mut BTreeMap<ShortcutAction, ShortcutStatus>,
+    settings: &ShortcutSettings,
+    action: ShortcutAction,
+    previous: Option<Shortcut>,
+) {
+    let Some(previous) = previous else { return };
+    match register(app, action, previous) {
+        Ok(()) => {
+            registered.insert(action, previous);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            status.insert(action, ShortcutStatus::failed(settings.accelerator(action), RegistrationState::Taken, &e));
+        }
+    }
+}
+
+// Registers the saved binding of every action marked `Conflict` or `Taken` whose keys no
+// registered action holds anymore
+fn retry_pending<R: Runtime>(
+    app: &AppHandle<R>,
+    settings: &ShortcutSettings,
+    registered: &mut BTreeMap<ShortcutAction, Shortcut>,
+    status: &mut BTreeMap<ShortcutAction, ShortcutStatus>,
+) {
+    for action in ShortcutAction::ALL {
+        let pending = status
+            .get(&action)
+            .is_some_and(|s| matches!(s.state, RegistrationState::Conflict | RegistrationState::Taken));
+        if !pending || registered.contains_key(&action) {
+            continue;
+        }
+        let accelerator = settings.accelerator(action);
+        let Ok(shortcut) = super::parse_accelerator(accelerator) else { continue };
+        if registered.values().any(|bound| bound.id() == shortcut.id()) {
+            continue;
+        }
+        let retried = match register(app, action, shortcut) {
+            Ok(()) => {
+                registered.insert(action, shortcut);
+                ShortcutStatus::registered(accelerator)
+            }
+            Err(e) => ShortcutStatus::failed(accelerator, RegistrationState::Taken, &e),
+        };
+        status.insert(action, retried);
+    }
+}
+
+/// Handle app toggle (hide/show) with input focus and app icon management
+fn handle_toggle_window<R: Runtime>(app: &AppHandle<R>) {
+    // Get the main window
+    let Some(window) = app.get_webview_window("main") else {
+        eprintln!("Main window not found");
+        return;
+    };
+
+    #[cfg(target_os = "windows")]
+    {
+        let state = app.state::<WindowVisibility>();
+        let mut is_hidden = state.0.lock().unwrap();
+        *is_hidden = !*is_hidden;
+
+        if let Err(e) = window.emit("toggle-window-visibility", *is_hidden) {
+            eprintln!("Failed to emit toggle-window-visibility event: {}", e);
+        }
+        return;
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    match window.is_visible() {
+        Ok(true) => {
+            // Window is visible, hide it and handle app icon based on user settings
+            if let Err(e) = window.hide() {
+                eprintln!("Failed to hide window: {}", e);
+            }
+
+         }
+        Ok(false) => {
+            // Window is hidden, show it and handle app icon based on user settings
+            if let Err(e) = window.show() {
+                eprintln!("Failed to show window: {}", e);
+            }
+
+            if let Err(e) = window.set_focus() {
+                eprintln!("Failed to focus window: {}", e);
+            }
+
+            // Emit event to focus text input
+            if let Err(e) = window.emit("focus-text-input", json!({})) {
+                eprintln!("Failed to emit focus event: {}", e);
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to check window visibility: {}", e);
+        }
+    }
+}
+
+
+/// Handle audio shortcut
+fn handle_audio_shortcut<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window("main") {
+        // Ensure window is visible
+        if let Ok(false) = window.is_visible() {
+            if let Err(e) = window.show() {
+                eprintln!("Failed to show window: {}", e);
+                return;
+            }
+            if let Err(e) = window.set_focus() {
+                eprintln!("Failed to focus window: {}", e);
+            }
+        }
+        
+        // Emit event to start audio recording
+        if let Err(e) = window.emit("start-audio-recording", json!({})) {
+            eprintln!("Failed to emit audio recording event: {}", e);
+        }
+    }
+}
+
+/// Handle screenshot shortcut - mode will be determined by user settings in frontend
+fn handle_screenshot_shortcut<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window("main") {
+        // Emit event to trigger screenshot - frontend will determine auto/manual mode
+        if let Err(e) = window.emit("trigger-screenshot", json!({})) {
+            eprintln!("Failed to emit screenshot event: {}", e);
+        }
+    }
+}
+
+/// Handle system audio shortcut
+fn handle_system_audio_shortcut<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window("main") {
+        // Ensure window is visible
+        if let Ok(false) = window.is_visible() {
+            if let Err(e) = window.show() {
+                eprintln!("Failed to show window: {}", e);
+                return;
+            }
+            if let Err(e) = window.set_focus() {
+                eprintln!("Failed to focus window: {}", e);
+            }
+        }
+        
+        // Emit event to toggle system audio capture - frontend will determine current state
+        if let Err(e) = window.emit("toggle-system-audio", json!({})) {
+            eprintln!("Failed to emit system audio event: {}", e);
+        }
+    }
+}
+
+/// Handle push-to-talk press, recording from the source chosen in settings until release
+fn handle_push_to_talk_shortcut<R: Runtime>(app: &AppHandle<R>) {
+    let source = match app_data_dir(app).and_then(|dir| super::load_settings(&dir)) {
+        Ok(settings) => settings.push_to_talk_source,
+        Err(e) => {
+            eprintln!("{}, recording from the microphone", e);
+            PushToTalkSource::default()
+        }
+    };
+    push_to_talk::start_push_to_talk(app, source);
+}
+
+/// Tauri command to get current shortcuts
+#[tauri::command]
+pub fn get_shortcuts<R: Runtime>(app: AppHandle<R>) -> Result<BTreeMap<ShortcutAction, String>, AppError> {
+    Ok(super::load_settings(&app_data_dir(&app)?)?.bindings())
+}
+
+#[tauri::command]
+pub fn get_push_to_talk_source<R: Runtime>(app: AppHandle<R>) -> Result<PushToTalkSource, AppError> {
+    Ok(super::load_settings(&app_data_dir(&app)?)?.push_to_talk_source)
+}
+
+#[tauri::command]
+pub fn set_push_to_talk_source<R: Runtime>(app: AppHandle<R>, source: PushToTalkSource) -> Result<(), AppError> {
+    super::set_push_to_talk_source(&app_data_dir(&app)?, source)
+}
+
+/// Where each action's binding stands, so settings can point at the one that needs changing
+#[tauri::command]
+pub fn get_shortcut_status<R: Runtime>(app: AppHandle<R>) -> BTreeMap<ShortcutAction, ShortcutStatus> {
+    let registry = app.state::<ShortcutRegistry>();
+    let registrations = registry.0.lock().unwrap();
+    registrations.status.clone()
+}
+
+/// Tauri command to check if shortcuts are registered
+#[tauri::command]
+pub fn check_shortcuts_registered<R: Runtime>(app: AppHandle<R>) -> Result<bool, String> {
+    let registry = app.state::<ShortcutRegistry>();
+    let registrations = registry.0.lock().unwrap();
+
+    for action in ShortcutAction::ALL {
+        let Some(shortcut) = registrations.shortcuts.get(&action) else {
+            return Ok(false);
+        };
+        if !app.global_shortcut().is_registered(*shortcut) {
+            return Ok(false);
+        }
+    }
+    
+    Ok(true)
+}
+// Tauri command to set app icon visibility in dock/taskbar
+#[tauri::command]
+pub fn set_app_icon_visibility<R: Runtime>(
+    app: AppHandle<R>,
+    visible: bool,
+) -> Result<(), String> {
+    println!("Setting app icon visibility to: {}", visible);
+    
+    #[cfg(target_os = "macos")]
+    {
+        // On macOS, use activation policy to control dock icon
+        let policy = if visible {
+            println!("Setting macOS activation policy to Regular (visible)");
+            tauri::ActivationPolicy::Regular
+        } else {
+            println!("Setting macOS activation policy to Accessory (hidden)");
+            tauri::ActivationPolicy::Accessory
+        };
+        
+        app.set_activation_policy(policy)
+            .map_err(|e| {
+                eprintln!("Failed to set activation policy: {}", e);
+                format!("Failed to set activation policy: {}", e)
+            })?;
+        
+        println!("Successfully set macOS activation policy");
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        // On Windows, control taskbar icon visibility
+        if let Some(window) = app.get_webview_window("main") {
+            println!("Setting Windows taskbar visibility to: {}", visible);
+            window.set_skip_taskbar(!visible)
+                .map_err(|e| {
+                    eprintln!("Failed to set taskbar visibility: {}", e);
+                    format!("Failed to set taskbar visibility: {}", e)
+                })?;
+            println!("Successfully set Windows taskbar visibility");
+        } else {
+            eprintln!("Main window not found on Windows");
+        }
+    }
+    
+    #[cfg(target_os = "linux")]
+    {
+        // On Linux, control panel icon visibility
+        if let Some(window) = app.get_webview_window("main") {
+            println!("Setting Linux panel visibility to: {}", visible);
+            window.set_skip_taskbar(!visible)
+                .map_err(|e| {
+                    eprintln!("Failed to set panel visibility: {}", e);
+                    format!("Failed to set panel visibility: {}", e)
+                })?;
+            println!("Successfully set Linux panel visibility");
+        } else {
+            eprintln!("Main window not found on Linux");
+        }
+    }
+    
+    Ok(())
+}
+
+/// Tauri command to set always on top state
+#[tauri::command]
+pub fn set_always_on_top<R: Runtime>(
+    app: AppHandle<R>,
+    enabled: bool,
+) -> Result<(), String> {
+    println!("Setting always on top to: {}", enabled);
+    
+    if let Some(window) = app.get_webview_window("main") {
+        window.set_always_on_top(enabled)
+            .map_err(|e| {
+                eprintln!("Failed to set always on top: {}", e);
+                format!("Failed to set always on top: {}", e)
+            })?;
+        
+        println!("Successfully set always on top to: {}", enabled);
+    } else {
+        eprintln!("Main window not found");
+        return Err("Main window not found".to_string());
+    }
+    
+    Ok(())
+}
@@ -0,0 +1,226 @@
+// The actions bound to global shortcuts, their default accelerators and the ones chosen in
+// settings, saved in `shortcuts.json` along with the push-to-talk source. Only bindings that
+// differ from the default are saved, so a default changed in a later version still reaches users
+// who never touched it. Registering with the OS happens in the commands.
+use tauri_plugin_global_shortcut::{Code, Modifiers, Shortcut};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::persist;
+use crate::push_to_talk::PushToTalkSource;
+
+mod commands;
+
+pub use commands::*;
+
+const SHORTCUTS_FILE: &str = "shortcuts.json";
+const WHAT: &str = "shortcut settings";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortcutAction {
+    /// Show or hide the main window
+    Toggle,
+    /// Start recording from the microphone
+    Audio,
+    Screenshot,
+    /// Start or stop capturing system audio
+    SystemAudio,
+    /// Record while held and send the recording when released
+    PushToTalk,
+}
+
+impl ShortcutAction {
+    pub const ALL: [ShortcutAction; 5] =
+        [Self::Toggle, Self::Audio, Self::Screenshot, Self::SystemAudio, Self::PushToTalk];
+
+    /// How the action is called in messages
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Toggle => "toggle",
+            Self::Audio => "audio",
+            Self::Screenshot => "screenshot",
+            Self::SystemAudio => "system audio",
+            Self::PushToTalk => "push-to-talk",
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn default_accelerator(self) -> &'static str {
+        match self {
+            Self::Toggle => "cmd+backslash",
+            Self::Audio => "cmd+shift+a",
+            Self::Screenshot => "cmd+shift+s",
+            Self::SystemAudio => "cmd+shift+m",
+            Self::PushToTalk => "cmd+shift+space",
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    pub fn default_accelerator(self) -> &'static str {
+        match self {
+            Self::Toggle => "ctrl+backslash",
+            Self::Audio => "ctrl+shift+a",
+            Self::Screenshot => "ctrl+shift+s",
+            Self::SystemAudio => "ctrl+shift+m",
+            Self::PushToTalk => "ctrl+shift+space",
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShortcutSettings {
+    /// Accelerators chosen in settings, by action
+    pub bindings: BTreeMap<ShortcutAction, String>,
+    /// What the push-to-talk shortcut records
+    pub push_to_talk_source: PushToTalkSource,
+}
+
+impl ShortcutSettings {
+    pub fn accelerator(&self, action: ShortcutAction) -> &str {
+        self.bindings.get(&action).map_or(action.default_accelerator(), String::as_str)
+    }
+
+    /// The accelerator of every action, defaults included
+    pub fn bindings(&self) -> BTreeMap<ShortcutAction, String> {
+        ShortcutAction::ALL
+            .into_iter()
+            .map(|action| (action, self.accelerator(action).to_string()))
+            .collect()
+    }
+}
+
+/// Parses an accelerator such as `ctrl+shift+a` or `cmdorctrl+f5`. A key without modifiers is
+/// only accepted for F1 to F24, anything else would swallow normal typing in every app.
+pub fn parse_accelerator(accelerator: &str) -> Result<Shortcut, AppError> {
+    let invalid = |reason: &dyn std::fmt::Display| {
+        AppError::Config(format!("Invalid shortcut \"{}\": {}", accelerator, reason))
+    };
+    let hotkey: Shortcut = accelerator.trim().parse().map_err(|e| invalid(&e))?;
+    if hotkey.mods.is_empty() && !is_function_key(hotkey.key) {
+        return Err(invalid(&"add a modifier such as ctrl, alt, shift or cmd"));
+    }
+    if hotkey.mods == Modifiers::SHIFT && !is_function_key(hotkey.key) {
+        return Err(invalid(&"shift alone only changes what the key types"));
+    }
+    Ok(hotkey)
+}
+
+fn is_function_key(code: Code) -> bool {
+    use Code::*;
+    matches!(
+        code,
+        F1 | F2 | F3 | F4 | F5 | F6 | F7 | F8 | F9 | F10 | F11 | F12
+            | F13 | F14 | F15 | F16 | F17 | F18 | F19 | F20 | F21 | F22 | F23 | F24
+    )
+}
+
+/// Parses `accelerator` for `action` and checks no other action is bound to the same keys
+pub fn check_binding(
+    settings: &ShortcutSettings,
+    action: ShortcutAction,
+    accelerator: &str,
+) -> Result<Shortcut, AppError> {
+    let hotkey = parse_accelerator(accelerator)?;
+    for other in ShortcutAction::ALL.into_iter().filter(|other| *other != action) {
+        let taken = parse_accelerator(settings.accelerator(other)).is_ok_and(|bound| bound.id() == hotkey.id());
+        if taken {
+            return Err(already_used(accelerator.trim(), other));
+        }
+    }
+    Ok(hotkey)
+}
+
+fn already_used(accelerator: &str, owner: ShortcutAction) -> AppError {
+    AppError::Config(format!("Shortcut \"{}\" is already used by the {} shortcut", accelerator, owner.label()))
+}
+
+/// Where an action's binding stands with the OS
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationState {
+    Registered,
+    /// Registration failed, usually because another app or the OS owns the keys
+    Taken,
+    /// The saved accelerator doesn't parse
+    Invalid,
+    /// Another action is bound to the same keys
+    Conflict,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ShortcutStatus {
+    pub accelerator: String,
+    pub state: RegistrationState,
+    /// Why the binding isn't registered
+    pub error: Option<String>,
+}
+
+impl ShortcutStatus {
+    pub fn registered(accelerator: &str) -> Self {
+        Self { accelerator: accelerator.to_string(), state: RegistrationState::Registered, error: None }
+    }
+
+    pub fn failed(accelerator: &str, state: RegistrationState, error: &AppError) -> Self {
+        Self { accelerator: accelerator.to_string(), state, error: Some(error.message()) }
+    }
+}
+
+/// Every action's saved accelerator, parsed. Of two actions bound to the same keys the first in
+/// `ShortcutAction::ALL` keeps them and the other gets a conflict.
+pub fn resolve_bindings(settings: &ShortcutSettings) -> BTreeMap<ShortcutAction, Result<Shortcut, ShortcutStatus>> {
+    let mut resolved = BTreeMap::new();
+    let mut claimed: Vec<(ShortcutAction, Shortcut)> = Vec::new();
+    for action in ShortcutAction::ALL {
+        let accelerator = settings.accelerator(action);
+        let binding = match parse_accelerator(accelerator) {
+            Err(e) => Err(ShortcutStatus::failed(accelerator, RegistrationState::Invalid, &e)),
+            Ok(hotkey) => match claimed.iter().find(|(_, claimed)| claimed.id() == hotkey.id()) {
+                Some((owner, _)) => {
+                    let e = already_used(accelerator, *owner);
+                    Err(ShortcutStatus::failed(accelerator, RegistrationState::Conflict, &e))
+                }
+                None => {
+                    claimed.push((action, hotkey));
+                    Ok(hotkey)
+                }
+            },
+        };
+        resolved.insert(action, binding);
+    }
+    resolved
+}
+
+fn settings_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(SHORTCUTS_FILE)
+}
+
+pub fn load_settings(app_data_dir: &Path) -> Result<ShortcutSettings, AppError> {
+    Ok(persist::read_json(&settings_path(app_data_dir), WHAT)?.unwrap_or_default())
+}
+
+/// Saves `accelerator` for `action`, forgetting it when it's the default again. Doesn't
+/// validate, `check_binding` comes first.
+pub fn save_binding(app_data_dir: &Path, action: ShortcutAction, accelerator: &str) -> Result<(), AppError> {
+    let accelerator = accelerator.trim();
+    let is_default = match (parse_accelerator(accelerator), parse_accelerator(action.default_accelerator())) {
+        (Ok(hotkey), Ok(default)) => hotkey.id() == default.id(),
+        _ => false,
+    };
+    persist::update_json(&settings_path(app_data_dir), WHAT, |settings: &mut ShortcutSettings| {
+        if is_default {
+            settings.bindings.remove(&action);
+        } else {
+            settings.bindings.insert(action, accelerator.to_string());
+        }
+    })
+}
+
+pub fn set_push_to_talk_source(app_data_dir: &Path, source: PushToTalkSource) -> Result<(), AppError> {
+    persist::update_json(&settings_path(app_data_dir), WHAT, |settings: &mut ShortcutSettings| {
+        settings.push_to_talk_source = source;
+    })
+}
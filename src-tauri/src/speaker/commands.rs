@@ -3,10 +3,9 @@ use tauri::{AppHandle, Emitter, Manager};
 use futures_util::StreamExt;
 use tauri_plugin_shell::ShellExt;
 use crate::speaker::{SpeakerInput};
+use crate::error::AppError;
+use crate::push_to_talk::wav_base64;
 use anyhow::Result;
-use hound::{WavSpec, WavWriter};
-use std::io::Cursor;
-use base64::{Engine as _, engine::general_purpose::STANDARD as B64};
 use std::collections::VecDeque;
 
 // Pluely AI Speech Detection
@@ -18,15 +17,15 @@ const MIN_SPEECH_CHUNKS: usize = 15;  // ~0.32s min speech duration
 const PRE_SPEECH_CHUNKS: usize = 15;  // ~0.32s pre-speech buffer
 
 #[tauri::command]
-pub async fn start_system_audio_capture(app: AppHandle) -> Result<(), String> {
+pub async fn start_system_audio_capture(app: AppHandle) -> Result<(), AppError> {
     let state = app.state::<crate::AudioState>();
     let mut guard = state.stream_task.lock().unwrap();
 
     if guard.is_some() {
-        return Err("Capture already running".to_string());
+        return Err(AppError::AudioDevice("Capture already running".to_string()));
     }
 
-    let input = SpeakerInput::new().map_err(|e| e.to_string())?;
+    let input = SpeakerInput::new().map_err(|e| AppError::AudioDevice(e.to_string()))?;
     let mut stream = input.stream();
     let sr = stream.sample_rate();
 
@@ -67,7 +66,7 @@ pub async fn start_system_audio_capture(app: AppHandle) -> Result<(), String> {
                         speech_buffer.extend_from_slice(&mono);
                         if speech_buffer.len() > max_samples {
                             // Force emit
-                            if let Ok(b64) = samples_to_wav_b64(sr, &speech_buffer) {
+                            if let Ok(b64) = wav_base64(sr, &speech_buffer) {
                                 let _ = app_clone.emit("speech-detected", b64).map_err(|e| eprintln!("emit speech-detected failed: {}", e));
                             }
                             speech_buffer.clear();
@@ -84,7 +83,7 @@ pub async fn start_system_audio_capture(app: AppHandle) -> Result<(), String> {
                                     if speech_buffer.len() > trim {
                                         speech_buffer.truncate(speech_buffer.len() - trim);
                                     }
-                                    if let Ok(b64) = samples_to_wav_b64(sr, &speech_buffer) {
+                                    if let Ok(b64) = wav_base64(sr, &speech_buffer) {
                                         let _ = app_clone.emit("speech-detected", b64).map_err(|e| eprintln!("emit speech-detected failed: {}", e));
                                     }
                                 }
@@ -122,29 +121,8 @@ fn process_chunk(mono_chunk: &[f32]) -> (f32, f32) {
     (rms, peak)
 }
 
-// Send samples to Pluely AI Speech
-fn samples_to_wav_b64(sample_rate: u32, mono_f32: &[f32]) -> Result<String, String> {
-    let mut cursor = Cursor::new(Vec::new());
-    let spec = WavSpec {
-        channels: 1,
-        sample_rate,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
-
-    let mut writer = WavWriter::new(&mut cursor, spec).map_err(|e| e.to_string())?;
-
-    for &s in mono_f32 {
-        let clamped = s.clamp(-1.0, 1.0);
-        let sample_i16 = (clamped * i16::MAX as f32) as i16;
-        writer.write_sample(sample_i16).map_err(|e| e.to_string())?;
-    }
-    writer.finalize().map_err(|e| e.to_string())?;
-    Ok(B64.encode(cursor.into_inner()))
-}
-
 #[tauri::command]
-pub async fn stop_system_audio_capture(app: AppHandle) -> Result<(), String> {
+pub async fn stop_system_audio_capture(app: AppHandle) -> Result<(), AppError> {
     let state = app.state::<crate::AudioState>();
     let mut guard = state.stream_task.lock().unwrap();
 
@@ -155,20 +133,20 @@ pub async fn stop_system_audio_capture(app: AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn check_system_audio_access(_app: AppHandle) -> Result<bool, String> {
-    let mut stream = SpeakerInput::new().map_err(|e| e.to_string())?.stream();
+pub async fn check_system_audio_access(_app: AppHandle) -> Result<bool, AppError> {
+    let mut stream = SpeakerInput::new().map_err(|e| AppError::AudioDevice(e.to_string()))?.stream();
     Ok(stream.next().await.is_some())
 }
 
 #[tauri::command]
-pub async fn request_system_audio_access(app: AppHandle) -> Result<(), String> {
+pub async fn request_system_audio_access(app: AppHandle) -> Result<(), AppError> {
     #[cfg(target_os = "macos")]
     {
-        app.shell().command("open").args(["x-apple.systempreferences:com.apple.preference.security?Privacy_AudioCapture"]).spawn().map_err(|e| e.to_string())?;
+        app.shell().command("open").args(["x-apple.systempreferences:com.apple.preference.security?Privacy_AudioCapture"]).spawn().map_err(|e| AppError::Permission(e.to_string()))?;
     }
     #[cfg(target_os = "windows")]
     {
-        app.shell().command("ms-settings:sound").spawn().map_err(|e| e.to_string())?;
+        app.shell().command("ms-settings:sound").spawn().map_err(|e| AppError::Permission(e.to_string()))?;
     }
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,463 @@
+// Server-sent events decoding, following the WHATWG event stream format
+// (https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation),
+// plus extractors that turn provider-specific events into text deltas.
+use futures_util::StreamExt;
+use serde_json::Value;
+
+use crate::chat::{FunctionCall, ToolCall};
+use crate::error::AppError;
+
+/// A dispatched server-sent event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event type, `"message"` when the server didn't send an `event:` field
+    pub event: String,
+    /// Data lines joined with `\n`
+    pub data: String,
+    /// Last event id seen on the stream, if any
+    pub id: Option<String>,
+    /// Reconnection time requested by the server, in milliseconds
+    pub retry: Option<u64>,
+}
+
+/// Incremental decoder. Feed it raw network chunks with [`SseDecoder::push`]; it buffers
+/// bytes until a full line is available, so multibyte characters split across chunks
+/// are never corrupted.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    line: Vec<u8>,
+    // A `\r` ended the previous chunk, so a leading `\n` in the next one belongs to it
+    pending_cr: bool,
+    started: bool,
+    event_type: String,
+    data: String,
+    has_data: bool,
+    last_event_id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes a chunk of bytes and returns every event completed by it
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+
+        for &byte in bytes {
+            if self.pending_cr {
+                self.pending_cr = false;
+                if byte == b'\n' {
+                    continue;
+                }
+            }
+
+            match byte {
+                b'\r' => {
+                    self.pending_cr = true;
+                    self.end_line(&mut events);
+                }
+                b'\n' => self.end_line(&mut events),
+                _ => self.line.push(byte),
+            }
+        }
+
+        events
+    }
+
+    /// Flushes a trailing event at end of stream. The spec says to discard it, but plenty of
+    /// servers close the connection without the final blank line, so we dispatch it instead.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let mut events = Vec::new();
+        if !self.line.is_empty() {
+            self.end_line(&mut events);
+        }
+        self.dispatch(&mut events);
+        events.pop()
+    }
+
+    fn end_line(&mut self, events: &mut Vec<SseEvent>) {
+        let raw = std::mem::take(&mut self.line);
+        let mut line = String::from_utf8_lossy(&raw).into_owned();
+
+        if !self.started {
+            self.started = true;
+            if let Some(stripped) = line.strip_prefix('\u{feff}') {
+                line = stripped.to_string();
+            }
+        }
+
+        if line.is_empty() {
+            self.dispatch(events);
+            return;
+        }
+
+        // Comment line, typically used as a keep-alive
+        if line.starts_with(':') {
+            return;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+
+        match field {
+            "event" => self.event_type = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry = value.parse().ok();
+            }
+            _ => {}
+        }
+    }
+
+    fn dispatch(&mut self, events: &mut Vec<SseEvent>) {
+        let event_type = std::mem::take(&mut self.event_type);
+
+        if !self.has_data {
+            return;
+        }
+        self.has_data = false;
+
+        let mut data = std::mem::take(&mut self.data);
+        if data.ends_with('\n') {
+            data.pop();
+        }
+
+        events.push(SseEvent {
+            event: if event_type.is_empty() { "message".to_string() } else { event_type },
+            data,
+            id: self.last_event_id.clone(),
+            retry: self.retry,
+        });
+    }
+}
+
+/// What an extractor made of a single event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamSignal {
+    /// A piece of response text
+    Delta(String),
+    /// The server signalled the end of the response
+    Done,
+    /// Bookkeeping event with nothing for the user (pings, role headers, usage...)
+    Ignore,
+}
+
+/// Token counts reported by the server. Either half may be missing: some servers only report
+/// usage on request, Anthropic sends prompt and completion counts in separate events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+}
+
+impl TokenUsage {
+    fn merge(&mut self, other: TokenUsage) {
+        self.prompt_tokens = other.prompt_tokens.or(self.prompt_tokens);
+        self.completion_tokens = other.completion_tokens.or(self.completion_tokens);
+    }
+}
+
+// More parallel tool calls than any model asks for in practice
+const MAX_TOOL_CALLS: usize = 16;
+
+/// A fragment of a streamed tool call. The first fragment for an `index` carries the id and
+/// name, later ones append to the JSON arguments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub arguments: String,
+}
+
+/// What a finished stream produced
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamOutput {
+    pub text: String,
+    pub usage: TokenUsage,
+    /// Tool calls assembled from their deltas, in index order
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl StreamOutput {
+    fn apply_tool_delta(&mut self, delta: ToolCallDelta) {
+        // The index comes from the server; don't let a bogus one allocate without bound
+        if delta.index >= MAX_TOOL_CALLS {
+            return;
+        }
+        while self.tool_calls.len() <= delta.index {
+            self.tool_calls.push(ToolCall {
+                id: String::new(),
+                kind: "function".to_string(),
+                function: FunctionCall { name: String::new(), arguments: String::new() },
+            });
+        }
+        let call = &mut self.tool_calls[delta.index];
+        if let Some(id) = delta.id {
+            call.id = id;
+        }
+        if let Some(name) = delta.name {
+            call.function.name.push_str(&name);
+        }
+        call.function.arguments.push_str(&delta.arguments);
+    }
+}
+
+/// Turns provider-specific events into text deltas. Returns `Err` with the server's message
+/// when the stream carries an error object.
+pub trait DeltaExtractor: Send + Sync {
+    fn extract(&self, event: &SseEvent) -> Result<StreamSignal, String>;
+
+    /// Token counts carried by this event, usually the last one before the end of the stream
+    fn usage(&self, _event: &SseEvent) -> Option<TokenUsage> {
+        None
+    }
+
+    /// Tool call fragments carried by this event
+    fn tool_calls(&self, _event: &SseEvent) -> Vec<ToolCallDelta> {
+        Vec::new()
+    }
+}
+
+/// OpenAI `chat.completion.chunk` streams, also used by the hosted backend,
+/// Ollama, LM Studio and most OpenAI-compatible servers
+pub struct OpenAiExtractor;
+
+impl DeltaExtractor for OpenAiExtractor {
+    fn extract(&self, event: &SseEvent) -> Result<StreamSignal, String> {
+        if event.data.trim() == "[DONE]" {
+            return Ok(StreamSignal::Done);
+        }
+        let Some(json) = parse_json(&event.data) else {
+            return Ok(StreamSignal::Ignore);
+        };
+        if let Some(error) = json.get("error") {
+            return Err(error_message(error));
+        }
+
+        let content = json
+            .get("choices")
+            .and_then(|c| c.get(0))
+            .and_then(|c| c.get("delta"))
+            .and_then(|d| d.get("content"))
+            .and_then(|c| c.as_str());
+
+        Ok(match content {
+            Some(text) if !text.is_empty() => StreamSignal::Delta(text.to_string()),
+            _ => StreamSignal::Ignore,
+        })
+    }
+
+    // Final chunk when `stream_options.include_usage` is set (or the server always sends it)
+    fn usage(&self, event: &SseEvent) -> Option<TokenUsage> {
+        let json = parse_json(&event.data)?;
+        let usage = json.get("usage").filter(|u| u.is_object())?;
+        Some(TokenUsage {
+            prompt_tokens: count(usage, "prompt_tokens"),
+            completion_tokens: count(usage, "completion_tokens"),
+        })
+    }
+
+    fn tool_calls(&self, event: &SseEvent) -> Vec<ToolCallDelta> {
+        let Some(json) = parse_json(&event.data) else {
+            return Vec::new();
+        };
+        let calls = json
+            .get("choices")
+            .and_then(|c| c.get(0))
+            .and_then(|c| c.get("delta"))
+            .and_then(|d| d.get("tool_calls"))
+            .and_then(|t| t.as_array());
+
+        calls
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(position, call)| {
+                let function = call.get("function");
+                let text = |value: Option<&Value>| value.and_then(|v| v.as_str()).map(str::to_string);
+                ToolCallDelta {
+                    index: call.get("index").and_then(|i| i.as_u64()).map_or(position, |i| i as usize),
+                    id: text(call.get("id")),
+                    name: text(function.and_then(|f| f.get("name"))),
+                    arguments: text(function.and_then(|f| f.get("arguments"))).unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Anthropic Messages API streams (`content_block_delta`, `message_stop`, `error`...)
+pub struct AnthropicExtractor;
+
+impl DeltaExtractor for AnthropicExtractor {
+    fn extract(&self, event: &SseEvent) -> Result<StreamSignal, String> {
+        let Some(json) = parse_json(&event.data) else {
+            return Ok(StreamSignal::Ignore);
+        };
+        // The payload repeats the event name in `type`; prefer it when `event:` was omitted
+        let kind = json.get("type").and_then(|t| t.as_str()).unwrap_or(&event.event);
+
+        match kind {
+            "error" => Err(json.get("error").map(error_message).unwrap_or_else(|| event.data.clone())),
+            "message_stop" => Ok(StreamSignal::Done),
+            // Input of a forced tool call is the answer itself, see `providers::anthropic`
+            "content_block_delta" => {
+                let delta = json.get("delta");
+                let text = match delta.and_then(|d| d.get("type")).and_then(|t| t.as_str()) {
+                    Some("text_delta") => delta.and_then(|d| d.get("text")),
+                    Some("input_json_delta") => delta.and_then(|d| d.get("partial_json")),
+                    _ => None,
+                };
+                match text.and_then(|t| t.as_str()) {
+                    Some(text) if !text.is_empty() => Ok(StreamSignal::Delta(text.to_string())),
+                    _ => Ok(StreamSignal::Ignore),
+                }
+            }
+            _ => Ok(StreamSignal::Ignore),
+        }
+    }
+
+    // `message_start` carries the input tokens, `message_delta` the running output count
+    fn usage(&self, event: &SseEvent) -> Option<TokenUsage> {
+        let json = parse_json(&event.data)?;
+        let usage = json
+            .get("message")
+            .and_then(|m| m.get("usage"))
+            .or_else(|| json.get("usage"))?;
+        Some(TokenUsage {
+            prompt_tokens: count(usage, "input_tokens"),
+            completion_tokens: count(usage, "output_tokens"),
+        })
+    }
+}
+
+/// Gemini `streamGenerateContent?alt=sse` streams
+pub struct GeminiExtractor;
+
+impl DeltaExtractor for GeminiExtractor {
+    fn extract(&self, event: &SseEvent) -> Result<StreamSignal, String> {
+        let Some(json) = parse_json(&event.data) else {
+            return Ok(StreamSignal::Ignore);
+        };
+        if let Some(error) = json.get("error") {
+            return Err(error_message(error));
+        }
+
+        let text: String = json
+            .get("candidates")
+            .and_then(|c| c.get(0))
+            .and_then(|c| c.get("content"))
+            .and_then(|c| c.get("parts"))
+            .and_then(|p| p.as_array())
+            .map(|parts| parts.iter().filter_map(|p| p.get("text").and_then(|t| t.as_str())).collect())
+            .unwrap_or_default();
+
+        Ok(if text.is_empty() { StreamSignal::Ignore } else { StreamSignal::Delta(text) })
+    }
+
+    // Every chunk repeats the running totals; the last one wins
+    fn usage(&self, event: &SseEvent) -> Option<TokenUsage> {
+        let json = parse_json(&event.data)?;
+        let usage = json.get("usageMetadata")?;
+        Some(TokenUsage {
+            prompt_tokens: count(usage, "promptTokenCount"),
+            completion_tokens: count(usage, "candidatesTokenCount"),
+        })
+    }
+}
+
+fn count(usage: &Value, key: &str) -> Option<u32> {
+    usage.get(key).and_then(|v| v.as_u64()).and_then(|v| u32::try_from(v).ok())
+}
+
+fn parse_json(data: &str) -> Option<Value> {
+    if data.trim().is_empty() {
+        return None;
+    }
+    serde_json::from_str(data).ok()
+}
+
+// Error objects come as `{ "message": ... }`, sometimes nested, sometimes a bare string
+fn error_message(error: &Value) -> String {
+    if let Some(message) = error.as_str() {
+        return message.to_string();
+    }
+    match error.get("message").and_then(|m| m.as_str()) {
+        Some(message) => message.to_string(),
+        None => error.to_string(),
+    }
+}
+
+/// Reads an SSE response body to the end (or until the extractor reports `Done`), calling
+/// `on_delta` for every text fragment. Returns the concatenated response text.
+pub async fn read_stream(
+    response: reqwest::Response,
+    extractor: &dyn DeltaExtractor,
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<String, AppError> {
+    Ok(read_stream_with_usage(response, extractor, on_delta).await?.text)
+}
+
+/// Like [`read_stream`], also collecting the token usage the server reported
+pub async fn read_stream_with_usage(
+    response: reqwest::Response,
+    extractor: &dyn DeltaExtractor,
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<StreamOutput, AppError> {
+    let mut stream = response.bytes_stream();
+    let mut decoder = SseDecoder::new();
+    let mut output = StreamOutput::default();
+
+    while let Some(chunk) = stream.next().await {
+        let bytes = chunk.map_err(|e| AppError::from(e).context("Stream error"))?;
+        for event in decoder.push(&bytes) {
+            if handle_event(&event, extractor, &mut output, on_delta)? {
+                return Ok(output);
+            }
+        }
+    }
+
+    if let Some(event) = decoder.finish() {
+        handle_event(&event, extractor, &mut output, on_delta)?;
+    }
+
+    Ok(output)
+}
+
+// Returns true once the stream is finished
+fn handle_event(
+    event: &SseEvent,
+    extractor: &dyn DeltaExtractor,
+    output: &mut StreamOutput,
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<bool, AppError> {
+    let signal = extractor
+        .extract(event)
+        .map_err(|message| AppError::Server { status: None, message })?;
+    if let Some(usage) = extractor.usage(event) {
+        output.usage.merge(usage);
+    }
+    for delta in extractor.tool_calls(event) {
+        output.apply_tool_delta(delta);
+    }
+
+    match signal {
+        StreamSignal::Delta(text) => {
+            output.text.push_str(&text);
+            on_delta(&text);
+            Ok(false)
+        }
+        StreamSignal::Done => Ok(true),
+        StreamSignal::Ignore => Ok(false),
+    }
+}
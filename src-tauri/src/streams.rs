@@ -0,0 +1,124 @@
+// Bookkeeping for in-flight chat streams: every stream has a request id, its events carry
+// that id, and it can be aborted through `cancel_chat_stream`.
+use futures_util::future::{AbortHandle, Abortable};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::fallback::ResponseSource;
+
+#[derive(Default)]
+pub struct ChatStreams {
+    handles: Mutex<HashMap<String, AbortHandle>>,
+}
+
+impl ChatStreams {
+    fn cancel(&self, request_id: &str) -> bool {
+        match self.handles.lock().unwrap().remove(request_id) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Payload of `chat_stream_chunk`
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatStreamChunk<'a> {
+    pub request_id: &'a str,
+    pub content: &'a str,
+    pub source: ResponseSource,
+}
+
+/// Payload of `chat_stream_complete`
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatStreamComplete<'a> {
+    pub request_id: &'a str,
+    pub response: &'a str,
+    pub cancelled: bool,
+    /// `None` when the stream was cancelled
+    pub source: Option<ResponseSource>,
+}
+
+/// Payload of `chat_stream_fallback`, sent when the backend is unreachable and the answer
+/// will come from the local runtime instead
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatStreamFallback<'a> {
+    pub request_id: &'a str,
+    pub reason: String,
+}
+
+/// Payload of `chat_tool_call`, sent when the model calls a tool so the UI can show what it's doing
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatToolCall<'a> {
+    pub request_id: &'a str,
+    pub tool: &'a str,
+}
+
+/// Uses the id supplied by the frontend, or makes one up for callers that don't track ids
+pub fn request_id_or_new(request_id: Option<String>) -> String {
+    request_id
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+pub fn emit_chunk(app: &AppHandle, request_id: &str, content: &str, source: ResponseSource) {
+    let _ = app.emit("chat_stream_chunk", ChatStreamChunk { request_id, content, source });
+}
+
+fn emit_complete(app: &AppHandle, request_id: &str, response: &str, source: Option<ResponseSource>) {
+    let cancelled = source.is_none();
+    let _ = app.emit("chat_stream_complete", ChatStreamComplete { request_id, response, cancelled, source });
+}
+
+pub fn emit_fallback(app: &AppHandle, request_id: &str, reason: &AppError) {
+    let _ = app.emit("chat_stream_fallback", ChatStreamFallback { request_id, reason: reason.message() });
+}
+
+pub fn emit_tool_call(app: &AppHandle, request_id: &str, tool: &str) {
+    let _ = app.emit("chat_tool_call", ChatToolCall { request_id, tool });
+}
+
+/// Runs a chat stream so that `cancel_chat_stream(request_id)` can abort it. Aborting drops
+/// the future, which drops the HTTP response body and closes the connection.
+pub async fn run_cancellable<F>(app: &AppHandle, request_id: &str, stream: F) -> Result<String, AppError>
+where
+    F: Future<Output = Result<(String, ResponseSource), AppError>>,
+{
+    let (handle, registration) = AbortHandle::new_pair();
+    {
+        let state = app.state::<ChatStreams>();
+        let mut handles = state.handles.lock().unwrap();
+        if handles.contains_key(request_id) {
+            return Err(AppError::Config(format!("A chat stream with id {} is already running", request_id)));
+        }
+        handles.insert(request_id.to_string(), handle);
+    }
+
+    let result = Abortable::new(stream, registration).await;
+    app.state::<ChatStreams>().handles.lock().unwrap().remove(request_id);
+
+    match result {
+        Ok(Ok((full_response, source))) => {
+            emit_complete(app, request_id, &full_response, Some(source));
+            Ok(full_response)
+        }
+        Ok(Err(e)) => Err(e),
+        Err(_) => {
+            emit_complete(app, request_id, "", None);
+            Err(AppError::Cancelled)
+        }
+    }
+}
+
+// Cancel a running chat stream. Returns false when no stream with that id is running.
+#[tauri::command]
+pub fn cancel_chat_stream(app: AppHandle, request_id: String) -> bool {
+    app.state::<ChatStreams>().cancel(&request_id)
+}
@@ -0,0 +1,109 @@
+// Structured output: the caller passes a JSON schema, the provider is asked for JSON matching
+// it, and the answer is validated here before it reaches the frontend. An invalid answer gets
+// one repair round where the model sees what was wrong.
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+
+/// Validation errors quoted back to the model in the repair prompt
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// A compiled schema for one structured request
+pub struct OutputSchema {
+    name: String,
+    schema: Value,
+    validator: jsonschema::Validator,
+}
+
+impl OutputSchema {
+    /// Fails with a config error when `schema` isn't a valid JSON schema
+    pub fn new(name: Option<String>, schema: Value) -> Result<Self, AppError> {
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| AppError::Config(format!("Invalid output schema: {}", e)))?;
+        let name = name
+            .map(|n| sanitize_name(&n))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| "response".to_string());
+        Ok(Self { name, schema, validator })
+    }
+
+    /// OpenAI-style `response_format`, sent as is to the backend and mapped by the direct provider
+    /// clients to their provider's own option. Models without one still get the instruction.
+    pub fn response_format(&self) -> Value {
+        json!({
+            "type": "json_schema",
+            "json_schema": { "name": self.name, "schema": self.schema }
+        })
+    }
+
+    /// Appended to the system prompt so models without native support still answer in JSON
+    pub fn instruction(&self) -> String {
+        format!(
+            "Respond only with a JSON value that matches this JSON schema, without markdown or any other text:\n{}",
+            self.schema
+        )
+    }
+
+    /// Parses and validates a complete answer, returning what was wrong otherwise
+    pub fn validate(&self, response: &str) -> Result<Value, Vec<String>> {
+        let value: Value = serde_json::from_str(extract_json(response))
+            .map_err(|e| vec![format!("The answer is not valid JSON: {}", e)])?;
+
+        let errors: Vec<String> = self
+            .validator
+            .iter_errors(&value)
+            .take(MAX_REPORTED_ERRORS)
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                if path.is_empty() {
+                    e.to_string()
+                } else {
+                    format!("{}: {}", path, e)
+                }
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Follow-up message asking the model to fix its previous answer
+    pub fn repair_prompt(&self, errors: &[String]) -> String {
+        format!(
+            "Your previous answer did not match the required JSON schema:\n- {}\n\nReply again with only the corrected JSON value.",
+            errors.join("\n- ")
+        )
+    }
+}
+
+/// Schema names are sent to providers that only accept `[a-zA-Z0-9_-]{1,64}`
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .take(64)
+        .collect()
+}
+
+/// The JSON in an answer, without the markdown fence or sentence some models wrap it in
+pub fn extract_json(response: &str) -> &str {
+    let trimmed = response.trim();
+    let unfenced = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|inner| inner.trim_start_matches(|c: char| c.is_ascii_alphabetic()).trim())
+        .unwrap_or(trimmed);
+
+    if unfenced.starts_with(['{', '[']) {
+        return unfenced;
+    }
+    // Prose around the value: take the outermost object or array
+    let start = unfenced.find(['{', '[']);
+    let end = unfenced.rfind(['}', ']']);
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => &unfenced[start..=end],
+        _ => unfenced,
+    }
+}
@@ -0,0 +1,101 @@
+// Tool execution and the Tauri commands behind tool settings, transcript and conversation mirroring
+use tauri::{AppHandle, Manager};
+
+use super::{
+    clipboard_output, load_conversations, load_settings, run_recent_transcript, run_search_conversations,
+    save_conversations, tool_infos, update_settings, BuiltinTool, IndexedConversation, ToolInfo, ToolOutput,
+    ToolSettings, TranscriptLog,
+};
+use crate::chat::{ToolCall, ToolMessage};
+use crate::error::AppError;
+use crate::models::now_secs;
+
+fn app_data_dir(app: &AppHandle) -> Result<std::path::PathBuf, AppError> {
+    app.path().app_data_dir()
+        .map_err(|e| AppError::Storage(format!("Failed to get app data directory: {}", e)))
+}
+
+/// Saved tool choices, or the defaults if they can't be read
+pub fn tool_settings(app: &AppHandle) -> ToolSettings {
+    app_data_dir(app)
+        .and_then(|dir| load_settings(&dir))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to load tool settings, using defaults: {}", e);
+            ToolSettings::default()
+        })
+}
+
+/// Runs one tool call. Failures and denied tools become the result text, so the model can
+/// carry on and tell the user instead of the whole chat failing.
+pub async fn run_tool(app: &AppHandle, call: &ToolCall, settings: &ToolSettings) -> ToolMessage {
+    let result = match BuiltinTool::from_name(&call.function.name) {
+        None => Err(format!("Unknown tool: {}", call.function.name)),
+        Some(tool) if !settings.is_allowed(tool) => Err(format!("The user has disabled the {} tool.", tool.name())),
+        Some(tool) => execute(app, tool, call).await,
+    };
+
+    result.unwrap_or_else(|message| ToolOutput::text(format!("Error: {}", message))).into_message(call)
+}
+
+async fn execute(app: &AppHandle, tool: BuiltinTool, call: &ToolCall) -> Result<ToolOutput, String> {
+    match tool {
+        BuiltinTool::Screenshot => {
+            let attachments = tokio::task::spawn_blocking(crate::capture_all_monitors)
+                .await
+                .map_err(|e| format!("Screenshot task failed: {}", e))??;
+            Ok(ToolOutput {
+                content: format!("Screenshots of the user's {} screen(s) are attached.", attachments.len()),
+                attachments,
+            })
+        }
+        BuiltinTool::RecentTranscript => run_recent_transcript(&app.state::<TranscriptLog>(), call, now_secs()),
+        BuiltinTool::SearchConversations => {
+            let conversations = app_data_dir(app)
+                .and_then(|dir| load_conversations(&dir))
+                .map_err(|e| e.message())?;
+            run_search_conversations(&conversations, call)
+        }
+        BuiltinTool::Clipboard => {
+            let text = tokio::task::spawn_blocking(|| arboard::Clipboard::new().and_then(|mut c| c.get_text()))
+                .await
+                .map_err(|e| format!("Clipboard task failed: {}", e))?;
+            match text {
+                Ok(text) => Ok(clipboard_output(&text)),
+                Err(arboard::Error::ContentNotAvailable) => Ok(clipboard_output("")),
+                Err(e) => Err(format!("Failed to read clipboard: {}", e)),
+            }
+        }
+    }
+}
+
+/// Records a transcription for the transcript tool
+pub fn record_transcript(app: &AppHandle, text: &str) {
+    app.state::<TranscriptLog>().push(text, now_secs());
+}
+
+#[tauri::command]
+pub fn get_tool_settings(app: AppHandle) -> Vec<ToolInfo> {
+    tool_infos(&tool_settings(&app))
+}
+
+#[tauri::command]
+pub fn set_tool_allowed(app: AppHandle, name: String, allowed: bool) -> Result<Vec<ToolInfo>, AppError> {
+    let tool = BuiltinTool::from_name(&name).ok_or_else(|| AppError::Config(format!("Unknown tool: {}", name)))?;
+    let settings = update_settings(&app_data_dir(&app)?, |settings| {
+        settings.tools.insert(tool.name().to_string(), allowed);
+        settings.clone()
+    })?;
+    Ok(tool_infos(&settings))
+}
+
+// Transcriptions made in the webview (custom speech providers) are reported here
+#[tauri::command]
+pub fn append_transcript(app: AppHandle, text: String) {
+    record_transcript(&app, &text);
+}
+
+// The webview owns chat history; it mirrors it here so the search tool can read it
+#[tauri::command]
+pub fn sync_conversations(app: AppHandle, conversations: Vec<IndexedConversation>) -> Result<(), AppError> {
+    save_conversations(&app_data_dir(&app)?, &conversations)
+}
@@ -0,0 +1,386 @@
+// Built-in tools the model can call during a chat to fetch context itself: a screenshot, the
+// recent transcript, past conversations and the clipboard. Each tool can be allowed or denied
+// by the user; denied tools are never offered to the model.
+mod commands;
+pub use commands::*;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::chat::{Attachment, ToolCall, ToolMessage};
+use crate::error::AppError;
+use crate::persist;
+
+const SETTINGS_FILE: &str = "tool_settings.json";
+const CONVERSATIONS_FILE: &str = "conversations_index.json";
+
+/// Rounds of tool calls allowed per chat request before the model must answer
+pub const MAX_TOOL_ROUNDS: usize = 3;
+// How much transcript is kept in memory
+const TRANSCRIPT_RETENTION_SECS: u64 = 30 * 60;
+const DEFAULT_TRANSCRIPT_SECS: u64 = 120;
+const MAX_SEARCH_RESULTS: usize = 10;
+const DEFAULT_SEARCH_RESULTS: usize = 5;
+// Characters of context on each side of a search match
+const SNIPPET_RADIUS: usize = 120;
+// Large clipboard contents are truncated rather than flooding the context window
+const MAX_CLIPBOARD_CHARS: usize = 8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinTool {
+    Screenshot,
+    RecentTranscript,
+    SearchConversations,
+    Clipboard,
+}
+
+impl BuiltinTool {
+    pub const ALL: [BuiltinTool; 4] = [
+        BuiltinTool::Screenshot,
+        BuiltinTool::RecentTranscript,
+        BuiltinTool::SearchConversations,
+        BuiltinTool::Clipboard,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BuiltinTool::Screenshot => "take_screenshot",
+            BuiltinTool::RecentTranscript => "get_recent_transcript",
+            BuiltinTool::SearchConversations => "search_conversations",
+            BuiltinTool::Clipboard => "read_clipboard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|tool| tool.name() == name)
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            BuiltinTool::Screenshot => "Capture the user's screens (every monitor) to see what they are looking at.",
+            BuiltinTool::RecentTranscript => {
+                "Get the transcript of what was said (microphone and system audio) in the last N seconds."
+            }
+            BuiltinTool::SearchConversations => {
+                "Search the user's past conversations with you for a word or phrase."
+            }
+            BuiltinTool::Clipboard => "Read the text currently on the user's clipboard.",
+        }
+    }
+
+    pub fn returns_images(self) -> bool {
+        matches!(self, BuiltinTool::Screenshot)
+    }
+
+    /// Screen and clipboard contents can be sensitive, so those are opt-in
+    fn allowed_by_default(self) -> bool {
+        matches!(self, BuiltinTool::RecentTranscript | BuiltinTool::SearchConversations)
+    }
+
+    fn parameters(self) -> Value {
+        match self {
+            BuiltinTool::Screenshot | BuiltinTool::Clipboard => json!({ "type": "object", "properties": {} }),
+            BuiltinTool::RecentTranscript => json!({
+                "type": "object",
+                "properties": {
+                    "seconds": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": TRANSCRIPT_RETENTION_SECS,
+                        "description": "How far back to look, in seconds"
+                    }
+                }
+            }),
+            BuiltinTool::SearchConversations => json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Words to look for" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_SEARCH_RESULTS }
+                },
+                "required": ["query"]
+            }),
+        }
+    }
+
+    /// OpenAI-style function definition
+    pub fn definition(self) -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.name(),
+                "description": self.description(),
+                "parameters": self.parameters(),
+            }
+        })
+    }
+}
+
+/// Per-tool allow/deny choices; tools missing from the map use their default
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ToolSettings {
+    pub tools: HashMap<String, bool>,
+}
+
+impl ToolSettings {
+    pub fn is_allowed(&self, tool: BuiltinTool) -> bool {
+        self.tools.get(tool.name()).copied().unwrap_or_else(|| tool.allowed_by_default())
+    }
+
+    /// Definitions of the tools the model may call. Text-only models aren't offered tools that
+    /// answer with images, since the result couldn't be sent back to them.
+    pub fn definitions(&self, accepts_images: bool) -> Vec<Value> {
+        BuiltinTool::ALL
+            .into_iter()
+            .filter(|tool| self.is_allowed(*tool) && (accepts_images || !tool.returns_images()))
+            .map(BuiltinTool::definition)
+            .collect()
+    }
+}
+
+/// A tool as shown in settings
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub allowed: bool,
+}
+
+pub fn tool_infos(settings: &ToolSettings) -> Vec<ToolInfo> {
+    BuiltinTool::ALL
+        .into_iter()
+        .map(|tool| ToolInfo { name: tool.name(), description: tool.description(), allowed: settings.is_allowed(tool) })
+        .collect()
+}
+
+/// What a tool returns to the model
+#[derive(Debug, Clone, Default)]
+pub struct ToolOutput {
+    pub content: String,
+    pub attachments: Vec<Attachment>,
+}
+
+impl ToolOutput {
+    pub fn text(content: impl Into<String>) -> Self {
+        Self { content: content.into(), attachments: Vec::new() }
+    }
+
+    pub fn into_message(self, call: &ToolCall) -> ToolMessage {
+        ToolMessage::Tool {
+            tool_call_id: call.id.clone(),
+            name: call.function.name.clone(),
+            content: self.content,
+            attachments: self.attachments,
+        }
+    }
+}
+
+/// Parses a call's JSON arguments; models send `""` for tools without parameters
+pub fn parse_arguments<T: DeserializeOwned + Default>(call: &ToolCall) -> Result<T, String> {
+    let arguments = call.function.arguments.trim();
+    if arguments.is_empty() {
+        return Ok(T::default());
+    }
+    serde_json::from_str(arguments).map_err(|e| format!("Invalid arguments for {}: {}", call.function.name, e))
+}
+
+fn settings_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(SETTINGS_FILE)
+}
+
+pub fn load_settings(app_data_dir: &Path) -> Result<ToolSettings, AppError> {
+    Ok(persist::read_json(&settings_path(app_data_dir), "tool settings")?.unwrap_or_default())
+}
+
+pub fn save_settings(app_data_dir: &Path, settings: &ToolSettings) -> Result<(), AppError> {
+    persist::write_json(&settings_path(app_data_dir), settings, "tool settings")
+}
+
+/// Changes the saved settings in place, so concurrent changes to other tools are kept
+pub fn update_settings<R>(app_data_dir: &Path, update: impl FnOnce(&mut ToolSettings) -> R) -> Result<R, AppError> {
+    persist::update_json(&settings_path(app_data_dir), "tool settings", update)
+}
+
+#[derive(Debug, Clone)]
+struct TranscriptEntry {
+    /// Unix seconds
+    at: u64,
+    text: String,
+}
+
+/// Transcriptions of the last `TRANSCRIPT_RETENTION_SECS`, for the transcript tool
+#[derive(Debug, Default)]
+pub struct TranscriptLog {
+    entries: Mutex<VecDeque<TranscriptEntry>>,
+}
+
+impl TranscriptLog {
+    pub fn push(&self, text: &str, now: u64) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(TranscriptEntry { at: now, text: text.to_string() });
+        while entries.front().is_some_and(|e| now.saturating_sub(e.at) > TRANSCRIPT_RETENTION_SECS) {
+            entries.pop_front();
+        }
+    }
+
+    /// Everything transcribed in the last `seconds`, oldest first, one line per transcription
+    pub fn recent(&self, seconds: u64, now: u64) -> Vec<String> {
+        let since = now.saturating_sub(seconds);
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.at >= since)
+            .map(|e| e.text.clone())
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TranscriptArgs {
+    seconds: Option<u64>,
+}
+
+pub fn run_recent_transcript(log: &TranscriptLog, call: &ToolCall, now: u64) -> Result<ToolOutput, String> {
+    let args: TranscriptArgs = parse_arguments(call)?;
+    let seconds = args.seconds.unwrap_or(DEFAULT_TRANSCRIPT_SECS).clamp(1, TRANSCRIPT_RETENTION_SECS);
+    let lines = log.recent(seconds, now);
+
+    Ok(ToolOutput::text(if lines.is_empty() {
+        format!("Nothing was transcribed in the last {} seconds.", seconds)
+    } else {
+        lines.join("\n")
+    }))
+}
+
+/// Conversation as mirrored from the webview's chat history, attachments left out
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IndexedConversation {
+    pub id: String,
+    pub title: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: u64,
+    pub messages: Vec<IndexedMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IndexedMessage {
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SearchHit {
+    pub conversation_id: String,
+    pub title: String,
+    pub snippet: String,
+}
+
+/// Conversations containing the most query words first, most recent first on ties
+pub fn search_conversations(conversations: &[IndexedConversation], query: &str, limit: usize) -> Vec<SearchHit> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    if terms.is_empty() {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<(usize, &IndexedConversation, String)> = conversations
+        .iter()
+        .filter_map(|conversation| {
+            let text = std::iter::once(conversation.title.as_str())
+                .chain(conversation.messages.iter().map(|m| m.content.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let lower = text.to_lowercase();
+            let score = terms.iter().filter(|term| lower.contains(term.as_str())).count();
+            (score > 0).then(|| (score, conversation, snippet(&text, &terms)))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.updated_at.cmp(&a.1.updated_at)));
+
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, conversation, snippet)| SearchHit {
+            conversation_id: conversation.id.clone(),
+            title: conversation.title.clone(),
+            snippet,
+        })
+        .collect()
+}
+
+// Text around the first matching term, on char boundaries
+fn snippet(text: &str, terms: &[String]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars.iter().flat_map(|c| c.to_lowercase()).collect();
+    // Lowercasing can change the length of a few scripts; fall back to the start then
+    let position = if lower.len() == chars.len() {
+        let lower: String = lower.into_iter().collect();
+        terms
+            .iter()
+            .filter_map(|term| lower.find(term.as_str()).map(|byte| lower[..byte].chars().count()))
+            .min()
+            .unwrap_or(0)
+    } else {
+        0
+    };
+
+    let start = position.saturating_sub(SNIPPET_RADIUS);
+    let end = (position + SNIPPET_RADIUS).min(chars.len());
+    let mut snippet: String = chars[start..end].iter().collect::<String>().replace('\n', " ");
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SearchArgs {
+    query: String,
+    limit: Option<usize>,
+}
+
+pub fn run_search_conversations(conversations: &[IndexedConversation], call: &ToolCall) -> Result<ToolOutput, String> {
+    let args: SearchArgs = parse_arguments(call)?;
+    let limit = args.limit.unwrap_or(DEFAULT_SEARCH_RESULTS).clamp(1, MAX_SEARCH_RESULTS);
+    let hits = search_conversations(conversations, &args.query, limit);
+
+    if hits.is_empty() {
+        return Ok(ToolOutput::text(format!("No past conversations mention \"{}\".", args.query)));
+    }
+    serde_json::to_string(&hits).map(ToolOutput::text).map_err(|e| e.to_string())
+}
+
+pub fn clipboard_output(text: &str) -> ToolOutput {
+    if text.trim().is_empty() {
+        return ToolOutput::text("The clipboard is empty or doesn't contain text.");
+    }
+    let mut content: String = text.chars().take(MAX_CLIPBOARD_CHARS).collect();
+    if content.len() < text.len() {
+        content.push_str("\n[truncated]");
+    }
+    ToolOutput::text(content)
+}
+
+fn conversations_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(CONVERSATIONS_FILE)
+}
+
+pub fn load_conversations(app_data_dir: &Path) -> Result<Vec<IndexedConversation>, AppError> {
+    Ok(persist::read_json(&conversations_path(app_data_dir), "conversation index")?.unwrap_or_default())
+}
+
+pub fn save_conversations(app_data_dir: &Path, conversations: &[IndexedConversation]) -> Result<(), AppError> {
+    let content = serde_json::to_vec(conversations)?;
+    persist::lock(&conversations_path(app_data_dir), "conversation index")?.write(&content, "conversation index")
+}
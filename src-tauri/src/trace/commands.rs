@@ -0,0 +1,61 @@
+// Tauri commands behind the HTTP trace setting and the debug log export
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use super::{debug_report, global, install, load_settings, save_settings, TraceSettings, Tracer};
+use crate::error::AppError;
+use crate::models::now_secs;
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    app.path().app_data_dir()
+        .map_err(|e| AppError::Storage(format!("Failed to get app data directory: {}", e)))
+}
+
+fn tracer() -> Result<&'static Tracer, AppError> {
+    global().ok_or_else(|| AppError::Config("HTTP tracing is not available".to_string()))
+}
+
+/// Installs the tracer with the saved setting; tracing stays off if it can't be read
+pub fn setup(app: &AppHandle) -> Result<(), AppError> {
+    let dir = app_data_dir(app)?;
+    let settings = load_settings(&dir).unwrap_or_else(|e| {
+        eprintln!("Failed to load trace settings, tracing disabled: {}", e);
+        TraceSettings::default()
+    });
+    install(Tracer::new(dir, settings.enabled));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_trace_settings(app: AppHandle) -> Result<TraceSettings, AppError> {
+    load_settings(&app_data_dir(&app)?)
+}
+
+#[tauri::command]
+pub fn set_trace_settings(app: AppHandle, settings: TraceSettings) -> Result<(), AppError> {
+    save_settings(&app_data_dir(&app)?, &settings)?;
+    tracer()?.set_enabled(settings.enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn clear_debug_log() -> Result<(), AppError> {
+    tracer()?.clear()
+}
+
+// Writes the report to the downloads folder (the app data directory if there is none) and
+// returns its path, so the user can attach it to a support ticket
+#[tauri::command]
+pub fn export_debug_log(app: AppHandle) -> Result<String, AppError> {
+    let settings = load_settings(&app_data_dir(&app)?)?;
+    let report = debug_report(tracer()?, env!("CARGO_PKG_VERSION"), &settings)?;
+
+    let dir = match app.path().download_dir() {
+        Ok(dir) => dir,
+        Err(_) => app_data_dir(&app)?,
+    };
+    let path = dir.join(format!("pluely-debug-{}.log", now_secs()));
+    fs::write(&path, report).map_err(|e| AppError::from(e).context("Failed to write debug log"))?;
+    Ok(path.to_string_lossy().into_owned())
+}
@@ -0,0 +1,350 @@
+// Opt-in HTTP trace log for debugging backend and provider issues. Every request made through
+// `send` is recorded as one JSON line: method, path, status, latency and truncated bodies.
+// Credentials, license details and base64 payloads are redacted before anything is written,
+// and the log rolls over so it never grows past two files of `MAX_LOG_BYTES`.
+mod commands;
+pub use commands::*;
+
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::{RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use crate::error::AppError;
+use crate::persist;
+use crate::models::now_secs;
+
+const SETTINGS_FILE: &str = "trace_settings.json";
+const LOG_FILE: &str = "http_trace.jsonl";
+const ROTATED_LOG_FILE: &str = "http_trace.1.jsonl";
+
+/// Size at which the log is rotated; one rotated file is kept
+pub const MAX_LOG_BYTES: u64 = 512 * 1024;
+/// Characters kept from each request and response body
+pub const MAX_BODY_CHARS: usize = 4000;
+// Shorter runs of base64 characters are usually ids or words, not payloads
+const MIN_BASE64_RUN: usize = 200;
+const REDACTED: &str = "[redacted]";
+
+const SENSITIVE_HEADERS: [&str; 2] = ["license_key", "instance"];
+// Custom provider templates can put keys in any header, so these match anywhere in the name
+const SENSITIVE_HEADER_PARTS: [&str; 5] = ["auth", "key", "token", "secret", "cookie"];
+const SENSITIVE_FIELDS: [&str; 11] = [
+    "license_key",
+    "instance",
+    "instance_id",
+    "instance_name",
+    "api_key",
+    "apikey",
+    "authorization",
+    "password",
+    "secret",
+    "token",
+    "access_token",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TraceSettings {
+    pub enabled: bool,
+}
+
+/// One request/response pair, as written to the log
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TraceEntry {
+    /// Unix seconds
+    pub timestamp: u64,
+    /// What the request was for, e.g. "chat" or "models"
+    pub operation: String,
+    pub method: String,
+    /// URL path only; hosts and query strings are left out
+    pub path: String,
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Option<String>,
+    /// `None` for streamed responses, which are consumed by the caller as they arrive
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Writes trace entries to the rolling log in the app data directory
+#[derive(Debug)]
+pub struct Tracer {
+    dir: PathBuf,
+    enabled: AtomicBool,
+    // Serializes appends and rotation
+    file: Mutex<()>,
+}
+
+impl Tracer {
+    pub fn new(dir: PathBuf, enabled: bool) -> Self {
+        Self { dir, enabled: AtomicBool::new(enabled), file: Mutex::new(()) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn record(&self, entry: &TraceEntry) -> Result<(), AppError> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _guard = self.file.lock().unwrap();
+        let path = self.dir.join(LOG_FILE);
+        // Another instance may be writing the same log
+        let log = persist::lock(&path, "trace log")?;
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size + line.len() as u64 > MAX_LOG_BYTES {
+            fs::rename(&path, self.dir.join(ROTATED_LOG_FILE))
+                .map_err(|e| AppError::from(e).context("Failed to rotate trace log"))?;
+        }
+
+        log.append(line.as_bytes(), "trace log")
+    }
+
+    /// The whole log, oldest entries first
+    pub fn read(&self) -> Result<String, AppError> {
+        let _guard = self.file.lock().unwrap();
+        let mut content = String::new();
+        for name in [ROTATED_LOG_FILE, LOG_FILE] {
+            match fs::read_to_string(self.dir.join(name)) {
+                Ok(part) => content.push_str(&part),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(AppError::from(e).context("Failed to read trace log")),
+            }
+        }
+        Ok(content)
+    }
+
+    pub fn clear(&self) -> Result<(), AppError> {
+        let _guard = self.file.lock().unwrap();
+        for name in [ROTATED_LOG_FILE, LOG_FILE] {
+            match fs::remove_file(self.dir.join(name)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(AppError::from(e).context("Failed to delete trace log"));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+// Like a logger, the tracer is process-wide so every call site can record without
+// threading it through; it is installed once at startup
+static TRACER: OnceLock<Tracer> = OnceLock::new();
+
+pub fn install(tracer: Tracer) {
+    if TRACER.set(tracer).is_err() {
+        eprintln!("HTTP tracer already installed");
+    }
+}
+
+pub fn global() -> Option<&'static Tracer> {
+    TRACER.get()
+}
+
+/// Sends `request`, recording it in the trace log when tracing is enabled
+pub async fn send(operation: &str, request: RequestBuilder) -> Result<Response, reqwest::Error> {
+    send_traced(global(), operation, request).await
+}
+
+/// [`send`] with an explicit tracer
+pub async fn send_traced(
+    tracer: Option<&Tracer>,
+    operation: &str,
+    request: RequestBuilder,
+) -> Result<Response, reqwest::Error> {
+    let Some(tracer) = tracer.filter(|t| t.is_enabled()) else {
+        return request.send().await;
+    };
+
+    let (client, request) = request.build_split();
+    let request = request?;
+    let mut entry = TraceEntry {
+        timestamp: now_secs(),
+        operation: operation.to_string(),
+        method: request.method().to_string(),
+        path: request.url().path().to_string(),
+        status: None,
+        latency_ms: 0,
+        request_headers: redact_headers(request.headers()),
+        request_body: request.body().and_then(|b| b.as_bytes()).map(redact_body),
+        response_body: None,
+        error: None,
+    };
+
+    let started = Instant::now();
+    let result = match client.execute(request).await {
+        Ok(response) => {
+            entry.status = Some(response.status().as_u16());
+            buffer_response(response, &mut entry).await
+        }
+        Err(e) => Err(e),
+    };
+    entry.latency_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let result = result.map_err(|e| {
+        let e = e.without_url();
+        entry.error = Some(e.to_string());
+        e
+    });
+
+    // Tracing must never fail the request itself
+    if let Err(e) = tracer.record(&entry) {
+        eprintln!("Failed to record HTTP trace: {}", e);
+    }
+    result
+}
+
+// Reads a non-streaming body so it can be logged, then hands the caller an equivalent response
+async fn buffer_response(response: Response, entry: &mut TraceEntry) -> Result<Response, reqwest::Error> {
+    let streaming = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    if streaming {
+        return Ok(response);
+    }
+
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+    entry.response_body = Some(redact_body(&body));
+
+    let mut rebuilt = http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    Ok(Response::from(rebuilt))
+}
+
+pub fn redact_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let name = name.as_str();
+            let sensitive = SENSITIVE_HEADERS.contains(&name) || SENSITIVE_HEADER_PARTS.iter().any(|part| name.contains(part));
+            let value = if sensitive {
+                REDACTED.to_string()
+            } else {
+                value.to_str().unwrap_or("[binary]").to_string()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// Body text with secrets and base64 payloads replaced, cut to `MAX_BODY_CHARS`
+pub fn redact_body(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    let redacted = match serde_json::from_str::<Value>(&text) {
+        Ok(mut json) => {
+            redact_json(&mut json);
+            json.to_string()
+        }
+        Err(_) => redact_base64_runs(&text),
+    };
+    truncate(redacted)
+}
+
+fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SENSITIVE_FIELDS.contains(&key.to_lowercase().as_str()) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_json),
+        Value::String(s) => *s = redact_base64_runs(s),
+        _ => {}
+    }
+}
+
+// Replaces long runs of base64 characters (images, audio, data URLs) with their length
+fn redact_base64_runs(text: &str) -> String {
+    let is_base64 = |c: char| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '-' | '_');
+    let mut output = String::with_capacity(text.len().min(MAX_BODY_CHARS * 2));
+    let mut run_start = None;
+
+    for (index, c) in text.char_indices() {
+        match (is_base64(c), run_start) {
+            (true, None) => run_start = Some(index),
+            (false, Some(start)) => {
+                push_run(&mut output, &text[start..index]);
+                run_start = None;
+                output.push(c);
+            }
+            (false, None) => output.push(c),
+            (true, Some(_)) => {}
+        }
+    }
+    if let Some(start) = run_start {
+        push_run(&mut output, &text[start..]);
+    }
+    output
+}
+
+// Base64 characters are ASCII, so byte length is the character count
+fn push_run(output: &mut String, run: &str) {
+    if run.len() >= MIN_BASE64_RUN {
+        output.push_str(&format!("[base64, {} chars]", run.len()));
+    } else {
+        output.push_str(run);
+    }
+}
+
+fn truncate(text: String) -> String {
+    let total = text.chars().count();
+    if total <= MAX_BODY_CHARS {
+        return text;
+    }
+    let kept: String = text.chars().take(MAX_BODY_CHARS).collect();
+    format!("{}… [truncated, {} chars total]", kept, total)
+}
+
+fn settings_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(SETTINGS_FILE)
+}
+
+pub fn load_settings(app_data_dir: &Path) -> Result<TraceSettings, AppError> {
+    Ok(persist::read_json(&settings_path(app_data_dir), "trace settings")?.unwrap_or_default())
+}
+
+pub fn save_settings(app_data_dir: &Path, settings: &TraceSettings) -> Result<(), AppError> {
+    persist::write_json(&settings_path(app_data_dir), settings, "trace settings")
+}
+
+/// Report for support tickets: app and platform details followed by the trace log
+pub fn debug_report(tracer: &Tracer, app_version: &str, settings: &TraceSettings) -> Result<String, AppError> {
+    let log = tracer.read()?;
+    let mut report = format!(
+        "Pluely debug log\napp_version: {}\nos: {}\narch: {}\nexported_at: {}\ntracing_enabled: {}\n\n",
+        app_version,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        now_secs(),
+        settings.enabled,
+    );
+    if log.is_empty() {
+        report.push_str("(no requests recorded; enable HTTP tracing and reproduce the issue first)\n");
+    } else {
+        report.push_str(&log);
+    }
+    Ok(report)
+}
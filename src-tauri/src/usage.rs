@@ -0,0 +1,281 @@
+// Token and cost accounting for chat requests. Every finished request appends one line to
+// `usage.jsonl`; summaries are computed from that log on demand. Counts the server doesn't
+// report are chars/4 estimates and the record is marked `estimated`. The log rolls over so it
+// never grows past two files of `MAX_LOG_BYTES`, and summaries only reach back that far.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::chat::estimate_tokens;
+use crate::error::AppError;
+use crate::fallback::ResponseSource;
+use crate::persist;
+use crate::models::{now_secs, Model};
+use crate::sse::TokenUsage;
+
+const USAGE_FILE: &str = "usage.jsonl";
+const ROTATED_USAGE_FILE: &str = "usage.1.jsonl";
+const WHAT: &str = "usage log";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Size at which the log is rotated; one rotated file is kept
+pub const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+
+/// One finished chat request, with every tool round it took
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageRecord {
+    /// Unix seconds when the request finished
+    pub timestamp: u64,
+    pub request_id: String,
+    pub conversation_id: Option<String>,
+    pub source: ResponseSource,
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// True when the server didn't report usage and the counts are local chars/4 estimates
+    pub estimated: bool,
+    pub latency_ms: u64,
+    /// `None` when the model has no known price
+    pub cost_usd: Option<f64>,
+}
+
+/// What we know about a request before it is sent, used to complete its record afterwards
+#[derive(Debug, Clone)]
+pub struct Metering {
+    pub model: String,
+    pub input_price_per_token: Option<f64>,
+    pub output_price_per_token: Option<f64>,
+    /// Local estimate of the prompt, used when the server doesn't report it
+    pub prompt_tokens_estimate: u32,
+}
+
+impl Metering {
+    pub fn for_model(model: Option<&Model>, prompt_tokens_estimate: usize) -> Self {
+        Self {
+            model: model.map(|m| m.model.clone()).unwrap_or_else(|| "default".to_string()),
+            input_price_per_token: model.and_then(|m| m.input_price_per_token),
+            output_price_per_token: model.and_then(|m| m.output_price_per_token),
+            prompt_tokens_estimate: u32::try_from(prompt_tokens_estimate).unwrap_or(u32::MAX),
+        }
+    }
+
+    /// Local runtimes cost nothing and report no usage over our stream
+    pub fn local(model: &str, prompt_tokens_estimate: usize) -> Self {
+        Self {
+            model: model.to_string(),
+            input_price_per_token: Some(0.0),
+            output_price_per_token: Some(0.0),
+            prompt_tokens_estimate: u32::try_from(prompt_tokens_estimate).unwrap_or(u32::MAX),
+        }
+    }
+
+    /// Builds the record, preferring server-reported counts over estimates
+    pub fn finish(
+        &self,
+        request_id: &str,
+        conversation_id: Option<String>,
+        source: ResponseSource,
+        response: &str,
+        reported: TokenUsage,
+        latency: Duration,
+    ) -> UsageRecord {
+        let completion_estimate = u32::try_from(estimate_tokens(response)).unwrap_or(u32::MAX);
+        let prompt_tokens = reported.prompt_tokens.unwrap_or(self.prompt_tokens_estimate);
+        let completion_tokens = reported.completion_tokens.unwrap_or(completion_estimate);
+
+        let cost_usd = match (self.input_price_per_token, self.output_price_per_token) {
+            (None, None) => None,
+            (input, output) => Some(
+                prompt_tokens as f64 * input.unwrap_or(0.0) + completion_tokens as f64 * output.unwrap_or(0.0),
+            ),
+        };
+
+        UsageRecord {
+            timestamp: now_secs(),
+            request_id: request_id.to_string(),
+            conversation_id,
+            source,
+            model: self.model.clone(),
+            prompt_tokens,
+            completion_tokens,
+            estimated: reported.prompt_tokens.is_none() || reported.completion_tokens.is_none(),
+            latency_ms: u64::try_from(latency.as_millis()).unwrap_or(u64::MAX),
+            cost_usd,
+        }
+    }
+}
+
+impl UsageRecord {
+    /// Adds a later round of the same request, e.g. the answer after a tool call
+    pub fn add_round(&mut self, round: UsageRecord) {
+        self.timestamp = round.timestamp;
+        self.prompt_tokens = self.prompt_tokens.saturating_add(round.prompt_tokens);
+        self.completion_tokens = self.completion_tokens.saturating_add(round.completion_tokens);
+        self.estimated |= round.estimated;
+        self.latency_ms = self.latency_ms.saturating_add(round.latency_ms);
+        self.cost_usd = match (self.cost_usd, round.cost_usd) {
+            (None, None) => None,
+            (cost, round_cost) => Some(cost.unwrap_or(0.0) + round_cost.unwrap_or(0.0)),
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UsagePeriod {
+    /// Since midnight UTC
+    Day,
+    /// Today and the six days before it
+    Week,
+    /// Today and the 29 days before it
+    Month,
+    All,
+}
+
+impl UsagePeriod {
+    /// First second included in the period
+    fn start(self, now: u64) -> u64 {
+        let today = now - now % SECONDS_PER_DAY;
+        match self {
+            UsagePeriod::Day => today,
+            UsagePeriod::Week => today.saturating_sub(6 * SECONDS_PER_DAY),
+            UsagePeriod::Month => today.saturating_sub(29 * SECONDS_PER_DAY),
+            UsagePeriod::All => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct UsageTotals {
+    pub requests: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Sum over requests with a known price
+    pub cost_usd: f64,
+    /// Requests whose model has no price, so `cost_usd` understates the real cost
+    pub unpriced_requests: u32,
+    pub estimated_requests: u32,
+    pub average_latency_ms: u64,
+    #[serde(skip)]
+    total_latency_ms: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.prompt_tokens += u64::from(record.prompt_tokens);
+        self.completion_tokens += u64::from(record.completion_tokens);
+        match record.cost_usd {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_requests += 1,
+        }
+        if record.estimated {
+            self.estimated_requests += 1;
+        }
+        self.total_latency_ms += record.latency_ms;
+        self.average_latency_ms = self.total_latency_ms / u64::from(self.requests);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModelUsage {
+    pub model: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DayUsage {
+    /// `YYYY-MM-DD`, UTC
+    pub date: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UsageSummary {
+    pub period: UsagePeriod,
+    /// Unix seconds of the start of the period
+    pub since: u64,
+    pub totals: UsageTotals,
+    /// Most expensive first
+    pub by_model: Vec<ModelUsage>,
+    /// Oldest first, days without requests are omitted
+    pub by_day: Vec<DayUsage>,
+}
+
+pub fn summarize(records: &[UsageRecord], period: UsagePeriod, now: u64) -> UsageSummary {
+    let since = period.start(now);
+    let mut totals = UsageTotals::default();
+    let mut by_model: BTreeMap<&str, UsageTotals> = BTreeMap::new();
+    let mut by_day: BTreeMap<u64, UsageTotals> = BTreeMap::new();
+
+    for record in records.iter().filter(|r| r.timestamp >= since) {
+        totals.add(record);
+        by_model.entry(&record.model).or_default().add(record);
+        by_day.entry(record.timestamp / SECONDS_PER_DAY).or_default().add(record);
+    }
+
+    let mut by_model: Vec<ModelUsage> = by_model
+        .into_iter()
+        .map(|(model, totals)| ModelUsage { model: model.to_string(), totals })
+        .collect();
+    by_model.sort_by(|a, b| b.totals.cost_usd.total_cmp(&a.totals.cost_usd));
+
+    let by_day = by_day
+        .into_iter()
+        .map(|(day, totals)| DayUsage { date: format_date(day), totals })
+        .collect();
+
+    UsageSummary { period, since, totals, by_model, by_day }
+}
+
+// Days since the Unix epoch to a proleptic Gregorian date (Howard Hinnant's civil_from_days)
+fn format_date(days: u64) -> String {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn usage_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(USAGE_FILE)
+}
+
+pub fn append_record(app_data_dir: &Path, record: &UsageRecord) -> Result<(), AppError> {
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+
+    let path = usage_path(app_data_dir);
+    let log = persist::lock(&path, WHAT)?;
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    if size + line.len() as u64 > MAX_LOG_BYTES {
+        fs::rename(&path, app_data_dir.join(ROTATED_USAGE_FILE))
+            .map_err(|e| AppError::from(e).context("Failed to rotate usage log"))?;
+    }
+
+    log.append(line.as_bytes(), WHAT)
+}
+
+/// All records, oldest first; lines that can't be parsed (e.g. a write cut short by a crash)
+/// are skipped
+pub fn load_records(app_data_dir: &Path) -> Result<Vec<UsageRecord>, AppError> {
+    let mut records = Vec::new();
+    for path in [app_data_dir.join(ROTATED_USAGE_FILE), usage_path(app_data_dir)] {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(AppError::from(e).context("Failed to read usage log")),
+        };
+        records.extend(content.lines().filter_map(|line| serde_json::from_str::<UsageRecord>(line).ok()));
+    }
+    Ok(records)
+}
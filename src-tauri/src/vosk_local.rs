@@ -115,7 +115,9 @@ pub async fn initialize_vosk_local() -> Result<String, String> {
 }
 
 #[tauri::command]
-pub async fn transcribe_audio_vosk(audio_base64: String) -> Result<String, String> {
-    LocalVosk::transcribe_audio(&audio_base64)
-        .map_err(|e| e.to_string())
+pub async fn transcribe_audio_vosk(app: tauri::AppHandle, audio_base64: String) -> Result<String, String> {
+    let transcription = LocalVosk::transcribe_audio(&audio_base64)
+        .map_err(|e| e.to_string())?;
+    crate::tools::record_transcript(&app, &transcription);
+    Ok(transcription)
 }
\ No newline at end of file
@@ -0,0 +1,107 @@
+use base64::Engine;
+use pluely_lib::attachments::{
+    encode_png, validate, validate_turn, RequestImages, MAX_ATTACHMENTS, MAX_ATTACHMENT_BYTES, MAX_IMAGES,
+};
+use pluely_lib::chat::{Attachment, AttachmentSource, ChatMessage, Role, ToolMessage};
+use pluely_lib::error::AppError;
+
+fn png(width: u32, height: u32) -> Attachment {
+    let pixels = vec![255u8; (width * height * 4) as usize];
+    encode_png(&pixels, width, height, AttachmentSource::Screen, None).unwrap()
+}
+
+fn unsupported_message(result: Result<Vec<Attachment>, AppError>) -> String {
+    match result {
+        Err(AppError::Unsupported(message)) => message,
+        other => panic!("expected an unsupported_input error, got {:?}", other),
+    }
+}
+
+#[test]
+fn fills_in_real_format_and_dimensions() {
+    let mut screen = png(3, 2);
+    screen.mime_type = "image/jpg".to_string();
+    screen.width = None;
+    screen.data = format!("data:image/jpg;base64,{}", screen.data);
+    screen.caption = Some("  Screen 2 of 2  ".to_string());
+
+    let validated = validate(vec![screen]).unwrap();
+    assert_eq!(validated[0].mime_type, "image/png");
+    assert_eq!((validated[0].width, validated[0].height), (Some(3), Some(2)));
+    assert!(!validated[0].data.starts_with("data:"));
+    assert_eq!(validated[0].caption.as_deref(), Some("Screen 2 of 2"));
+    assert_eq!(validated[0].source, AttachmentSource::Screen);
+}
+
+#[test]
+fn rejects_bad_data_naming_the_image() {
+    let not_base64 = Attachment::new("image/png", "%%%", AttachmentSource::File);
+    let message = unsupported_message(validate(vec![png(1, 1), not_base64]));
+    assert!(message.starts_with("Image 2 can't be sent"), "{}", message);
+
+    let text = base64::engine::general_purpose::STANDARD.encode("just some text");
+    let not_an_image = Attachment::new("image/png", text, AttachmentSource::Clipboard);
+    assert!(unsupported_message(validate(vec![not_an_image])).contains("only PNG, JPEG, WebP and GIF"));
+}
+
+#[test]
+fn enforces_count_and_size_limits() {
+    let too_many = vec![png(1, 1); MAX_ATTACHMENTS + 1];
+    assert!(unsupported_message(validate(too_many)).starts_with("Too many images"));
+
+    let huge = "A".repeat(MAX_ATTACHMENT_BYTES / 3 * 4 + 8);
+    let message = unsupported_message(validate(vec![Attachment::new("image/png", huge, AttachmentSource::File)]));
+    assert!(message.contains("larger than"), "{}", message);
+}
+
+fn turn(attachments: Vec<Attachment>) -> ChatMessage {
+    ChatMessage { role: Role::User, content: "look".to_string(), attachments }
+}
+
+#[test]
+fn limits_cover_the_whole_request() {
+    let not_an_image = Attachment::new("image/png", "%%%", AttachmentSource::File);
+    let mut history: Vec<ChatMessage> = (0..5).map(|_| turn(vec![png(1, 1); 4])).collect();
+    history.push(turn(vec![not_an_image]));
+
+    let (attachments, mut images) = validate_turn(vec![png(2, 2); 3], &mut history).unwrap();
+    assert_eq!(attachments.len(), 3);
+    // The invalid image and the oldest ones past the budget are dropped, text kept
+    assert!(history[5].attachments.is_empty());
+    let kept: usize = history.iter().map(|m| m.attachments.len()).sum();
+    assert_eq!(kept, MAX_IMAGES - 3);
+    assert!(history[0].attachments.len() < 4);
+    assert_eq!(history[0].content, "look");
+
+    let mut screenshot = ToolMessage::Tool {
+        tool_call_id: "call_1".to_string(),
+        name: "take_screenshot".to_string(),
+        content: "Screenshots are attached.".to_string(),
+        attachments: vec![png(1, 1)],
+    };
+    images.validate_tool_result(&mut screenshot);
+    let ToolMessage::Tool { content, attachments, .. } = screenshot else { unreachable!() };
+    assert!(attachments.is_empty());
+    assert!(content.contains("could not be attached"), "{}", content);
+}
+
+#[test]
+fn tool_images_are_validated() {
+    let mut images = RequestImages::default();
+    let mut result = ToolMessage::Tool {
+        tool_call_id: "call_1".to_string(),
+        name: "take_screenshot".to_string(),
+        content: "Screenshots are attached.".to_string(),
+        attachments: vec![png(4, 3)],
+    };
+    images.validate_tool_result(&mut result);
+    let ToolMessage::Tool { attachments, .. } = result else { unreachable!() };
+    assert_eq!((attachments[0].width, attachments[0].height), (Some(4), Some(3)));
+}
+
+#[test]
+fn history_without_metadata_still_parses() {
+    let attachment: Attachment = serde_json::from_str(r#"{"mime_type":"image/png","data":"AAAA"}"#).unwrap();
+    assert_eq!(attachment.source, AttachmentSource::File);
+    assert_eq!(serde_json::to_value(&attachment).unwrap()["source"], "file");
+}
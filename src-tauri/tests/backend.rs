@@ -0,0 +1,251 @@
+mod support;
+
+use pluely_lib::backend::{clear_credentials, load_credentials, Backend, BackendConfig, ChatTurn};
+use pluely_lib::chat::{Attachment, AttachmentSource, ChatMessage, Role, ToolMessage};
+use pluely_lib::error::AppError;
+use pluely_lib::license;
+use pluely_lib::models::{Model, ModelCache};
+use pluely_lib::secret_store::{self, SecretStore};
+use serde_json::json;
+use std::sync::Mutex;
+use support::{credentials, MockBackend, CHECKOUT_URL, INSTANCE_ID, LICENSE_KEY, MODELS_ETAG, TRANSCRIPTION};
+use wiremock::ResponseTemplate;
+
+fn turn(user_message: &str) -> ChatTurn {
+    ChatTurn { user_message: user_message.to_string(), ..ChatTurn::default() }
+}
+
+fn model(id: &str, modality: &str) -> Model {
+    serde_json::from_value(support::model_json(id, modality)).unwrap()
+}
+
+#[tokio::test]
+async fn streams_chat_with_license_and_model_headers() {
+    let mock = MockBackend::start().await;
+    let model = model("gpt-4o", "multimodal");
+    let mut history = vec![ChatMessage { role: Role::User, content: "Hi".to_string(), attachments: Vec::new() }];
+    history.push(ChatMessage { role: Role::Assistant, content: "Hello!".to_string(), attachments: Vec::new() });
+    let turn = ChatTurn { history, system_prompt: Some("Be brief".to_string()), ..turn("Capital of France?") };
+
+    let (request, _) = turn.request(Some(&model), &[], &[]).unwrap();
+    let mut deltas = Vec::new();
+    let output = mock
+        .backend()
+        .stream_chat(Some(&credentials()), Some(&model), &request, &|_| {}, &mut |d: &str| deltas.push(d.to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(deltas, ["The capital", " of France", " is Paris."]);
+    assert_eq!(output.text, "The capital of France is Paris.");
+    assert_eq!((output.usage.prompt_tokens, output.usage.completion_tokens), (Some(42), Some(7)));
+
+    let sent = mock.requests_to("/api/chat").await;
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].headers.get("provider").unwrap(), "openai");
+    assert_eq!(sent[0].headers.get("model").unwrap(), "gpt-4o");
+    let body: serde_json::Value = sent[0].body_json().unwrap();
+    assert_eq!(body["user_message"], "Capital of France?");
+    assert_eq!(body["system_prompt"], "Be brief");
+    assert_eq!(body["messages"].as_array().unwrap().len(), 2);
+    // Older backends read the history as a JSON string
+    let legacy: serde_json::Value = serde_json::from_str(body["history"].as_str().unwrap()).unwrap();
+    assert_eq!(legacy[1], json!({ "role": "assistant", "content": [{ "type": "text", "text": "Hello!" }] }));
+    assert!(body.get("image_base64").is_none());
+    assert!(body.get("tools").is_none());
+}
+
+#[tokio::test]
+async fn self_hosted_profiles_never_get_the_license() {
+    let mock = MockBackend::start().await;
+    let config = BackendConfig { sends_license: false, ..mock.config() };
+    let (request, _) = turn("Hello").request(None, &[], &[]).unwrap();
+    // The mock only answers licensed requests, what matters is what was sent
+    let _ = Backend::new(reqwest::Client::new(), config)
+        .stream_chat(Some(&credentials()), None, &request, &|_| {}, &mut |_| {})
+        .await;
+
+    let sent = mock.requests_to("/api/chat").await;
+    assert!(sent[0].headers.get("license_key").is_none());
+    assert!(sent[0].headers.get("instance").is_none());
+}
+
+#[tokio::test]
+async fn rejects_images_for_text_only_models_before_sending() {
+    let turn = ChatTurn {
+        attachments: vec![Attachment::new("image/png", "iVBORw0KGgo=", AttachmentSource::Screen)],
+        ..turn("What is on my screen?")
+    };
+    let error = turn.request(Some(&model("o3-mini", "text")), &[], &[]).unwrap_err();
+    assert!(matches!(error, AppError::Unsupported(_)));
+    let (request, _) = turn.request(Some(&model("gpt-4o", "multimodal")), &[], &[]).unwrap();
+    assert_eq!(serde_json::to_value(&request).unwrap()["image_base64"], "iVBORw0KGgo=");
+
+    // Images from earlier turns or tool results count too
+    let screen = Attachment::new("image/png", "iVBORw0KGgo=", AttachmentSource::Screen);
+    let history = vec![ChatMessage { role: Role::User, content: "Look".to_string(), attachments: vec![screen.clone()] }];
+    let with_history = ChatTurn { history, attachments: Vec::new(), ..turn.clone() };
+    assert!(matches!(with_history.request(Some(&model("o3-mini", "text")), &[], &[]), Err(AppError::Unsupported(_))));
+
+    let screenshot = ToolMessage::Tool {
+        tool_call_id: "call_1".to_string(),
+        name: "take_screenshot".to_string(),
+        content: "Attached.".to_string(),
+        attachments: vec![screen],
+    };
+    let text_only_turn = ChatTurn { attachments: Vec::new(), ..turn };
+    let error = text_only_turn.request(Some(&model("o3-mini", "text")), &[], &[screenshot]).unwrap_err();
+    assert!(matches!(error, AppError::Unsupported(_)));
+}
+
+#[tokio::test]
+async fn transcribes_audio() {
+    let mock = MockBackend::start().await;
+    let response = mock.backend().transcribe(Some(&credentials()), "UklGRg==".to_string(), &|_| {}).await.unwrap();
+
+    assert!(response.success);
+    assert_eq!(response.transcription.as_deref(), Some(TRANSCRIPTION));
+    let body: serde_json::Value = mock.requests_to("/api/audio").await[0].body_json().unwrap();
+    assert_eq!(body, json!({ "audio_base64": "UklGRg==" }));
+}
+
+#[tokio::test]
+async fn fetches_and_revalidates_models() {
+    let mock = MockBackend::start().await;
+    let backend = mock.backend();
+
+    let fetched = backend.refresh_models(None, &|_| {}).await.unwrap();
+    assert_eq!(fetched.etag.as_deref(), Some(MODELS_ETAG));
+    assert_eq!(fetched.models.len(), 2);
+
+    let stale = ModelCache { fetched_at: 0, ..fetched.clone() };
+    let revalidated = backend.refresh_models(Some(&stale), &|_| {}).await.unwrap();
+    assert_eq!(revalidated.models, fetched.models);
+    assert!(revalidated.fetched_at > 0);
+}
+
+#[tokio::test]
+async fn activates_license_and_reports_unknown_keys() {
+    let mock = MockBackend::start().await;
+    let backend = mock.backend();
+
+    let activated = backend.activate(LICENSE_KEY.to_string(), "test-instance".to_string()).await.unwrap();
+    assert!(activated.activated);
+    assert_eq!(activated.instance.unwrap().id, INSTANCE_ID);
+
+    let rejected = backend.activate("NOT-A-KEY".to_string(), "test-instance".to_string()).await.unwrap();
+    assert!(!rejected.activated);
+    assert_eq!(rejected.error.as_deref(), Some("license_key not found."));
+
+    let checkout = backend.checkout().await.unwrap();
+    assert_eq!(checkout.checkout_url.as_deref(), Some(CHECKOUT_URL));
+}
+
+#[tokio::test]
+async fn manages_license_instances() {
+    let mock = MockBackend::start().await;
+    let backend = mock.backend();
+
+    let listed = backend.list_instances(LICENSE_KEY.to_string()).await.unwrap();
+    let names: Vec<_> = listed.instances.iter().map(|instance| instance.name.as_str()).collect();
+    assert_eq!(names, ["test-instance", "old-laptop"]);
+    assert_eq!(listed.license.unwrap().activation_usage, 2);
+
+    let validated = backend.validate(LICENSE_KEY.to_string(), INSTANCE_ID.to_string()).await.unwrap();
+    assert!(validated.valid);
+    assert_eq!(validated.license.unwrap().status, "active");
+
+    let deactivated = backend.deactivate(LICENSE_KEY.to_string(), INSTANCE_ID.to_string()).await.unwrap();
+    assert!(deactivated.deactivated);
+    let unknown = backend.deactivate(LICENSE_KEY.to_string(), "instance-9999".to_string()).await.unwrap();
+    assert!(!unknown.deactivated);
+    assert!(unknown.error.is_some());
+
+    let sent: serde_json::Value = mock.requests_to("/deactivate").await[0].body_json().unwrap();
+    assert_eq!(sent, json!({ "license_key": LICENSE_KEY, "instance_id": INSTANCE_ID }));
+}
+
+#[test]
+fn keeps_one_instance_name_per_install() {
+    let dir = std::env::temp_dir().join(format!("pluely-install-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let id = license::install_id(&dir).unwrap();
+    assert_eq!(license::install_id(&dir).unwrap(), id);
+    assert_eq!(license::instance_name(&id), format!("pluely-{}", id));
+
+    let other = std::env::temp_dir().join(format!("pluely-install-other-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&other);
+    assert_ne!(license::install_id(&other).unwrap(), id);
+}
+
+#[tokio::test]
+async fn maps_backend_failures_to_typed_errors() {
+    let mock = MockBackend::start().await;
+    let wrong_key = BackendConfig { api_access_key: Some("wrong".to_string()), ..mock.config() };
+    let error = Backend::new(reqwest::Client::new(), wrong_key).checkout().await.unwrap_err();
+    assert!(matches!(error, AppError::Auth(_)), "{:?}", error);
+
+    // A Retry-After beyond the policy's limit is reported instead of waited out
+    mock.fail("/api/audio", ResponseTemplate::new(429).insert_header("Retry-After", "120")).await;
+    let error = mock.backend().transcribe(Some(&credentials()), String::new(), &|_| {}).await.unwrap_err();
+    assert!(matches!(error, AppError::Quota(_)), "{:?}", error);
+    assert_eq!(mock.requests_to("/api/audio").await.len(), 1);
+
+    mock.fail("/checkout", ResponseTemplate::new(500).set_body_string("upstream exploded")).await;
+    let error = mock.backend().checkout().await.unwrap_err();
+    assert!(matches!(error, AppError::Server { .. }), "{:?}", error);
+
+    mock.fail("/api/models", ResponseTemplate::new(200).set_body_string("{\"models\": [")).await;
+    let error = mock.backend().refresh_models(None, &|_| {}).await.unwrap_err();
+    assert!(matches!(error, AppError::Parse(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn retries_transient_failures_and_reports_them() {
+    let mock = MockBackend::start().await;
+    mock.fail("/api/chat", ResponseTemplate::new(503)).await;
+    let retries = Mutex::new(Vec::new());
+
+    let (request, _) = turn("Hello").request(None, &[], &[]).unwrap();
+    let error = mock
+        .backend()
+        .stream_chat(Some(&credentials()), None, &request, &|event| retries.lock().unwrap().push(event.attempt), &mut |_| {})
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, AppError::Server { .. }), "{:?}", error);
+    assert_eq!(*retries.lock().unwrap(), [2, 3]);
+    let sent = mock.requests_to("/api/chat").await;
+    assert_eq!(sent.len(), 3);
+    assert_eq!(sent[0].headers.get("provider").unwrap(), "None");
+}
+
+#[tokio::test]
+async fn model_refreshes_use_the_backend_retry_policy() {
+    let mock = MockBackend::start().await;
+    mock.fail("/api/models", ResponseTemplate::new(503)).await;
+
+    let error = mock.backend().check_health().await.unwrap_err();
+    assert!(matches!(error, AppError::Server { .. }), "{:?}", error);
+    // `MockBackend::backend` allows 3 attempts, the default policy 4
+    assert_eq!(mock.requests_to("/api/models").await.len(), 3);
+}
+
+#[test]
+fn reads_stored_credentials() {
+    let store = SecretStore::in_memory();
+    assert!(matches!(load_credentials(&store), Err(AppError::Auth(_))));
+
+    let selected = serde_json::to_string(&support::model_json("gpt-4o", "multimodal")).unwrap();
+    store.set(secret_store::LICENSE, secret_store::LICENSE_KEY, LICENSE_KEY).unwrap();
+    store.set(secret_store::LICENSE, secret_store::INSTANCE_ID, INSTANCE_ID).unwrap();
+    store.set(secret_store::LICENSE, secret_store::SELECTED_MODEL, &selected).unwrap();
+
+    let credentials = load_credentials(&store).unwrap();
+    assert_eq!(credentials.license_key, LICENSE_KEY);
+    assert_eq!(credentials.selected_model.unwrap().id, "gpt-4o");
+
+    clear_credentials(&store).unwrap();
+    assert!(matches!(load_credentials(&store), Err(AppError::Auth(_))));
+    assert!(store.list(secret_store::LICENSE).unwrap().is_empty());
+}
@@ -0,0 +1,219 @@
+use pluely_lib::chat::{ChatMessage, Role};
+use pluely_lib::curl_template::{
+    execute, get_by_path, parse_curl, render, streaming_content, RenderedRequest, TemplateInput,
+};
+use pluely_lib::error::AppError;
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use wiremock::matchers::{body_partial_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const OPENAI_TEMPLATE: &str = r#"curl https://api.openai.com/v1/chat/completions \
+  -H "Content-Type: application/json" \
+  -H "Authorization: Bearer {{API_KEY}}" \
+  -d '{
+    "model": "{{MODEL}}",
+    "messages": [
+      {"role": "system", "content": "{{SYSTEM_PROMPT}}"},
+      {"role": "user", "content": [
+        {"type": "text", "text": "{{TEXT}}"},
+        {"type": "image_url", "image_url": {"url": "data:image/png;base64,{{IMAGE}}"}}
+      ]}
+    ]
+  }'"#;
+
+fn input(user_message: &str) -> TemplateInput {
+    TemplateInput {
+        user_message: user_message.to_string(),
+        system_prompt: Some("Be brief".to_string()),
+        variables: HashMap::from([
+            ("api_key".to_string(), "sk-test".to_string()),
+            ("model".to_string(), "gpt-4o".to_string()),
+        ]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn parses_multiline_curl() {
+    let template = parse_curl(OPENAI_TEMPLATE).unwrap();
+
+    assert_eq!(template.method, Method::POST);
+    assert_eq!(template.url, "https://api.openai.com/v1/chat/completions");
+    assert_eq!(
+        template.headers,
+        vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("Authorization".to_string(), "Bearer {{API_KEY}}".to_string()),
+        ]
+    );
+    assert!(template.body.unwrap().contains("\"{{MODEL}}\""));
+}
+
+#[test]
+fn rejects_unsupported_options_and_bad_quoting() {
+    assert!(matches!(parse_curl("curl -o out.json https://example.com"), Err(AppError::Config(_))));
+    assert!(matches!(parse_curl("curl 'https://example.com"), Err(AppError::Config(_))));
+    assert!(matches!(parse_curl("wget https://example.com"), Err(AppError::Config(_))));
+}
+
+#[test]
+fn substituted_text_is_json_escaped() {
+    let template = parse_curl(OPENAI_TEMPLATE).unwrap();
+    let request = render(&template, &input("Say \"hi\"\nthen {{MODEL}}"), false).unwrap();
+    let body: Value = serde_json::from_str(request.body.as_deref().unwrap()).unwrap();
+
+    assert_eq!(body["model"], "gpt-4o");
+    assert_eq!(body["messages"][0]["content"], "Be brief");
+    assert_eq!(body["messages"][1]["content"][0]["text"], "Say \"hi\"\nthen {{MODEL}}");
+    assert_eq!(request.headers[1].1, "Bearer sk-test");
+}
+
+#[test]
+fn expands_history_and_images() {
+    let template = parse_curl(OPENAI_TEMPLATE).unwrap();
+    let mut input = input("What is this?");
+    input.images_base64 = vec!["AAAA".to_string(), "BBBB".to_string()];
+    input.history = vec![
+        ChatMessage { role: Role::User, content: "Hello".to_string(), attachments: Vec::new() },
+        ChatMessage { role: Role::Assistant, content: "Hi there".to_string(), attachments: Vec::new() },
+    ];
+
+    let request = render(&template, &input, true).unwrap();
+    let body: Value = serde_json::from_str(request.body.as_deref().unwrap()).unwrap();
+    let messages = body["messages"].as_array().unwrap();
+
+    assert_eq!(body["stream"], true);
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[1], json!({ "role": "user", "content": "Hello" }));
+    assert_eq!(messages[2], json!({ "role": "assistant", "content": "Hi there" }));
+    let parts = messages[3]["content"].as_array().unwrap();
+    assert_eq!(parts.len(), 3);
+    assert_eq!(parts[1]["image_url"]["url"], "data:image/png;base64,AAAA");
+    assert_eq!(parts[2]["image_url"]["url"], "data:image/png;base64,BBBB");
+}
+
+#[test]
+fn drops_image_part_without_images() {
+    let template = parse_curl(OPENAI_TEMPLATE).unwrap();
+    let request = render(&template, &input("Hi"), false).unwrap();
+    let body: Value = serde_json::from_str(request.body.as_deref().unwrap()).unwrap();
+
+    assert_eq!(body["messages"][1]["content"], json!([{ "type": "text", "text": "Hi" }]));
+}
+
+#[test]
+fn reports_missing_variables_and_unsupported_images() {
+    let template = parse_curl(OPENAI_TEMPLATE).unwrap();
+    let mut without_key = input("Hi");
+    without_key.variables.remove("api_key");
+    assert!(matches!(render(&template, &without_key, false), Err(AppError::Config(m)) if m.contains("API_KEY")));
+
+    let text_only = parse_curl(r#"curl https://example.com/{{MODEL}} -d '{"prompt": "{{TEXT}}"}'"#).unwrap();
+    let mut with_image = input("Hi");
+    with_image.images_base64 = vec!["AAAA".to_string()];
+    assert!(matches!(render(&text_only, &with_image, false), Err(AppError::Config(_))));
+}
+
+#[test]
+fn url_values_are_percent_encoded() {
+    let template = parse_curl("curl 'https://example.com/models/{{MODEL}}:generate?key={{API_KEY}}'").unwrap();
+    let mut input = input("Hi");
+    input.variables.insert("api_key".to_string(), "a&b=c".to_string());
+
+    let request = render(&template, &input, false).unwrap();
+    assert_eq!(request.url, "https://example.com/models/gpt-4o:generate?key=a%26b%3Dc");
+    assert_eq!(request.method, Method::GET);
+}
+
+#[test]
+fn finds_content_by_path() {
+    let response = json!({ "choices": [{ "message": { "content": "Hi" } }] });
+    assert_eq!(get_by_path(&response, "choices[0].message.content"), Some(&json!("Hi")));
+    assert_eq!(get_by_path(&response, "choices[1].message"), None);
+
+    let openai = json!({ "choices": [{ "delta": { "content": "Hel" } }] });
+    let gemini = json!({ "candidates": [{ "content": { "parts": [{ "text": "lo" }] } }] });
+    let anthropic = json!({ "type": "content_block_delta", "delta": { "type": "text_delta", "text": "!" } });
+    assert_eq!(streaming_content(&openai, "choices[0].message.content").as_deref(), Some("Hel"));
+    assert_eq!(streaming_content(&gemini, "").as_deref(), Some("lo"));
+    assert_eq!(streaming_content(&anthropic, "content[0].text").as_deref(), Some("!"));
+}
+
+#[tokio::test]
+async fn executes_streaming_template() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("authorization", "Bearer sk-test"))
+        .and(body_partial_json(json!({ "model": "gpt-4o", "stream": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            concat!(
+                "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\" world\"}}]}\n\n",
+                "data: [DONE]\n\n",
+            ),
+            "text/event-stream",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let curl = OPENAI_TEMPLATE.replace("https://api.openai.com", &server.uri());
+    let request = render(&parse_curl(&curl).unwrap(), &input("Hi"), true).unwrap();
+
+    let mut chunks = Vec::new();
+    let response = execute(
+        &reqwest::Client::new(),
+        &request,
+        true,
+        "choices[0].message.content",
+        &mut |chunk| chunks.push(chunk.to_string()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response, "Hello world");
+    assert_eq!(chunks, vec!["Hello", " world"]);
+}
+
+#[tokio::test]
+async fn executes_non_streaming_template() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{ "message": { "role": "assistant", "content": "Hi!" } }]
+        })))
+        .mount(&server)
+        .await;
+
+    let curl = OPENAI_TEMPLATE.replace("https://api.openai.com", &server.uri());
+    let request = render(&parse_curl(&curl).unwrap(), &input("Hi"), false).unwrap();
+    let response = execute(&reqwest::Client::new(), &request, false, "choices[0].message.content", &mut |_| {})
+        .await
+        .unwrap();
+
+    assert_eq!(response, "Hi!");
+}
+
+#[tokio::test]
+async fn json_bodies_default_to_a_json_content_type() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(header("content-type", "application/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "response": "Hi!" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let request = RenderedRequest {
+        method: Method::POST,
+        url: format!("{}/api/generate", server.uri()),
+        headers: Vec::new(),
+        body: Some(r#"{"model":"llama3","prompt":"Hi"}"#.to_string()),
+    };
+    let response = execute(&reqwest::Client::new(), &request, false, "response", &mut |_| {}).await.unwrap();
+    assert_eq!(response, "Hi!");
+}
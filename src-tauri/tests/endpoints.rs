@@ -0,0 +1,109 @@
+mod support;
+
+use pluely_lib::backend::{AuthScheme, Backend, BackendConfig};
+use pluely_lib::endpoints::{self, EndpointProfile, DEFAULT_PROFILE};
+use pluely_lib::secret_store::{SecretStore, ENDPOINT_KEYS};
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+use support::MockBackend;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pluely-endpoints-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn profile(name: &str, base_url: &str, auth: AuthScheme) -> EndpointProfile {
+    EndpointProfile { name: name.to_string(), base_url: base_url.to_string(), payment_url: None, auth }
+}
+
+#[test]
+fn switches_between_saved_profiles() {
+    let dir = temp_dir("switch");
+    let store = SecretStore::in_memory();
+
+    let company = profile("company", "https://pluely.internal.example.com/", AuthScheme::Header { name: "X-Api-Key".to_string() });
+    endpoints::save_profile(&dir, &store, company, Some("company-key")).unwrap();
+
+    // Access keys go to the secret store, never to the profiles file
+    assert_eq!(store.get(ENDPOINT_KEYS, "company").unwrap().as_deref(), Some("company-key"));
+    assert!(!fs::read_to_string(dir.join("endpoint_profiles.json")).unwrap().contains("company-key"));
+
+    let names: Vec<_> = endpoints::list_profiles(&dir, &store).unwrap().into_iter().map(|p| (p.name, p.active)).collect();
+    assert_eq!(names, [(DEFAULT_PROFILE.to_string(), true), ("company".to_string(), false)]);
+
+    // The built-in profile has a key only when the build (or environment) provides one
+    let built_in = endpoints::list_profiles(&dir, &store).unwrap().remove(0);
+    assert_eq!(built_in.has_access_key, BackendConfig::from_env().api_access_key.is_some());
+
+    endpoints::set_active(&dir, "company").unwrap();
+    let config = endpoints::active_config(&dir, &store).unwrap();
+    assert_eq!(config.app_endpoint.as_deref(), Some("https://pluely.internal.example.com"));
+    // The license saved here was issued for the built-in profile only
+    assert!(!config.sends_license);
+    assert!(endpoints::config_for(&dir, &store, DEFAULT_PROFILE).unwrap().sends_license);
+    assert_eq!(config.auth_header().unwrap(), Some(("X-Api-Key".to_string(), "company-key".to_string())));
+
+    // Editing without a key keeps the stored one
+    endpoints::save_profile(&dir, &store, profile("company", "https://pluely.example.org", AuthScheme::Bearer), None).unwrap();
+    let config = endpoints::active_config(&dir, &store).unwrap();
+    assert_eq!(config.auth_header().unwrap(), Some(("Authorization".to_string(), "Bearer company-key".to_string())));
+
+    endpoints::remove_profile(&dir, &store, "company").unwrap();
+    assert_eq!(endpoints::load_settings(&dir).unwrap().active, None);
+    assert_eq!(store.get(ENDPOINT_KEYS, "company").unwrap(), None);
+    assert!(endpoints::set_active(&dir, "company").is_err());
+}
+
+#[test]
+fn rejects_invalid_profiles() {
+    let dir = temp_dir("invalid");
+    let store = SecretStore::in_memory();
+    let save = |profile: EndpointProfile| endpoints::save_profile(&dir, &store, profile, None).unwrap_err();
+
+    assert_eq!(save(profile(DEFAULT_PROFILE, "https://example.com", AuthScheme::Bearer)).code(), "config_error");
+    assert_eq!(save(profile("has space", "https://example.com", AuthScheme::Bearer)).code(), "config_error");
+    assert_eq!(save(profile("ftp", "ftp://example.com", AuthScheme::Bearer)).code(), "config_error");
+    assert_eq!(save(profile("relative", "/api", AuthScheme::Bearer)).code(), "config_error");
+    let bad_header = AuthScheme::Header { name: "Bad Header".to_string() };
+    assert_eq!(save(profile("header", "https://example.com", bad_header)).code(), "config_error");
+
+    assert!(endpoints::remove_profile(&dir, &store, DEFAULT_PROFILE).is_err());
+    assert!(endpoints::list_profiles(&dir, &store).unwrap().len() == 1);
+}
+
+#[tokio::test]
+async fn health_check_uses_the_profile_auth_scheme() {
+    let dir = temp_dir("health");
+    let store = SecretStore::in_memory();
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/models"))
+        .and(header("x-api-key", "company-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "models": [support::model_json("llama-3", "text")] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({ "error": "Invalid API access key" })))
+        .with_priority(10)
+        .mount(&server)
+        .await;
+
+    let auth = AuthScheme::Header { name: "X-Api-Key".to_string() };
+    endpoints::save_profile(&dir, &store, profile("company", &server.uri(), auth), Some("company-key")).unwrap();
+    let config = endpoints::config_for(&dir, &store, "company").unwrap();
+    let report = Backend::new(reqwest::Client::new(), config).check_health().await.unwrap();
+    assert_eq!(report.models, 1);
+
+    endpoints::save_profile(&dir, &store, profile("company", &server.uri(), AuthScheme::None), None).unwrap();
+    let config = endpoints::config_for(&dir, &store, "company").unwrap();
+    let error = Backend::new(reqwest::Client::new(), config).check_health().await.unwrap_err();
+    assert_eq!(error.code(), "auth_error");
+
+    // The hosted backend's scheme
+    let mock = MockBackend::start().await;
+    assert_eq!(mock.backend().check_health().await.unwrap().models, 2);
+}
@@ -0,0 +1,27 @@
+use pluely_lib::error::AppError;
+use reqwest::StatusCode;
+use serde_json::json;
+
+#[test]
+fn names_non_json_bodies_only_by_status() {
+    let page = "<html><body>Bad gateway for https://internal.example/v1?key=sk-secret</body></html>";
+    let error = AppError::from_response(StatusCode::BAD_GATEWAY, page);
+    assert_eq!(error.message(), "Server error (502): Bad Gateway");
+    assert_eq!(AppError::from_response(StatusCode::FORBIDDEN, page).message(), "Server error (403)");
+}
+
+#[test]
+fn redacts_and_shortens_json_messages() {
+    let body = json!({ "error": { "message": "Model not found" } }).to_string();
+    assert_eq!(AppError::from_response(StatusCode::NOT_FOUND, &body).message(), "Server error (404): Model not found");
+
+    let echoed = format!("Invalid image {}", "A".repeat(5000));
+    let body = json!({ "message": echoed }).to_string();
+    let message = AppError::from_response(StatusCode::TOO_MANY_REQUESTS, &body).message();
+    assert_eq!(message, "Invalid image [base64, 5000 chars]");
+
+    let body = json!({ "error": "x ".repeat(1000) }).to_string();
+    let message = AppError::from_response(StatusCode::UNAUTHORIZED, &body).message();
+    assert!(message.chars().count() <= 301, "{}", message);
+    assert!(message.ends_with('…'));
+}
@@ -0,0 +1,98 @@
+use pluely_lib::error::AppError;
+use pluely_lib::fallback::{should_fall_back, stream_local, LocalFallbackSettings};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const COMPLETION_STREAM: &str = concat!(
+    "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\n",
+    "data: {\"choices\":[{\"delta\":{\"content\":\" from llama\"}}]}\n\n",
+    "data: [DONE]\n\n",
+);
+
+fn settings(server: &MockServer) -> LocalFallbackSettings {
+    LocalFallbackSettings {
+        enabled: true,
+        base_url: format!("{}/v1", server.uri()),
+        model: "llama3.2".to_string(),
+        context_window: Some(4096),
+    }
+}
+
+#[tokio::test]
+async fn streams_from_local_openai_compatible_server() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({ "model": "llama3.2", "stream": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(COMPLETION_STREAM, "text/event-stream"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut chunks = Vec::new();
+    let response = stream_local(
+        &reqwest::Client::new(),
+        &settings(&server),
+        "Hi".to_string(),
+        Some("Be brief".to_string()),
+        Vec::new(),
+        Vec::new(),
+        &mut |chunk| chunks.push(chunk.to_string()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response, "Hello from llama");
+    assert_eq!(chunks, vec!["Hello", " from llama"]);
+}
+
+#[tokio::test]
+async fn reports_local_server_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "error": "model 'llama3.2' not found" })))
+        .mount(&server)
+        .await;
+
+    let error = stream_local(
+        &reqwest::Client::new(),
+        &settings(&server),
+        "Hi".to_string(),
+        None,
+        Vec::new(),
+        Vec::new(),
+        &mut |_| {},
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(
+        error,
+        AppError::Server { status: Some(404), message: "model 'llama3.2' not found".to_string() }
+    );
+}
+
+#[tokio::test]
+async fn unreachable_backend_triggers_fallback() {
+    // Nothing listens on the discard port
+    let error: AppError = reqwest::Client::new()
+        .post("http://127.0.0.1:9/api/chat")
+        .send()
+        .await
+        .unwrap_err()
+        .into();
+
+    assert!(matches!(error, AppError::Network(_)));
+    assert!(should_fall_back(&error));
+}
+
+#[test]
+fn backend_answers_are_not_replaced_by_local_ones() {
+    assert!(!should_fall_back(&AppError::Auth("License expired".to_string())));
+    assert!(!should_fall_back(&AppError::Quota("Too many requests".to_string())));
+    assert!(!should_fall_back(&AppError::Server { status: Some(500), message: "boom".to_string() }));
+    assert!(should_fall_back(&AppError::Server { status: Some(503), message: "down".to_string() }));
+}
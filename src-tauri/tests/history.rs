@@ -0,0 +1,42 @@
+use pluely_lib::chat::{fit_history, ChatMessage, Role};
+
+fn message(role: Role, content: &str) -> ChatMessage {
+    ChatMessage { role, content: content.to_string(), attachments: Vec::new() }
+}
+
+fn conversation(turns: usize, words_per_turn: usize) -> Vec<ChatMessage> {
+    (0..turns)
+        .map(|i| {
+            let role = if i % 2 == 0 { Role::User } else { Role::Assistant };
+            message(role, &format!("turn{} ", i).repeat(words_per_turn))
+        })
+        .collect()
+}
+
+#[test]
+fn keeps_history_that_fits() {
+    let history = conversation(4, 10);
+    let fitted = fit_history(history, 8192, 100);
+    assert_eq!(fitted.len(), 4);
+}
+
+#[test]
+fn drops_oldest_turns_and_adds_a_summary() {
+    // Each turn is ~500 tokens, only a handful fit in the budget
+    let history = conversation(20, 300);
+    let fitted = fit_history(history, 4096, 100);
+
+    assert!(fitted.len() < 20);
+    assert_eq!(fitted[0].role, Role::System);
+    assert!(fitted[0].content.contains("omitted"));
+    assert!(fitted[0].content.contains("turn0"));
+    // The newest turn always survives
+    assert!(fitted.last().unwrap().content.starts_with("turn19"));
+}
+
+#[test]
+fn returns_empty_history_when_nothing_fits() {
+    let history = conversation(2, 300);
+    let fitted = fit_history(history, 1024, 0);
+    assert!(fitted.is_empty());
+}
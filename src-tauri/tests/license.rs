@@ -0,0 +1,134 @@
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey};
+use pluely_lib::error::AppError;
+use pluely_lib::license::{self, LicenseClaims, LicenseState, LicenseVerifier};
+use pluely_lib::secret_store::{self, SecretStore};
+use std::collections::BTreeMap;
+
+const DAY: u64 = 24 * 60 * 60;
+// 2024-03-10T12:00:00Z
+const NOW: u64 = 1_710_072_000;
+const INSTANCE_ID: &str = "instance-0001";
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+fn verifier() -> LicenseVerifier {
+    LicenseVerifier::new(&signing_key().verifying_key().to_bytes(), 7).unwrap()
+}
+
+fn claims(issued_at: u64, expires_at: Option<u64>) -> LicenseClaims {
+    LicenseClaims {
+        instance_id: INSTANCE_ID.to_string(),
+        issued_at,
+        expires_at,
+        grace_days: None,
+        entitlements: BTreeMap::from([("chat".to_string(), true), ("transcription".to_string(), false)]),
+    }
+}
+
+/// A token as the payment service signs it
+fn token(key: &SigningKey, claims: &LicenseClaims) -> String {
+    let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    let signed = format!("v1.{}", engine.encode(serde_json::to_vec(claims).unwrap()));
+    let signature = key.sign(signed.as_bytes());
+    format!("{}.{}", signed, engine.encode(signature.to_bytes()))
+}
+
+#[test]
+fn reports_days_remaining_and_entitlements_offline() {
+    let token = token(&signing_key(), &claims(NOW - DAY, Some(NOW + 30 * DAY + 3_600)));
+    let status = verifier().status(Some(INSTANCE_ID), Some(&token), NOW);
+
+    assert_eq!(status.state, LicenseState::Active);
+    assert!(status.active);
+    assert_eq!(status.days_remaining, Some(30));
+    assert_eq!(status.offline_days_remaining, Some(6));
+    assert_eq!(status.entitlements.get("chat"), Some(&true));
+    assert_eq!(status.entitlements.get("transcription"), Some(&false));
+}
+
+#[test]
+fn expires_and_asks_for_validation_after_the_grace_period() {
+    let verifier = verifier();
+
+    let expired = token(&signing_key(), &claims(NOW - DAY, Some(NOW - 1)));
+    let status = verifier.status(Some(INSTANCE_ID), Some(&expired), NOW);
+    assert_eq!(status.state, LicenseState::Expired);
+    assert!(!status.active);
+
+    let stale = token(&signing_key(), &claims(NOW - 8 * DAY, None));
+    let status = verifier.status(Some(INSTANCE_ID), Some(&stale), NOW);
+    assert_eq!(status.state, LicenseState::NeedsValidation);
+    assert_eq!((status.days_remaining, status.offline_days_remaining), (None, Some(0)));
+
+    // The license's own grace period wins over the build's
+    let longer = token(&signing_key(), &LicenseClaims { grace_days: Some(30), ..claims(NOW - 8 * DAY, None) });
+    let status = verifier.status(Some(INSTANCE_ID), Some(&longer), NOW);
+    assert_eq!(status.state, LicenseState::Active);
+    assert_eq!(status.offline_days_remaining, Some(22));
+}
+
+#[test]
+fn rejects_forged_tampered_and_foreign_tokens() {
+    let verifier = verifier();
+    let claims = claims(NOW, Some(NOW + DAY));
+
+    let forged = token(&SigningKey::from_bytes(&[9; 32]), &claims);
+    let error = verifier.verify(&forged, INSTANCE_ID).unwrap_err();
+    assert_eq!(error, AppError::Auth("License token signature is invalid".to_string()));
+
+    // Lifting the expiry of a genuine token breaks its signature
+    let genuine = token(&signing_key(), &claims);
+    let (_, signature) = genuine.rsplit_once('.').unwrap();
+    let lifted = token(&SigningKey::from_bytes(&[9; 32]), &LicenseClaims { expires_at: None, ..claims.clone() });
+    let (lifted_claims, _) = lifted.rsplit_once('.').unwrap();
+    assert!(verifier.verify(&format!("{}.{}", lifted_claims, signature), INSTANCE_ID).is_err());
+
+    let status = verifier.status(Some("instance-0002"), Some(&genuine), NOW);
+    assert_eq!(status.state, LicenseState::Invalid);
+    assert_eq!(status.error.as_deref(), Some("License token was issued for another instance"));
+
+    assert_eq!(verifier.status(Some(INSTANCE_ID), Some("not-a-token"), NOW).state, LicenseState::Invalid);
+}
+
+#[test]
+fn stores_only_tokens_that_verify() {
+    let store = SecretStore::in_memory();
+    let verifier = verifier();
+    assert_eq!(license::status(&store, &verifier, NOW).unwrap().state, LicenseState::Missing);
+
+    let forged = token(&SigningKey::from_bytes(&[9; 32]), &claims(NOW, None));
+    assert!(license::save_activation(&store, &verifier, "PLUELY-1234", INSTANCE_ID, Some(&forged)).is_err());
+    assert_eq!(store.get(secret_store::LICENSE, secret_store::LICENSE_KEY).unwrap(), None);
+
+    // Activations from before tokens keep working until the next validation
+    license::save_activation(&store, &verifier, "PLUELY-1234", INSTANCE_ID, None).unwrap();
+    let status = license::status(&store, &verifier, NOW).unwrap();
+    assert_eq!(status.state, LicenseState::Unverified);
+    assert!(status.active);
+
+    let fresh = token(&signing_key(), &claims(NOW, Some(NOW + 10 * DAY)));
+    license::save_token(&store, &verifier, &fresh).unwrap();
+    assert_eq!(license::status(&store, &verifier, NOW).unwrap().days_remaining, Some(10));
+}
+
+#[test]
+fn a_refused_validation_stays_invalid_until_the_next_token() {
+    let store = SecretStore::in_memory();
+    let verifier = verifier();
+    let issued = token(&signing_key(), &claims(NOW, None));
+    license::save_activation(&store, &verifier, "PLUELY-1234", INSTANCE_ID, Some(&issued)).unwrap();
+    assert!(license::status(&store, &verifier, NOW).unwrap().active);
+
+    license::revoke(&store, Some("License has been refunded")).unwrap();
+    assert_eq!(store.get(secret_store::LICENSE, secret_store::LICENSE_TOKEN).unwrap(), None);
+    let status = license::status(&store, &verifier, NOW).unwrap();
+    assert_eq!(status.state, LicenseState::Invalid);
+    assert!(!status.active);
+    assert_eq!(status.error.as_deref(), Some("License has been refunded"));
+
+    license::save_token(&store, &verifier, &issued).unwrap();
+    assert_eq!(license::status(&store, &verifier, NOW).unwrap().state, LicenseState::Active);
+}
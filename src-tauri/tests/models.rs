@@ -0,0 +1,85 @@
+use pluely_lib::models::{load_cache, now_secs, refresh_catalogue, save_cache, Model, ModelCache, CACHE_TTL};
+use pluely_lib::retry::RetryPolicy;
+use serde_json::json;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn model_json(id: &str, modality: &str) -> serde_json::Value {
+    json!({
+        "provider": "openai",
+        "name": id,
+        "id": id,
+        "model": id,
+        "description": "",
+        "modality": modality,
+        "isAvailable": true,
+    })
+}
+
+#[tokio::test]
+async fn stores_etag_and_revalidates() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/models"))
+        .and(header("if-none-match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/models"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("ETag", "\"v1\"")
+                .set_body_json(json!({ "models": [model_json("gpt-4o", "multimodal")] })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/models", server.uri());
+    let auth = ("Authorization".to_string(), "Bearer key".to_string());
+
+    let fetched = refresh_catalogue(&client, &url, Some(&auth), None, &RetryPolicy::default(), &|_| {}).await.unwrap();
+    assert_eq!(fetched.etag.as_deref(), Some("\"v1\""));
+    assert_eq!(fetched.models.len(), 1);
+
+    let stale = ModelCache { fetched_at: 0, ..fetched.clone() };
+    let revalidated = refresh_catalogue(&client, &url, Some(&auth), Some(&stale), &RetryPolicy::default(), &|_| {}).await.unwrap();
+    assert_eq!(revalidated.models, fetched.models);
+    assert!(revalidated.is_fresh(now_secs()));
+}
+
+#[test]
+fn cache_expires_after_ttl() {
+    let cache = ModelCache { fetched_at: 1_000, etag: None, models: Vec::new() };
+    assert!(cache.is_fresh(1_000 + CACHE_TTL.as_secs() - 1));
+    assert!(!cache.is_fresh(1_000 + CACHE_TTL.as_secs()));
+}
+
+#[test]
+fn cache_round_trips_through_disk() {
+    let dir = std::env::temp_dir().join(format!("pluely-models-{}", std::process::id()));
+    let model: Model = serde_json::from_value(model_json("gpt-4o", "multimodal")).unwrap();
+    let cache = ModelCache { fetched_at: 42, etag: Some("\"v2\"".to_string()), models: vec![model] };
+
+    save_cache(&dir, &cache).unwrap();
+    assert_eq!(load_cache(&dir), Some(cache));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn vision_support_falls_back_to_modality() {
+    let text_only: Model = serde_json::from_value(model_json("gpt-3.5", "text")).unwrap();
+    let multimodal: Model = serde_json::from_value(model_json("gpt-4o", "multimodal")).unwrap();
+    assert!(!text_only.accepts_images());
+    assert!(multimodal.accepts_images());
+
+    let mut explicit = model_json("o1", "multimodal");
+    explicit["supportsVision"] = json!(false);
+    explicit["inputPricePerToken"] = json!(0.000015);
+    let explicit: Model = serde_json::from_value(explicit).unwrap();
+    assert!(!explicit.accepts_images());
+    assert_eq!(explicit.input_price_per_token, Some(0.000015));
+}
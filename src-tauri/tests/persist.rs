@@ -0,0 +1,97 @@
+use pluely_lib::persist::{self, append_line, read_json, update_json, write_json};
+use pluely_lib::tools::{self, ToolSettings};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pluely-persist-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn replaces_the_file_and_keeps_the_previous_version() {
+    let path = temp_dir("replace").join("settings.json");
+
+    assert_eq!(read_json::<u32>(&path, "settings").unwrap(), None);
+    write_json(&path, &1, "settings").unwrap();
+    write_json(&path, &2, "settings").unwrap();
+
+    assert_eq!(read_json::<u32>(&path, "settings").unwrap(), Some(2));
+    assert_eq!(fs::read_to_string(path.with_file_name("settings.json.bak")).unwrap(), "1");
+    assert!(!path.with_file_name("settings.json.tmp").exists());
+}
+
+#[test]
+fn recovers_a_corrupted_file_from_its_backup() {
+    let dir = temp_dir("recover");
+    let mut settings = ToolSettings::default();
+    settings.tools.insert("search_conversations".to_string(), false);
+    tools::save_settings(&dir, &settings).unwrap();
+    tools::save_settings(&dir, &settings).unwrap();
+
+    // A crash mid-write with plain fs::write would have left this
+    fs::write(dir.join("tool_settings.json"), "{\"tools\": {\"search_con").unwrap();
+
+    assert_eq!(tools::load_settings(&dir).unwrap(), settings);
+    // Restored on disk too, not only in memory
+    let restored: ToolSettings = serde_json::from_str(&fs::read_to_string(dir.join("tool_settings.json")).unwrap()).unwrap();
+    assert_eq!(restored, settings);
+}
+
+#[test]
+fn a_corrupted_file_never_replaces_a_good_backup() {
+    let path = temp_dir("bad-backup").join("settings.json");
+    write_json(&path, &1, "settings").unwrap();
+    write_json(&path, &2, "settings").unwrap();
+    fs::write(&path, "garbage").unwrap();
+
+    write_json(&path, &3, "settings").unwrap();
+    assert_eq!(fs::read_to_string(path.with_file_name("settings.json.bak")).unwrap(), "1");
+
+    // Without a usable backup the parse error is reported
+    fs::write(&path, "garbage").unwrap();
+    fs::write(path.with_file_name("settings.json.bak"), "garbage").unwrap();
+    let error = read_json::<u32>(&path, "settings").unwrap_err();
+    assert_eq!(error.code(), "parse_error");
+    assert!(error.message().starts_with("Failed to parse settings"));
+}
+
+#[test]
+fn concurrent_updates_and_appends_are_not_lost() {
+    let dir = temp_dir("concurrent");
+    let path = dir.join("counters.json");
+    let log = dir.join("log.jsonl");
+
+    let writers: Vec<_> = (0..8)
+        .map(|writer| {
+            let (path, log) = (path.clone(), log.clone());
+            thread::spawn(move || {
+                for i in 0..25 {
+                    update_json(&path, "counters", |counters: &mut BTreeMap<String, u32>| {
+                        *counters.entry(format!("writer-{}", writer)).or_default() += 1;
+                    })
+                    .unwrap();
+                    append_line(&log, &format!("{{\"writer\":{},\"i\":{}}}", writer, i), "log").unwrap();
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    let counters: BTreeMap<String, u32> = read_json(&path, "counters").unwrap().unwrap();
+    assert_eq!(counters.len(), 8);
+    assert!(counters.values().all(|&count| count == 25));
+
+    let lines = fs::read_to_string(&log).unwrap();
+    assert_eq!(lines.lines().count(), 200);
+    assert!(lines.lines().all(|line| serde_json::from_str::<serde_json::Value>(line).is_ok()));
+
+    // The lock is released once the guard is dropped
+    drop(persist::lock(&path, "counters").unwrap());
+    update_json(&path, "counters", |counters: &mut BTreeMap<String, u32>| counters.clear()).unwrap();
+}
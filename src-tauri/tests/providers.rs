@@ -0,0 +1,120 @@
+use pluely_lib::error::AppError;
+use pluely_lib::providers::{
+    self, api_key_for, delete_api_key, origin_of, store_api_key, ProviderConfig, ProviderKind, ProviderRequest,
+};
+use pluely_lib::secret_store::{SecretStore, PROVIDER_KEYS};
+use pluely_lib::structured::OutputSchema;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pluely-providers-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn origins_need_a_fixed_http_host() {
+    assert_eq!(origin_of("https://api.openai.com/v1/chat/completions?key={{API_KEY}}").unwrap(), "https://api.openai.com");
+    assert_eq!(origin_of("http://localhost:11434/api/{{MODEL}}").unwrap(), "http://localhost:11434");
+    for url in ["https://{{HOST}}/v1", "https://api.example.com.{{SUFFIX}}/v1", "ftp://example.com", "api.openai.com/v1"] {
+        assert!(origin_of(url).is_err(), "{}", url);
+    }
+}
+
+#[test]
+fn keys_are_only_sent_to_the_host_they_were_saved_for() {
+    let dir = temp_dir("origin");
+    let store = SecretStore::in_memory();
+    store_api_key(&dir, &store, "openai", "sk-test", "https://api.openai.com/v1/chat/completions").unwrap();
+
+    let key = api_key_for(&dir, &store, "openai", "https://api.openai.com/v1/responses").unwrap();
+    assert_eq!(key.as_deref(), Some("sk-test"));
+    let error = api_key_for(&dir, &store, "openai", "https://collector.example.com/v1/chat/completions").unwrap_err();
+    assert!(matches!(error, AppError::Auth(_)), "{:?}", error);
+
+    // A key written without an origin isn't trusted with any host
+    store.set(PROVIDER_KEYS, "groq", "gsk-test").unwrap();
+    assert!(api_key_for(&dir, &store, "groq", "https://api.groq.com/openai/v1").is_err());
+
+    delete_api_key(&dir, &store, "openai").unwrap();
+    assert_eq!(api_key_for(&dir, &store, "openai", "https://api.openai.com/v1").unwrap(), None);
+    assert!(providers::load_settings(&dir).unwrap().key_origins.is_empty());
+}
+
+#[test]
+fn direct_providers_are_resolved_by_id() {
+    let dir = temp_dir("direct");
+    let store = SecretStore::in_memory();
+    let config = ProviderConfig {
+        id: "team-anthropic".to_string(),
+        kind: ProviderKind::Anthropic,
+        base_url: "https://api.anthropic.com/v1/".to_string(),
+        model: "claude-sonnet".to_string(),
+        max_tokens: None,
+        context_window: None,
+    };
+    providers::save_provider(&dir, config.clone()).unwrap();
+    let saved = providers::provider_config(&dir, "team-anthropic").unwrap();
+    assert_eq!(saved.base_url, "https://api.anthropic.com/v1");
+    store_api_key(&dir, &store, &saved.id, "sk-ant", &saved.base_url).unwrap();
+
+    // Pointing the provider elsewhere doesn't take the key along
+    providers::save_provider(&dir, ProviderConfig { base_url: "https://collector.example.com/v1".to_string(), ..config }).unwrap();
+    let moved = providers::provider_config(&dir, "team-anthropic").unwrap();
+    assert!(api_key_for(&dir, &store, &moved.id, &moved.base_url).is_err());
+
+    providers::remove_provider(&dir, &store, "team-anthropic").unwrap();
+    assert!(providers::provider_config(&dir, "team-anthropic").is_err());
+    assert_eq!(store.get(PROVIDER_KEYS, "team-anthropic").unwrap(), None);
+}
+
+async fn structured_request(kind: ProviderKind, route: &str, stream: &str) -> (String, serde_json::Value) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(route))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(stream.to_string(), "text/event-stream"))
+        .mount(&server)
+        .await;
+    let config = ProviderConfig {
+        id: "direct".to_string(),
+        kind,
+        base_url: server.uri(),
+        model: "some-model".to_string(),
+        max_tokens: None,
+        context_window: None,
+    };
+    let schema = OutputSchema::new(Some("items".to_string()), json!({ "type": "object" })).unwrap();
+    let request = ProviderRequest::new(&config, "List them".to_string(), None, Vec::new(), Vec::new())
+        .with_response_format(schema.response_format());
+
+    let answer = providers::stream_chat(&reqwest::Client::new(), &config, Some("key"), &request, &mut |_| {})
+        .await
+        .unwrap();
+    let sent = server.received_requests().await.unwrap();
+    (answer, sent[0].body_json().unwrap())
+}
+
+#[tokio::test]
+async fn structured_output_uses_each_providers_own_option() {
+    let stream = "data: {\"choices\":[{\"delta\":{\"content\":\"{}\"}}]}\n\ndata: [DONE]\n\n";
+    let (answer, body) = structured_request(ProviderKind::OpenAiCompatible, "/chat/completions", stream).await;
+    assert_eq!(answer, "{}");
+    assert_eq!(body["response_format"]["type"], "json_schema");
+    assert_eq!(body["response_format"]["json_schema"]["name"], "items");
+
+    let stream = concat!(
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{}\"}}\n\n",
+        "event: message_stop\n",
+        "data: {\"type\":\"message_stop\"}\n\n",
+    );
+    let (answer, body) = structured_request(ProviderKind::Anthropic, "/messages", stream).await;
+    assert_eq!(answer, "{}");
+    assert_eq!(body["tools"][0]["name"], "items");
+    assert_eq!(body["tools"][0]["input_schema"], json!({ "type": "object" }));
+    assert_eq!(body["tool_choice"], json!({ "type": "tool", "name": "items" }));
+}
@@ -0,0 +1,41 @@
+use base64::Engine;
+use pluely_lib::push_to_talk::{self, Recording, MAX_SEGMENT_SECS};
+use std::io::Cursor;
+
+fn decode(audio_base64: &str) -> (hound::WavSpec, Vec<i16>) {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(audio_base64).unwrap();
+    let mut reader = hound::WavReader::new(Cursor::new(bytes)).unwrap();
+    let samples = reader.samples::<i16>().map(Result::unwrap).collect();
+    (reader.spec(), samples)
+}
+
+#[test]
+fn sends_the_whole_held_segment_as_mono_wav() {
+    let mut recording = Recording::new(1_000);
+    // Stereo frames, averaged per frame
+    let frames: Vec<f32> = (0..500).flat_map(|_| [0.5, -0.5]).chain((0..500).flat_map(|_| [1.0, 0.0])).collect();
+    recording.push_interleaved(&frames, 2);
+    assert_eq!(recording.duration_ms(), 1_000);
+
+    let (spec, samples) = decode(&recording.into_wav_base64().unwrap().unwrap());
+    assert_eq!((spec.channels, spec.sample_rate, spec.bits_per_sample), (1, 1_000, 16));
+    assert_eq!(samples.len(), 1_000);
+    assert_eq!((samples[0], samples[999]), (0, i16::MAX / 2));
+}
+
+#[test]
+fn drops_taps_and_caps_long_holds() {
+    let mut tap = Recording::new(1_000);
+    (0..299).for_each(|_| tap.push(0.1));
+    assert_eq!(tap.into_wav_base64().unwrap(), None);
+
+    let mut stuck = Recording::new(10);
+    (0..10 * MAX_SEGMENT_SECS + 50).for_each(|_| stuck.push(0.1));
+    assert_eq!(stuck.duration_ms(), MAX_SEGMENT_SECS * 1_000);
+}
+
+#[test]
+fn clamps_samples_when_encoding() {
+    let (_, samples) = decode(&push_to_talk::wav_base64(16_000, &[2.0, -2.0, 0.0]).unwrap());
+    assert_eq!(samples, [i16::MAX, -i16::MAX, 0]);
+}
@@ -0,0 +1,155 @@
+use pluely_lib::error::AppError;
+use pluely_lib::secret_store::{
+    self, migrate, EncryptedFile, Keychain, MemoryBackend, SecretBackend, SecretStore, StoreKind, ENDPOINT_KEYS,
+    INSTANCE_ID, LICENSE, LICENSE_KEY, PROVIDER_KEYS, SCHEMA_VERSION, SELECTED_MODEL,
+};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pluely-secrets-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_plaintext(dir: &Path) {
+    let plaintext = json!({ "license_key": "PLUELY-1234", "instance_id": "instance-1", "selected_pluely_model": null });
+    fs::write(dir.join("secure_storage.json"), plaintext.to_string()).unwrap();
+}
+
+#[test]
+fn encrypted_file_round_trips_without_plaintext_on_disk() {
+    let dir = temp_dir("round-trip");
+    let store = SecretStore::with_backend(Box::new(EncryptedFile::new(&dir)), &dir);
+    assert_eq!(store.kind(), StoreKind::EncryptedFile);
+    assert_eq!(store.get(LICENSE, LICENSE_KEY).unwrap(), None);
+
+    store.set(LICENSE, LICENSE_KEY, "PLUELY-1234").unwrap();
+    store.set(PROVIDER_KEYS, "openai", "sk-test").unwrap();
+    let on_disk = fs::read_to_string(dir.join("secure_storage.enc")).unwrap();
+    assert!(!on_disk.contains("PLUELY-1234") && !on_disk.contains("sk-test"));
+
+    // A new instance, as after a restart
+    let reopened = SecretStore::with_backend(Box::new(EncryptedFile::new(&dir)), &dir);
+    assert_eq!(reopened.get(LICENSE, LICENSE_KEY).unwrap().as_deref(), Some("PLUELY-1234"));
+    reopened.delete(LICENSE, LICENSE_KEY).unwrap();
+    reopened.delete(LICENSE, LICENSE_KEY).unwrap();
+    assert_eq!(reopened.get(LICENSE, LICENSE_KEY).unwrap(), None);
+    assert_eq!(reopened.get(PROVIDER_KEYS, "openai").unwrap().as_deref(), Some("sk-test"));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(dir.join("secure_storage.key")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn enforces_namespace_schemas() {
+    let store = SecretStore::in_memory();
+    assert!(matches!(store.set("passwords", "x", "y"), Err(AppError::Config(_))));
+    assert!(matches!(store.set(LICENSE, "api_key", "y"), Err(AppError::Config(_))));
+    assert!(matches!(store.set(PROVIDER_KEYS, "../openai", "y"), Err(AppError::Config(_))));
+    assert!(matches!(store.set(ENDPOINT_KEYS, "company", ""), Err(AppError::Config(_))));
+    assert!(matches!(store.set(ENDPOINT_KEYS, "company", &"k".repeat(5_000)), Err(AppError::Config(_))));
+    // Speech-to-text keys aren't kept here, the webview still sends those requests itself
+    assert!(matches!(store.set("stt_keys", "deepgram", "dg-key"), Err(AppError::Config(_))));
+
+    store.set(ENDPOINT_KEYS, "company", "co-key").unwrap();
+    store.set(ENDPOINT_KEYS, "custom:staging-1", "st-key").unwrap();
+    store.set(PROVIDER_KEYS, "openai", "sk-test").unwrap();
+    assert_eq!(store.list(ENDPOINT_KEYS).unwrap(), ["company", "custom:staging-1"]);
+
+    store.delete(ENDPOINT_KEYS, "company").unwrap();
+    assert_eq!(store.list(ENDPOINT_KEYS).unwrap(), ["custom:staging-1"]);
+    assert_eq!(store.list(LICENSE).unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn only_the_license_can_be_read_back_by_the_frontend() {
+    assert!(secret_store::namespace(LICENSE).unwrap().check_readable().is_ok());
+    for name in [PROVIDER_KEYS, ENDPOINT_KEYS] {
+        let error = secret_store::namespace(name).unwrap().check_readable().unwrap_err();
+        assert_eq!(error.code(), "config_error");
+    }
+}
+
+#[test]
+fn migrates_plaintext_credentials_and_deletes_the_file() {
+    let dir = temp_dir("migrate");
+    write_plaintext(&dir);
+
+    let store = SecretStore::with_backend(Box::new(MemoryBackend::default()), &dir);
+    assert_eq!(store.get(LICENSE, LICENSE_KEY).unwrap().as_deref(), Some("PLUELY-1234"));
+    assert_eq!(store.get(LICENSE, INSTANCE_ID).unwrap().as_deref(), Some("instance-1"));
+    assert_eq!(store.get(LICENSE, SELECTED_MODEL).unwrap(), None);
+    assert_eq!(store.list(LICENSE).unwrap(), [INSTANCE_ID, LICENSE_KEY]);
+    assert!(!dir.join("secure_storage.json").exists());
+    assert_eq!(store.schema_version().unwrap(), SCHEMA_VERSION);
+
+    // Migrations run once; a file showing up again is left alone
+    write_plaintext(&dir);
+    assert_eq!(migrate(&store, &dir).unwrap(), 0);
+    assert!(dir.join("secure_storage.json").exists());
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn migrates_flat_keys_without_overwriting_newer_values() {
+    let dir = temp_dir("flat");
+    let backend = MemoryBackend::default();
+    backend.set("pluely_license_key", "PLUELY-OLD").unwrap();
+    backend.set("pluely_instance_id", "instance-1").unwrap();
+    backend.set("license/license_key", "PLUELY-NEW").unwrap();
+
+    let store = SecretStore::with_backend(Box::new(backend), &dir);
+    assert_eq!(store.get(LICENSE, LICENSE_KEY).unwrap().as_deref(), Some("PLUELY-NEW"));
+    assert_eq!(store.get(LICENSE, INSTANCE_ID).unwrap().as_deref(), Some("instance-1"));
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn retries_a_failed_migration_on_the_next_start() {
+    let dir = temp_dir("unreadable");
+    fs::write(dir.join("secure_storage.json"), "{ not json").unwrap();
+
+    let store = SecretStore::with_backend(Box::new(EncryptedFile::new(&dir)), &dir);
+    assert_eq!(store.get(LICENSE, LICENSE_KEY).unwrap(), None);
+    assert!(dir.join("secure_storage.json").exists());
+    assert!(store.schema_version().unwrap() < SCHEMA_VERSION);
+
+    write_plaintext(&dir);
+    let reopened = SecretStore::with_backend(Box::new(EncryptedFile::new(&dir)), &dir);
+    assert_eq!(reopened.get(LICENSE, LICENSE_KEY).unwrap().as_deref(), Some("PLUELY-1234"));
+    assert_eq!(reopened.schema_version().unwrap(), SCHEMA_VERSION);
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn reports_a_lost_key_instead_of_returning_nothing() {
+    let dir = temp_dir("lost-key");
+    let store = SecretStore::with_backend(Box::new(EncryptedFile::new(&dir)), &dir);
+    store.set(LICENSE, LICENSE_KEY, "PLUELY-1234").unwrap();
+
+    fs::write(dir.join("secure_storage.key"), "bm90IGEga2V5").unwrap();
+    assert!(matches!(store.get(LICENSE, LICENSE_KEY), Err(AppError::Storage(_))));
+    fs::remove_file(dir.join("secure_storage.key")).unwrap();
+    assert!(matches!(store.get(LICENSE, LICENSE_KEY), Err(AppError::Storage(_))));
+    let _ = fs::remove_dir_all(dir);
+}
+
+// Needs a running Secret Service; CI starts gnome-keyring in a private D-Bus session
+#[test]
+#[ignore]
+fn keychain_round_trips() {
+    assert!(Keychain::is_available());
+    let key = format!("test-{}", std::process::id());
+    Keychain.set(&key, "PLUELY-1234").unwrap();
+    assert_eq!(Keychain.get(&key).unwrap().as_deref(), Some("PLUELY-1234"));
+    Keychain.delete(&key).unwrap();
+    assert_eq!(Keychain.get(&key).unwrap(), None);
+}
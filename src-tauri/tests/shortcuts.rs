@@ -0,0 +1,82 @@
+use pluely_lib::push_to_talk::PushToTalkSource;
+use pluely_lib::shortcuts::{self, RegistrationState, ShortcutAction, ShortcutSettings};
+use std::fs;
+use std::path::PathBuf;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pluely-shortcuts-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn validates_accelerators() {
+    assert!(shortcuts::parse_accelerator("ctrl+shift+k").is_ok());
+    assert!(shortcuts::parse_accelerator(" CmdOrCtrl+F5 ").is_ok());
+    assert!(shortcuts::parse_accelerator("f9").is_ok());
+
+    for invalid in ["", "k", "shift+k", "ctrl+", "ctrl+shift", "ctrl+nope", "ctrl+k+j"] {
+        let error = shortcuts::parse_accelerator(invalid).unwrap_err();
+        assert_eq!(error.code(), "config_error", "{}", invalid);
+    }
+}
+
+#[test]
+fn refuses_keys_bound_to_another_action() {
+    let settings = ShortcutSettings::default();
+    let taken = ShortcutAction::Audio.default_accelerator().to_uppercase();
+    let error = shortcuts::check_binding(&settings, ShortcutAction::Screenshot, &taken).unwrap_err();
+    assert!(error.message().contains("already used by the audio shortcut"), "{}", error.message());
+
+    // Rebinding an action to its own keys is fine
+    assert!(shortcuts::check_binding(&settings, ShortcutAction::Audio, &taken).is_ok());
+}
+
+#[test]
+fn saves_only_bindings_that_differ_from_the_default() {
+    let dir = temp_dir("save");
+    shortcuts::save_binding(&dir, ShortcutAction::Screenshot, " alt+shift+p ").unwrap();
+
+    let bindings = shortcuts::load_settings(&dir).unwrap().bindings();
+    assert_eq!(bindings[&ShortcutAction::Screenshot], "alt+shift+p");
+    assert_eq!(bindings[&ShortcutAction::Toggle], ShortcutAction::Toggle.default_accelerator());
+    let saved = fs::read_to_string(dir.join("shortcuts.json")).unwrap();
+    assert!(saved.contains("\"screenshot\"") && !saved.contains("\"toggle\""), "{}", saved);
+
+    let default = ShortcutAction::Screenshot.default_accelerator().to_uppercase();
+    shortcuts::save_binding(&dir, ShortcutAction::Screenshot, &default).unwrap();
+    assert!(shortcuts::load_settings(&dir).unwrap().bindings.is_empty());
+}
+
+#[test]
+fn keeps_the_push_to_talk_source_next_to_the_bindings() {
+    let dir = temp_dir("source");
+    assert_eq!(shortcuts::load_settings(&dir).unwrap().push_to_talk_source, PushToTalkSource::Microphone);
+
+    shortcuts::save_binding(&dir, ShortcutAction::PushToTalk, "alt+f9").unwrap();
+    shortcuts::set_push_to_talk_source(&dir, PushToTalkSource::System).unwrap();
+
+    let settings = shortcuts::load_settings(&dir).unwrap();
+    assert_eq!(settings.push_to_talk_source, PushToTalkSource::System);
+    assert_eq!(settings.accelerator(ShortcutAction::PushToTalk), "alt+f9");
+}
+
+#[test]
+fn reports_each_binding_that_cannot_be_registered() {
+    let mut settings = ShortcutSettings::default();
+    settings.bindings.insert(ShortcutAction::Audio, "ctrl+nope".to_string());
+    let toggle = ShortcutAction::Toggle.default_accelerator().to_string();
+    settings.bindings.insert(ShortcutAction::SystemAudio, toggle.clone());
+
+    let resolved = shortcuts::resolve_bindings(&settings);
+    assert!(resolved[&ShortcutAction::Toggle].is_ok());
+    assert!(resolved[&ShortcutAction::Screenshot].is_ok());
+
+    let invalid = resolved[&ShortcutAction::Audio].as_ref().unwrap_err();
+    assert_eq!((invalid.accelerator.as_str(), invalid.state), ("ctrl+nope", RegistrationState::Invalid));
+
+    // The first action keeps the keys, the later one is flagged
+    let conflict = resolved[&ShortcutAction::SystemAudio].as_ref().unwrap_err();
+    assert_eq!((conflict.accelerator.as_str(), conflict.state), (toggle.as_str(), RegistrationState::Conflict));
+    assert!(conflict.error.as_deref().unwrap().contains("toggle shortcut"));
+}
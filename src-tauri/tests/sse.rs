@@ -0,0 +1,162 @@
+use pluely_lib::sse::{
+    AnthropicExtractor, DeltaExtractor, GeminiExtractor, OpenAiExtractor, SseDecoder, SseEvent, StreamSignal,
+    TokenUsage,
+};
+
+fn decode_all(chunks: &[&[u8]]) -> Vec<SseEvent> {
+    let mut decoder = SseDecoder::new();
+    let mut events: Vec<SseEvent> = chunks.iter().flat_map(|c| decoder.push(c)).collect();
+    events.extend(decoder.finish());
+    events
+}
+
+fn message(data: &str) -> SseEvent {
+    SseEvent { event: "message".to_string(), data: data.to_string(), id: None, retry: None }
+}
+
+#[test]
+fn joins_multi_line_data() {
+    let events = decode_all(&[b"data: first\ndata: second\n\n"]);
+    assert_eq!(events, vec![message("first\nsecond")]);
+}
+
+#[test]
+fn reads_event_names_ids_and_retry() {
+    let events = decode_all(&[b"event: content_block_delta\nid: 7\nretry: 1500\ndata: {}\n\n"]);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event, "content_block_delta");
+    assert_eq!(events[0].id.as_deref(), Some("7"));
+    assert_eq!(events[0].retry, Some(1500));
+}
+
+#[test]
+fn event_name_resets_after_dispatch() {
+    let events = decode_all(&[b"event: ping\ndata: a\n\ndata: b\n\n"]);
+    assert_eq!(events[0].event, "ping");
+    assert_eq!(events[1].event, "message");
+}
+
+#[test]
+fn skips_comments_and_empty_events() {
+    let events = decode_all(&[b": keep-alive\n\nevent: noop\n\n:another\ndata: x\n\n"]);
+    assert_eq!(events, vec![message("x")]);
+}
+
+#[test]
+fn handles_crlf_and_cr_line_endings() {
+    let events = decode_all(&[b"data: a\r\n\r\ndata: b\r\rdata: c\n\n"]);
+    assert_eq!(events, vec![message("a"), message("b"), message("c")]);
+}
+
+#[test]
+fn crlf_split_across_chunks_is_one_line_break() {
+    let events = decode_all(&[b"data: a\r", b"\n\r", b"\n"]);
+    assert_eq!(events, vec![message("a")]);
+}
+
+#[test]
+fn keeps_multibyte_characters_split_across_chunks() {
+    let text = "data: héllo 👋\n\n".as_bytes();
+    // Split in the middle of the 4-byte emoji
+    let split = text.len() - 4;
+    let events = decode_all(&[&text[..split], &text[split..]]);
+    assert_eq!(events, vec![message("héllo 👋")]);
+}
+
+#[test]
+fn only_strips_a_single_leading_space() {
+    let events = decode_all(&[b"data:no-space\n\ndata:  two-spaces\n\n"]);
+    assert_eq!(events, vec![message("no-space"), message(" two-spaces")]);
+}
+
+#[test]
+fn strips_leading_bom() {
+    let events = decode_all(&["\u{feff}data: x\n\n".as_bytes()]);
+    assert_eq!(events, vec![message("x")]);
+}
+
+#[test]
+fn flushes_unterminated_event_at_end_of_stream() {
+    let events = decode_all(&[b"data: tail"]);
+    assert_eq!(events, vec![message("tail")]);
+}
+
+#[test]
+fn openai_extracts_deltas_and_done() {
+    let chunk = message(r#"{"choices":[{"delta":{"content":"Hi"}}]}"#);
+    assert_eq!(OpenAiExtractor.extract(&chunk), Ok(StreamSignal::Delta("Hi".to_string())));
+    assert_eq!(OpenAiExtractor.extract(&message("[DONE]")), Ok(StreamSignal::Done));
+    let role_only = message(r#"{"choices":[{"delta":{"role":"assistant"}}]}"#);
+    assert_eq!(OpenAiExtractor.extract(&role_only), Ok(StreamSignal::Ignore));
+}
+
+#[test]
+fn openai_surfaces_mid_stream_errors() {
+    let error = message(r#"{"error":{"message":"Rate limit reached","type":"rate_limit"}}"#);
+    assert_eq!(OpenAiExtractor.extract(&error), Err("Rate limit reached".to_string()));
+}
+
+#[test]
+fn anthropic_extracts_text_deltas() {
+    let event = SseEvent {
+        event: "content_block_delta".to_string(),
+        data: r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#.to_string(),
+        id: None,
+        retry: None,
+    };
+    assert_eq!(AnthropicExtractor.extract(&event), Ok(StreamSignal::Delta("Hello".to_string())));
+
+    let stop = message(r#"{"type":"message_stop"}"#);
+    assert_eq!(AnthropicExtractor.extract(&stop), Ok(StreamSignal::Done));
+
+    let ping = message(r#"{"type":"ping"}"#);
+    assert_eq!(AnthropicExtractor.extract(&ping), Ok(StreamSignal::Ignore));
+
+    // Structured answers arrive as the input of a forced tool call
+    let json = message(r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{\"a\":"}}"#);
+    assert_eq!(AnthropicExtractor.extract(&json), Ok(StreamSignal::Delta(r#"{"a":"#.to_string())));
+}
+
+#[test]
+fn anthropic_surfaces_error_events() {
+    let event = SseEvent {
+        event: "error".to_string(),
+        data: r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#.to_string(),
+        id: None,
+        retry: None,
+    };
+    assert_eq!(AnthropicExtractor.extract(&event), Err("Overloaded".to_string()));
+}
+
+#[test]
+fn gemini_joins_all_parts() {
+    let chunk = message(r#"{"candidates":[{"content":{"parts":[{"text":"Hel"},{"text":"lo"}],"role":"model"}}]}"#);
+    assert_eq!(GeminiExtractor.extract(&chunk), Ok(StreamSignal::Delta("Hello".to_string())));
+
+    let error = message(r#"{"error":{"code":400,"message":"API key not valid","status":"INVALID_ARGUMENT"}}"#);
+    assert_eq!(GeminiExtractor.extract(&error), Err("API key not valid".to_string()));
+}
+
+#[test]
+fn extracts_reported_token_usage() {
+    let openai = message(r#"{"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":34}}"#);
+    assert_eq!(
+        OpenAiExtractor.usage(&openai),
+        Some(TokenUsage { prompt_tokens: Some(12), completion_tokens: Some(34) })
+    );
+    assert_eq!(OpenAiExtractor.usage(&message(r#"{"choices":[],"usage":null}"#)), None);
+
+    let start = message(r#"{"type":"message_start","message":{"usage":{"input_tokens":25,"output_tokens":1}}}"#);
+    let delta = message(r#"{"type":"message_delta","usage":{"output_tokens":15}}"#);
+    assert_eq!(AnthropicExtractor.usage(&start).and_then(|u| u.prompt_tokens), Some(25));
+    assert_eq!(
+        AnthropicExtractor.usage(&delta),
+        Some(TokenUsage { prompt_tokens: None, completion_tokens: Some(15) })
+    );
+
+    let gemini = message(r#"{"usageMetadata":{"promptTokenCount":7,"candidatesTokenCount":3}}"#);
+    assert_eq!(
+        GeminiExtractor.usage(&gemini),
+        Some(TokenUsage { prompt_tokens: Some(7), completion_tokens: Some(3) })
+    );
+}
@@ -0,0 +1,62 @@
+use pluely_lib::structured::{extract_json, OutputSchema};
+use serde_json::json;
+
+fn action_items() -> OutputSchema {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "items": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": { "owner": { "type": "string" }, "task": { "type": "string" } },
+                    "required": ["owner", "task"]
+                }
+            }
+        },
+        "required": ["items"]
+    });
+    OutputSchema::new(Some("action items!".to_string()), schema).unwrap()
+}
+
+#[test]
+fn accepts_matching_answers() {
+    let value = action_items().validate(r#"{"items":[{"owner":"Ana","task":"Send notes"}]}"#).unwrap();
+    assert_eq!(value["items"][0]["owner"], "Ana");
+}
+
+#[test]
+fn reports_schema_violations_with_their_path() {
+    let errors = action_items().validate(r#"{"items":[{"owner":"Ana"}]}"#).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("/items/0:"), "{}", errors[0]);
+    assert!(errors[0].contains("task"));
+
+    let errors = action_items().validate("Sure! Here are the items.").unwrap_err();
+    assert!(errors[0].starts_with("The answer is not valid JSON"));
+}
+
+#[test]
+fn strips_fences_and_surrounding_prose() {
+    assert_eq!(extract_json("```json\n{\"a\": 1}\n```"), "{\"a\": 1}");
+    assert_eq!(extract_json("Here you go: [1, 2] Hope that helps."), "[1, 2]");
+    assert_eq!(extract_json("  {\"a\": [1]}  "), "{\"a\": [1]}");
+    assert!(action_items().validate("```\n{\"items\": []}\n```").is_ok());
+}
+
+#[test]
+fn builds_provider_request_fields() {
+    let schema = action_items();
+    let format = schema.response_format();
+    assert_eq!(format["type"], "json_schema");
+    assert_eq!(format["json_schema"]["name"], "action_items_");
+    assert!(schema.instruction().contains("\"required\":[\"items\"]"));
+
+    let repair = schema.repair_prompt(&["/items: expected array".to_string()]);
+    assert!(repair.contains("- /items: expected array"));
+}
+
+#[test]
+fn rejects_invalid_schemas() {
+    assert!(OutputSchema::new(None, json!({ "type": "nonsense" })).is_err());
+}
@@ -0,0 +1,238 @@
+// In-process stand-in for the Pluely backend and payment service. It answers the same routes
+// with the same shapes as production, so the tests exercise the real request building and
+// response handling without network access. Each test starts its own server.
+// Shared by several test crates, each using only part of it.
+#![allow(dead_code)]
+
+use pluely_lib::backend::{AuthScheme, Backend, BackendConfig, Credentials};
+use pluely_lib::retry::RetryPolicy;
+use serde_json::{json, Value};
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+pub const API_ACCESS_KEY: &str = "test-access-key";
+pub const LICENSE_KEY: &str = "PLUELY-TEST-LICENSE-0001";
+pub const INSTANCE_ID: &str = "instance-0001";
+pub const MODELS_ETAG: &str = "\"models-v1\"";
+pub const TRANSCRIPTION: &str = "What is the capital of France?";
+pub const CHECKOUT_URL: &str = "https://checkout.example.com/buy/pluely";
+
+pub struct MockBackend {
+    pub server: MockServer,
+}
+
+impl MockBackend {
+    /// Server answering every route successfully for the test credentials, and with 401 for
+    /// requests without the access key
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(query_param("stream", "true"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .and(header("license_key", LICENSE_KEY))
+            .and(header("instance", INSTANCE_ID))
+            .respond_with(sse(&["The capital", " of France", " is Paris."]))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/audio"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .and(header("license_key", LICENSE_KEY))
+            .and(header("instance", INSTANCE_ID))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "transcription": TRANSCRIPTION,
+                "error": null,
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/models"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .and(header("if-none-match", MODELS_ETAG))
+            .respond_with(ResponseTemplate::new(304))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/models"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", MODELS_ETAG)
+                    .set_body_json(json!({ "models": [model_json("gpt-4o", "multimodal"), model_json("o3-mini", "text")] })),
+            )
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/activate"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .and(body_partial_json(json!({ "license_key": LICENSE_KEY })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "activated": true,
+                "error": null,
+                "license_key": LICENSE_KEY,
+                "instance": instance_json(INSTANCE_ID, "test-instance"),
+            })))
+            .mount(&server)
+            .await;
+        // Unknown keys, as the payment service reports them
+        Mock::given(method("POST"))
+            .and(path("/activate"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "activated": false,
+                "error": "license_key not found.",
+                "license_key": null,
+                "instance": null,
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/deactivate"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .and(body_partial_json(json!({ "license_key": LICENSE_KEY, "instance_id": INSTANCE_ID })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "deactivated": true, "error": null })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/deactivate"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "deactivated": false,
+                "error": "license_key or instance not found.",
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/validate"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .and(body_partial_json(json!({ "license_key": LICENSE_KEY, "instance_id": INSTANCE_ID })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "valid": true,
+                "error": null,
+                "license": license_json(),
+                "instance": instance_json(INSTANCE_ID, "test-instance"),
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/instances"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .and(body_partial_json(json!({ "license_key": LICENSE_KEY })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "instances": [instance_json(INSTANCE_ID, "test-instance"), instance_json("instance-0002", "old-laptop")],
+                "license": license_json(),
+                "error": null,
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/checkout"))
+            .and(header("authorization", format!("Bearer {}", API_ACCESS_KEY).as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "success": true,
+                "checkout_url": CHECKOUT_URL,
+                "error": null,
+            })))
+            .mount(&server)
+            .await;
+
+        // Anything that didn't match the access key above
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({ "error": "Invalid API access key" })))
+            .with_priority(10)
+            .mount(&server)
+            .await;
+
+        Self { server }
+    }
+
+    /// Answers `route` with `response` instead, ahead of the default routes
+    pub async fn fail(&self, route: &str, response: ResponseTemplate) {
+        Mock::given(method("POST"))
+            .and(path(route))
+            .respond_with(response)
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    pub fn config(&self) -> BackendConfig {
+        BackendConfig {
+            app_endpoint: Some(self.server.uri()),
+            payment_endpoint: Some(self.server.uri()),
+            api_access_key: Some(API_ACCESS_KEY.to_string()),
+            auth: AuthScheme::Bearer,
+            sends_license: true,
+        }
+    }
+
+    /// Client for this server, with retries fast enough for tests
+    pub fn backend(&self) -> Backend {
+        Backend::new(reqwest::Client::new(), self.config()).with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            max_retry_after: Duration::from_secs(1),
+        })
+    }
+
+    pub async fn requests_to(&self, route: &str) -> Vec<Request> {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|r| r.url.path() == route)
+            .collect()
+    }
+}
+
+pub fn credentials() -> Credentials {
+    Credentials { license_key: LICENSE_KEY.to_string(), instance_id: INSTANCE_ID.to_string(), selected_model: None }
+}
+
+pub fn instance_json(id: &str, name: &str) -> Value {
+    json!({ "id": id, "name": name, "created_at": "2024-03-10T12:00:00Z" })
+}
+
+pub fn license_json() -> Value {
+    json!({ "status": "active", "activation_limit": 3, "activation_usage": 2, "expires_at": null })
+}
+
+pub fn model_json(id: &str, modality: &str) -> Value {
+    json!({
+        "provider": "openai",
+        "name": id,
+        "id": id,
+        "model": id,
+        "description": "",
+        "modality": modality,
+        "isAvailable": true,
+        "contextWindow": 128_000,
+    })
+}
+
+/// OpenAI-style stream with the given text deltas, a usage chunk and the terminator
+pub fn sse(deltas: &[&str]) -> ResponseTemplate {
+    let mut body = String::new();
+    for delta in deltas {
+        body.push_str(&format!("data: {}\n\n", json!({ "choices": [{ "delta": { "content": delta } }] })));
+    }
+    body.push_str(&format!(
+        "data: {}\n\n",
+        json!({ "choices": [], "usage": { "prompt_tokens": 42, "completion_tokens": 7 } })
+    ));
+    body.push_str("data: [DONE]\n\n");
+    ResponseTemplate::new(200).set_body_raw(body, "text/event-stream")
+}
@@ -0,0 +1,112 @@
+use pluely_lib::chat::{FunctionCall, ToolCall};
+use pluely_lib::sse::{read_stream_with_usage, OpenAiExtractor};
+use pluely_lib::tools::{
+    run_recent_transcript, search_conversations, BuiltinTool, IndexedConversation, IndexedMessage, ToolSettings,
+    TranscriptLog,
+};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn call(name: &str, arguments: &str) -> ToolCall {
+    ToolCall {
+        id: "call_1".to_string(),
+        kind: "function".to_string(),
+        function: FunctionCall { name: name.to_string(), arguments: arguments.to_string() },
+    }
+}
+
+fn conversation(id: &str, updated_at: u64, content: &str) -> IndexedConversation {
+    IndexedConversation {
+        id: id.to_string(),
+        title: format!("Conversation {}", id),
+        updated_at,
+        messages: vec![IndexedMessage { role: "user".to_string(), content: content.to_string() }],
+    }
+}
+
+#[tokio::test]
+async fn assembles_tool_calls_from_stream_deltas() {
+    let body = [
+        r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_a","type":"function","function":{"name":"search_conversations","arguments":""}}]}}]}"#,
+        r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"query\":"}}]}}]}"#,
+        r#"data: {"choices":[{"delta":{"tool_calls":[{"index":1,"id":"call_b","function":{"name":"read_clipboard","arguments":"{}"}}]}}]}"#,
+        r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"budget\"}"}}]}}]}"#,
+        "data: [DONE]",
+    ]
+    .join("\n\n");
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body + "\n\n", "text/event-stream"))
+        .mount(&server)
+        .await;
+
+    let response = reqwest::Client::new().post(server.uri()).send().await.unwrap();
+    let output = read_stream_with_usage(response, &OpenAiExtractor, &mut |_| {}).await.unwrap();
+
+    assert_eq!(output.text, "");
+    assert_eq!(output.tool_calls.len(), 2);
+    assert_eq!(output.tool_calls[0].id, "call_a");
+    assert_eq!(output.tool_calls[0].function.name, "search_conversations");
+    assert_eq!(output.tool_calls[0].function.arguments, r#"{"query":"budget"}"#);
+    assert_eq!(output.tool_calls[1].function.name, "read_clipboard");
+}
+
+#[test]
+fn sensitive_tools_are_opt_in() {
+    let mut settings = ToolSettings::default();
+    let offered: Vec<String> = settings
+        .definitions(true)
+        .iter()
+        .map(|d| d["function"]["name"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(offered, vec!["get_recent_transcript", "search_conversations"]);
+
+    settings.tools.insert("take_screenshot".to_string(), true);
+    settings.tools.insert("search_conversations".to_string(), false);
+    assert!(settings.is_allowed(BuiltinTool::Screenshot));
+    assert!(!settings.is_allowed(BuiltinTool::SearchConversations));
+    assert_eq!(settings.definitions(true).len(), 2);
+    // A text-only model couldn't see the screenshot
+    assert_eq!(settings.definitions(false).len(), 1);
+}
+
+#[test]
+fn transcript_tool_returns_requested_window() {
+    let log = TranscriptLog::default();
+    log.push("first", 1_000);
+    log.push("  ", 1_050);
+    log.push("second", 1_100);
+    log.push("third", 1_150);
+
+    let output = run_recent_transcript(&log, &call("get_recent_transcript", r#"{"seconds":60}"#), 1_160).unwrap();
+    assert_eq!(output.content, "second\nthird");
+
+    // Models send empty arguments for the default two minutes
+    let output = run_recent_transcript(&log, &call("get_recent_transcript", ""), 1_200).unwrap();
+    assert_eq!(output.content, "second\nthird");
+
+    // Entries older than the retention window are dropped
+    log.push("later", 1_200 + 31 * 60);
+    assert_eq!(log.recent(u64::MAX, 1_200 + 31 * 60), vec!["later"]);
+
+    assert!(run_recent_transcript(&log, &call("get_recent_transcript", "{oops"), 0).is_err());
+}
+
+#[test]
+fn search_ranks_by_matched_terms_then_recency() {
+    let conversations = vec![
+        conversation("old", 1, "We discussed the quarterly budget and hiring plans."),
+        conversation("new", 2, "The budget looks fine."),
+        conversation("other", 3, "Nothing relevant here."),
+    ];
+
+    let hits = search_conversations(&conversations, "Budget hiring", 5);
+    let ids: Vec<&str> = hits.iter().map(|h| h.conversation_id.as_str()).collect();
+    assert_eq!(ids, vec!["old", "new"]);
+    assert!(hits[0].snippet.contains("quarterly budget"));
+
+    let hits = search_conversations(&conversations, "budget", 5);
+    assert_eq!(hits[0].conversation_id, "new");
+    assert_eq!(search_conversations(&conversations, "budget", 1).len(), 1);
+    assert!(search_conversations(&conversations, "   ", 5).is_empty());
+}
@@ -0,0 +1,120 @@
+use pluely_lib::trace::{redact_body, send_traced, TraceEntry, Tracer, MAX_BODY_CHARS, MAX_LOG_BYTES};
+use serde_json::json;
+use std::path::PathBuf;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pluely-trace-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn entries(tracer: &Tracer) -> Vec<TraceEntry> {
+    tracer.read().unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+#[tokio::test]
+async fn records_redacted_requests_and_keeps_the_response_readable() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .respond_with(ResponseTemplate::new(500).set_body_json(json!({ "error": "upstream failed" })))
+        .mount(&server)
+        .await;
+
+    let dir = temp_dir("redact");
+    let tracer = Tracer::new(dir.clone(), true);
+    let image = "A".repeat(5_000);
+    let request = reqwest::Client::new()
+        .post(format!("{}/api/chat?stream=true", server.uri()))
+        .header("Authorization", "Bearer secret-access-key")
+        .header("license_key", "LICENSE-1234")
+        .header("instance", "instance-5678")
+        .header("model", "gpt-4o")
+        .json(&json!({
+            "user_message": "What is on my screen?",
+            "license_key": "LICENSE-1234",
+            "attachments": [{ "mime_type": "image/png", "data": image }],
+        }));
+
+    let response = send_traced(Some(&tracer), "chat", request).await.unwrap();
+    assert_eq!(response.status(), 500);
+    assert_eq!(response.text().await.unwrap(), r#"{"error":"upstream failed"}"#);
+
+    let log = tracer.read().unwrap();
+    for secret in ["secret-access-key", "LICENSE-1234", "instance-5678", &image[..300]] {
+        assert!(!log.contains(secret), "{} leaked into {}", secret, log);
+    }
+
+    let entry = &entries(&tracer)[0];
+    assert_eq!((entry.operation.as_str(), entry.method.as_str()), ("chat", "POST"));
+    assert_eq!(entry.path, "/api/chat");
+    assert_eq!(entry.status, Some(500));
+    assert!(entry.request_headers.contains(&("model".to_string(), "gpt-4o".to_string())));
+    assert!(entry.request_headers.contains(&("authorization".to_string(), "[redacted]".to_string())));
+    let request_body = entry.request_body.as_deref().unwrap();
+    assert!(request_body.contains("What is on my screen?"));
+    assert!(request_body.contains("[base64, 5000 chars]"));
+    assert_eq!(entry.response_body.as_deref(), Some(r#"{"error":"upstream failed"}"#));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn leaves_streams_to_the_caller() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("data: hello\n\n", "text/event-stream"))
+        .mount(&server)
+        .await;
+
+    let dir = temp_dir("stream");
+    let tracer = Tracer::new(dir.clone(), true);
+    let response = send_traced(Some(&tracer), "chat", reqwest::Client::new().get(server.uri())).await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "data: hello\n\n");
+    assert_eq!(entries(&tracer)[0].response_body, None);
+
+    tracer.set_enabled(false);
+    send_traced(Some(&tracer), "chat", reqwest::Client::new().get(server.uri())).await.unwrap();
+    assert_eq!(entries(&tracer).len(), 1);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn rolls_over_at_the_size_limit() {
+    let dir = temp_dir("rotate");
+    let tracer = Tracer::new(dir.clone(), true);
+    let entry = TraceEntry {
+        timestamp: 0,
+        operation: "models".to_string(),
+        method: "POST".to_string(),
+        path: "/api/models".to_string(),
+        status: Some(200),
+        latency_ms: 12,
+        request_headers: Vec::new(),
+        request_body: Some("x".repeat(MAX_BODY_CHARS)),
+        response_body: None,
+        error: None,
+    };
+
+    let per_entry = serde_json::to_string(&entry).unwrap().len() as u64 + 1;
+    let count = (MAX_LOG_BYTES / per_entry) * 3;
+    for _ in 0..count {
+        tracer.record(&entry).unwrap();
+    }
+
+    let total: u64 = std::fs::read_dir(&dir).unwrap().map(|f| f.unwrap().metadata().unwrap().len()).sum();
+    assert!(total <= 2 * MAX_LOG_BYTES);
+    assert!(entries(&tracer).len() as u64 > MAX_LOG_BYTES / per_entry);
+
+    tracer.clear().unwrap();
+    assert_eq!(tracer.read().unwrap(), "");
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn truncates_long_bodies() {
+    let body = redact_body("word ".repeat(2_000).as_bytes());
+    assert!(body.ends_with("[truncated, 10000 chars total]"));
+    assert_eq!(redact_body(b"not json, short"), "not json, short");
+}
@@ -0,0 +1,115 @@
+use pluely_lib::fallback::ResponseSource;
+use pluely_lib::sse::TokenUsage;
+use pluely_lib::usage::{append_record, load_records, summarize, Metering, UsagePeriod, UsageRecord, MAX_LOG_BYTES};
+use std::fs;
+use std::time::Duration;
+
+const DAY: u64 = 24 * 60 * 60;
+// 2024-03-10T12:00:00Z
+const NOW: u64 = 1_710_072_000;
+
+fn record(timestamp: u64, model: &str, prompt_tokens: u32, completion_tokens: u32, cost_usd: Option<f64>) -> UsageRecord {
+    UsageRecord {
+        timestamp,
+        request_id: format!("{}-{}", model, timestamp),
+        conversation_id: None,
+        source: ResponseSource::Backend,
+        model: model.to_string(),
+        prompt_tokens,
+        completion_tokens,
+        estimated: false,
+        latency_ms: 1_000,
+        cost_usd,
+    }
+}
+
+#[test]
+fn prefers_reported_usage_and_prices_it() {
+    let metering = Metering {
+        model: "gpt-4o".to_string(),
+        input_price_per_token: Some(0.000_002),
+        output_price_per_token: Some(0.000_008),
+        prompt_tokens_estimate: 999,
+    };
+    let reported = TokenUsage { prompt_tokens: Some(100), completion_tokens: Some(50) };
+    let record = metering.finish("r1", None, ResponseSource::Backend, "ignored", reported, Duration::from_millis(1_500));
+
+    assert_eq!((record.prompt_tokens, record.completion_tokens), (100, 50));
+    assert!(!record.estimated);
+    assert_eq!(record.latency_ms, 1_500);
+    assert!((record.cost_usd.unwrap() - 0.000_6).abs() < 1e-12);
+}
+
+#[test]
+fn estimates_missing_usage() {
+    let metering = Metering::for_model(None, 40);
+    let record = metering.finish("r1", None, ResponseSource::Backend, "twelve chars", TokenUsage::default(), Duration::ZERO);
+
+    assert_eq!((record.prompt_tokens, record.completion_tokens), (40, 3));
+    assert!(record.estimated);
+    assert_eq!(record.cost_usd, None);
+}
+
+#[test]
+fn tool_rounds_add_up_to_one_request() {
+    let mut total = record(NOW, "gpt-4o", 100, 10, Some(0.001));
+    let mut answer = record(NOW + 2, "gpt-4o", 150, 40, Some(0.002));
+    answer.estimated = true;
+    total.add_round(answer);
+
+    assert_eq!((total.prompt_tokens, total.completion_tokens), (250, 50));
+    assert_eq!((total.timestamp, total.latency_ms), (NOW + 2, 2_000));
+    assert!(total.estimated);
+    assert!((total.cost_usd.unwrap() - 0.003).abs() < 1e-12);
+    assert_eq!(summarize(&[total], UsagePeriod::All, NOW).totals.requests, 1);
+}
+
+#[test]
+fn usage_log_rolls_over() {
+    let dir = std::env::temp_dir().join(format!("pluely-usage-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let line_bytes = serde_json::to_string(&record(NOW, "gpt-4o", 1, 1, None)).unwrap().len() as u64 + 1;
+    let per_file = MAX_LOG_BYTES / line_bytes;
+
+    for i in 0..per_file + 10 {
+        append_record(&dir, &record(NOW + i, "gpt-4o", 1, 1, None)).unwrap();
+    }
+    assert!(fs::metadata(dir.join("usage.jsonl")).unwrap().len() <= MAX_LOG_BYTES);
+    assert_eq!(load_records(&dir).unwrap().len() as u64, per_file + 10);
+
+    // A second roll-over drops the oldest file
+    for i in 0..per_file {
+        append_record(&dir, &record(NOW + per_file + 10 + i, "gpt-4o", 1, 1, None)).unwrap();
+    }
+    let records = load_records(&dir).unwrap();
+    assert!((records.len() as u64) < 2 * per_file + 10);
+    assert!(records.windows(2).all(|pair| pair[0].timestamp < pair[1].timestamp));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn summarizes_by_model_and_day() {
+    let records = vec![
+        record(NOW - 40 * DAY, "gpt-4o", 1_000, 1_000, Some(1.0)),
+        record(NOW - DAY, "gpt-4o", 100, 10, Some(0.5)),
+        record(NOW - DAY + 60, "llama", 200, 20, None),
+        record(NOW, "gpt-4o", 300, 30, Some(0.25)),
+    ];
+
+    let week = summarize(&records, UsagePeriod::Week, NOW);
+    assert_eq!(week.totals.requests, 3);
+    assert_eq!(week.totals.prompt_tokens, 600);
+    assert_eq!(week.totals.completion_tokens, 60);
+    assert!((week.totals.cost_usd - 0.75).abs() < 1e-9);
+    assert_eq!(week.totals.unpriced_requests, 1);
+
+    assert_eq!(week.by_model[0].model, "gpt-4o");
+    assert_eq!(week.by_model[0].totals.requests, 2);
+    assert_eq!(week.by_model[1].model, "llama");
+
+    let days: Vec<(&str, u32)> = week.by_day.iter().map(|d| (d.date.as_str(), d.totals.requests)).collect();
+    assert_eq!(days, vec![("2024-03-09", 2), ("2024-03-10", 1)]);
+
+    assert_eq!(summarize(&records, UsagePeriod::Day, NOW).totals.requests, 1);
+    assert_eq!(summarize(&records, UsagePeriod::All, NOW).totals.requests, 4);
+}
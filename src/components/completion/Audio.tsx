@@ -10,6 +10,7 @@ export const Audio = ({
   setEnableVAD,
   submit,
   setState,
+  isPushToTalkRecording,
 }: UseCompletionReturn) => {
   return (
     <Popover open={micOpen} onOpenChange={setMicOpen}>
@@ -27,9 +28,19 @@ export const Audio = ({
               setEnableVAD(!enableVAD);
             }}
             className="cursor-pointer"
-            title="Toggle voice input (VAD)"
+            title={
+              isPushToTalkRecording
+                ? "Recording while the push-to-talk key is held"
+                : "Toggle voice input (VAD)"
+            }
           >
-            <MicIcon className="h-4 w-4" />
+            <MicIcon
+              className={
+                isPushToTalkRecording
+                  ? "h-4 w-4 animate-pulse text-red-500"
+                  : "h-4 w-4"
+              }
+            />
           </Button>
         )}
       </PopoverTrigger>
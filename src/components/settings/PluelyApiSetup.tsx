@@ -10,6 +10,9 @@ import {
 import { invoke } from "@tauri-apps/api/core";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import { useApp } from "@/contexts";
+import { getErrorMessage } from "@/lib/utils";
+import { getLicenseStatus, LicenseStatus } from "@/lib/functions/pluely.api";
+import { getSecrets, removeSecrets, setSecrets } from "@/lib/storage";
 import {
   Command,
   CommandEmpty,
@@ -31,6 +34,12 @@ interface ActivationResponse {
     name: string;
     created_at: string;
   };
+  token?: string;
+}
+
+interface DeactivationResponse {
+  deactivated: boolean;
+  error?: string;
 }
 
 interface CheckoutResponse {
@@ -39,12 +48,6 @@ interface CheckoutResponse {
   error?: string;
 }
 
-interface StorageResult {
-  license_key?: string;
-  instance_id?: string;
-  selected_pluely_model?: string;
-}
-
 interface Model {
   provider: string;
   name: string;
@@ -53,11 +56,34 @@ interface Model {
   description: string;
   modality: string;
   isAvailable: boolean;
+  contextWindow?: number;
+  supportsVision?: boolean;
+  inputPricePerToken?: number;
+  outputPricePerToken?: number;
 }
 
-const LICENSE_KEY_STORAGE_KEY = "pluely_license_key";
-const INSTANCE_ID_STORAGE_KEY = "pluely_instance_id";
-const SELECTED_PLUELY_MODEL_STORAGE_KEY = "selected_pluely_model";
+const LICENSE_KEY_STORAGE_KEY = "license_key";
+const INSTANCE_ID_STORAGE_KEY = "instance_id";
+const SELECTED_PLUELY_MODEL_STORAGE_KEY = "selected_model";
+const LICENSE_TOKEN_STORAGE_KEY = "license_token";
+
+// One line about expiry and the offline grace period, from the signed license token
+const describeLicenseStatus = (status: LicenseStatus): string | null => {
+  switch (status.state) {
+    case "active":
+      return status.days_remaining === null
+        ? "License active, no expiry."
+        : `License active, ${status.days_remaining} day(s) remaining.`;
+    case "expired":
+      return "License expired. Renew it to keep using Pluely API.";
+    case "needs_validation":
+      return "License couldn't be validated online recently. Connect to the internet to keep using Pluely API.";
+    case "invalid":
+      return `License couldn't be verified: ${status.error ?? "unknown error"}`;
+    default:
+      return null;
+  }
+};
 
 export const PluelyApiSetup = () => {
   const { pluelyApiEnabled, setPluelyApiEnabled } = useApp();
@@ -65,6 +91,9 @@ export const PluelyApiSetup = () => {
   const [licenseKey, setLicenseKey] = useState("");
   const [storedLicenseKey, setStoredLicenseKey] = useState<string | null>(null);
   const [maskedLicenseKey, setMaskedLicenseKey] = useState<string | null>(null);
+  const [licenseStatus, setLicenseStatus] = useState<LicenseStatus | null>(
+    null
+  );
   const [isLoading, setIsLoading] = useState(false);
   const [isCheckoutLoading, setIsCheckoutLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
@@ -108,24 +137,28 @@ export const PluelyApiSetup = () => {
   const loadLicenseStatus = async () => {
     try {
       // Get all stored data in one call
-      const storage = await invoke<StorageResult>("secure_storage_get");
+      const storage = await getSecrets("license");
+      const storedKey = storage[LICENSE_KEY_STORAGE_KEY];
 
-      if (storage.license_key) {
-        setStoredLicenseKey(storage.license_key);
+      if (storedKey) {
+        setStoredLicenseKey(storedKey);
 
         // Get masked version from Tauri command
         const masked = await invoke<string>("mask_license_key_cmd", {
-          licenseKey: storage.license_key,
+          licenseKey: storedKey,
         });
         setMaskedLicenseKey(masked);
+        setLicenseStatus(await getLicenseStatus());
       } else {
         setStoredLicenseKey(null);
         setMaskedLicenseKey(null);
+        setLicenseStatus(null);
       }
 
-      if (storage.selected_pluely_model) {
+      const storedModelJson = storage[SELECTED_PLUELY_MODEL_STORAGE_KEY];
+      if (storedModelJson) {
         try {
-          const storedModel = JSON.parse(storage.selected_pluely_model);
+          const storedModel = JSON.parse(storedModelJson);
           setSelectedModel(storedModel);
         } catch (e) {
           console.error("Failed to parse stored model:", e);
@@ -139,6 +172,7 @@ export const PluelyApiSetup = () => {
       // If we can't read from storage, assume no license is stored
       setStoredLicenseKey(null);
       setMaskedLicenseKey(null);
+      setLicenseStatus(null);
       setSelectedModel(null);
     }
   };
@@ -162,20 +196,7 @@ export const PluelyApiSetup = () => {
       );
 
       if (response.activated && response.instance) {
-        // Store the license data securely in one call
-        await invoke("secure_storage_save", {
-          items: [
-            {
-              key: LICENSE_KEY_STORAGE_KEY,
-              value: licenseKey.trim(),
-            },
-            {
-              key: INSTANCE_ID_STORAGE_KEY,
-              value: response.instance.id,
-            },
-          ],
-        });
-
+        // The backend has stored the license and its signed token
         setSuccess("License activated successfully!");
         setLicenseKey(""); // Clear the input
 
@@ -188,7 +209,7 @@ export const PluelyApiSetup = () => {
       }
     } catch (err) {
       console.error("License activation failed:", err);
-      setError(typeof err === "string" ? err : "Failed to activate license");
+      setError(getErrorMessage(err) || "Failed to activate license");
     } finally {
       setIsLoading(false);
     }
@@ -200,16 +221,34 @@ export const PluelyApiSetup = () => {
     setSuccess(null);
 
     try {
-      // Remove all license data from secure storage in one call
-      await invoke("secure_storage_remove", {
-        keys: [
-          LICENSE_KEY_STORAGE_KEY,
-          INSTANCE_ID_STORAGE_KEY,
-          SELECTED_PLUELY_MODEL_STORAGE_KEY,
-        ],
-      });
+      // Free this device's seat; the backend forgets the license here once it's released
+      let deactivationError: string | null = null;
+      try {
+        const response: DeactivationResponse = await invoke(
+          "deactivate_license"
+        );
+        if (!response.deactivated) {
+          deactivationError = response.error || "Unknown error";
+        }
+      } catch (err) {
+        deactivationError = getErrorMessage(err);
+      }
 
-      setSuccess("License removed successfully!");
+      // Remove all license data from secure storage in one call, even if the seat wasn't freed
+      await removeSecrets("license", [
+        LICENSE_KEY_STORAGE_KEY,
+        INSTANCE_ID_STORAGE_KEY,
+        SELECTED_PLUELY_MODEL_STORAGE_KEY,
+        LICENSE_TOKEN_STORAGE_KEY,
+      ]);
+
+      if (deactivationError) {
+        setError(
+          `License removed from this device, but its seat couldn't be released: ${deactivationError}`
+        );
+      } else {
+        setSuccess("License deactivated and removed successfully!");
+      }
 
       // Disable Pluely API when license is removed
       setPluelyApiEnabled(false);
@@ -228,13 +267,8 @@ export const PluelyApiSetup = () => {
     setIsPopoverOpen(false); // Close popover when model is selected
     setSearchValue(""); // Reset search when model is selected
     try {
-      await invoke("secure_storage_save", {
-        items: [
-          {
-            key: SELECTED_PLUELY_MODEL_STORAGE_KEY,
-            value: JSON.stringify(model),
-          },
-        ],
+      await setSecrets("license", {
+        [SELECTED_PLUELY_MODEL_STORAGE_KEY]: JSON.stringify(model),
       });
     } catch (error) {
       console.error("Failed to save model selection:", error);
@@ -270,7 +304,7 @@ export const PluelyApiSetup = () => {
       }
     } catch (err) {
       console.error("Failed to get checkout URL:", err);
-      setError(typeof err === "string" ? err : "Failed to get checkout URL");
+      setError(getErrorMessage(err) || "Failed to get checkout URL");
     } finally {
       setIsCheckoutLoading(false);
     }
@@ -513,6 +547,11 @@ export const PluelyApiSetup = () => {
                   )}
                 </Button>
               </div>
+              {licenseStatus && describeLicenseStatus(licenseStatus) ? (
+                <p className="text-sm text-muted-foreground -mt-1">
+                  {describeLicenseStatus(licenseStatus)}
+                </p>
+              ) : null}
               {storedLicenseKey ? (
                 <div className="-mt-1">
                   <p className="text-sm font-medium text-muted-foreground select-auto">
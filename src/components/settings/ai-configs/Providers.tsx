@@ -1,4 +1,10 @@
 import { Button, Header, Input, Selection, TextInput } from "@/components";
+import {
+  hasProviderApiKey,
+  removeProviderApiKey,
+  saveProviderApiKey,
+} from "@/lib/storage";
+import { getErrorMessage } from "@/lib/utils";
 import { UseSettingsReturn } from "@/types";
 import curl2Json, { ResultJSON } from "@bany/curl-to-json";
 import { KeyIcon, TrashIcon } from "lucide-react";
@@ -12,6 +18,11 @@ export const Providers = ({
 }: UseSettingsReturn) => {
   const [localSelectedProvider, setLocalSelectedProvider] =
     useState<ResultJSON | null>(null);
+  // Whether the secret store holds a key for the provider; the key itself stays in Rust
+  const [hasSavedKey, setHasSavedKey] = useState(false);
+  // Typed key, only until it's saved; it never goes into the provider variables
+  const [apiKeyInput, setApiKeyInput] = useState("");
+  const [apiKeyError, setApiKeyError] = useState<string | null>(null);
 
   useEffect(() => {
     if (selectedAIProvider?.provider) {
@@ -25,18 +36,50 @@ export const Providers = ({
     }
   }, [selectedAIProvider?.provider]);
 
+  useEffect(() => {
+    const providerId = selectedAIProvider?.provider;
+    setApiKeyInput("");
+    setApiKeyError(null);
+    if (!providerId) {
+      setHasSavedKey(false);
+      return;
+    }
+    hasProviderApiKey(providerId)
+      .then(setHasSavedKey)
+      .catch(() => setHasSavedKey(false));
+  }, [selectedAIProvider?.provider]);
+
   const findKeyAndValue = (key: string) => {
     return variables?.find((v) => v?.key === key);
   };
 
-  const getApiKeyValue = () => {
-    const apiKeyVar = findKeyAndValue("api_key");
-    if (!apiKeyVar || !selectedAIProvider?.variables) return "";
-    return selectedAIProvider?.variables?.[apiKeyVar.key] || "";
+  const saveApiKey = async () => {
+    const providerId = selectedAIProvider?.provider;
+    const apiKey = apiKeyInput.trim();
+    const curl = allAiProviders?.find((p) => p?.id === providerId)?.curl;
+    if (!providerId || !apiKey || !curl) return;
+
+    try {
+      await saveProviderApiKey(providerId, apiKey, curl);
+      setApiKeyInput("");
+      setApiKeyError(null);
+      setHasSavedKey(true);
+    } catch (error) {
+      console.error("Failed to save provider API key:", error);
+      setApiKeyError(getErrorMessage(error));
+    }
   };
 
-  const isApiKeyEmpty = () => {
-    return !getApiKeyValue().trim();
+  const removeApiKey = async () => {
+    const providerId = selectedAIProvider?.provider;
+    if (!providerId) return;
+
+    try {
+      await removeProviderApiKey(providerId);
+      setHasSavedKey(false);
+    } catch (error) {
+      console.error("Failed to remove provider API key:", error);
+    }
   };
 
   return (
@@ -87,88 +130,55 @@ export const Providers = ({
               )?.isCustom
                 ? "Custom Provider"
                 : selectedAIProvider?.provider
-            } API key to authenticate and access AI models. Your key is stored locally and never shared.`}
+            } API key to authenticate and access AI models. Your key is stored locally and never shared.${
+              hasSavedKey ? " A key is saved." : ""
+            }`}
           />
 
           <div className="space-y-2">
             <div className="flex gap-2">
               <Input
                 type="password"
-                placeholder="**********"
-                value={getApiKeyValue()}
-                onChange={(value) => {
-                  const apiKeyVar = findKeyAndValue("api_key");
-                  if (!apiKeyVar || !selectedAIProvider) return;
-
-                  onSetSelectedAIProvider({
-                    ...selectedAIProvider,
-                    variables: {
-                      ...selectedAIProvider.variables,
-                      [apiKeyVar.key]:
-                        typeof value === "string" ? value : value.target.value,
-                    },
-                  });
-                }}
+                placeholder={
+                  hasSavedKey ? "Key saved, type to replace it" : "**********"
+                }
+                value={apiKeyInput}
+                onChange={(value) =>
+                  setApiKeyInput(
+                    typeof value === "string" ? value : value.target.value
+                  )
+                }
                 onKeyDown={(e) => {
-                  const apiKeyVar = findKeyAndValue("api_key");
-                  if (!apiKeyVar || !selectedAIProvider) return;
-
-                  onSetSelectedAIProvider({
-                    ...selectedAIProvider,
-                    variables: {
-                      ...selectedAIProvider.variables,
-                      [apiKeyVar.key]: (e.target as HTMLInputElement).value,
-                    },
-                  });
+                  if (e.key === "Enter") saveApiKey();
                 }}
                 disabled={false}
                 className="flex-1 h-11 border-1 border-input/50 focus:border-primary/50 transition-colors"
               />
-              {isApiKeyEmpty() ? (
+              {!apiKeyInput.trim() && hasSavedKey ? (
                 <Button
-                  onClick={() => {
-                    const apiKeyVar = findKeyAndValue("api_key");
-                    if (!apiKeyVar || !selectedAIProvider || isApiKeyEmpty())
-                      return;
-
-                    onSetSelectedAIProvider({
-                      ...selectedAIProvider,
-                      variables: {
-                        ...selectedAIProvider.variables,
-                        [apiKeyVar.key]: getApiKeyValue(),
-                      },
-                    });
-                  }}
-                  disabled={isApiKeyEmpty()}
+                  onClick={removeApiKey}
                   size="icon"
+                  variant="destructive"
                   className="shrink-0 h-11 w-11"
-                  title="Submit API Key"
+                  title="Remove API Key"
                 >
-                  <KeyIcon className="h-4 w-4" />
+                  <TrashIcon className="h-4 w-4" />
                 </Button>
               ) : (
                 <Button
-                  onClick={() => {
-                    const apiKeyVar = findKeyAndValue("api_key");
-                    if (!apiKeyVar || !selectedAIProvider) return;
-
-                    onSetSelectedAIProvider({
-                      ...selectedAIProvider,
-                      variables: {
-                        ...selectedAIProvider.variables,
-                        [apiKeyVar.key]: "",
-                      },
-                    });
-                  }}
+                  onClick={saveApiKey}
+                  disabled={!apiKeyInput.trim()}
                   size="icon"
-                  variant="destructive"
                   className="shrink-0 h-11 w-11"
-                  title="Remove API Key"
+                  title="Submit API Key"
                 >
-                  <TrashIcon className="h-4 w-4" />
+                  <KeyIcon className="h-4 w-4" />
                 </Button>
               )}
             </div>
+            {apiKeyError ? (
+              <p className="text-xs text-red-500 mt-1">{apiKeyError}</p>
+            ) : null}
           </div>
         </div>
       ) : null}
@@ -16,7 +16,6 @@ export const AI_PROVIDERS = [
     curl: `curl https://api.anthropic.com/v1/messages \\
   -H "x-api-key: {{API_KEY}}" \\
   -H "anthropic-version: 2023-06-01" \\
-  -H "anthropic-dangerous-direct-browser-access: true" \\
   -H "content-type: application/json" \\
   -d '{
     "model": "{{MODEL}}",
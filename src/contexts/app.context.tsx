@@ -10,6 +10,7 @@ import {
   updateAlwaysOnTop,
   updateTitlesVisibility,
   CustomizableState,
+  saveProviderApiKey,
 } from "@/lib/storage";
 import { IContextType, ScreenshotConfig, TYPE_PROVIDER } from "@/types";
 import curl2Json from "@bany/curl-to-json";
@@ -144,7 +145,24 @@ export const AppProvider = ({ children }: { children: ReactNode }) => {
       STORAGE_KEYS.SELECTED_AI_PROVIDER
     );
     if (savedSelectedAi) {
-      setSelectedAIProvider(JSON.parse(savedSelectedAi));
+      const selected = JSON.parse(savedSelectedAi);
+      // Older versions kept the API key here; move it to the secret store once
+      const { api_key: legacyApiKey, ...variables } = selected.variables ?? {};
+      if (legacyApiKey !== undefined) {
+        const curl = [...AI_PROVIDERS, ...aiList].find(
+          (p) => p.id === selected.provider
+        )?.curl;
+        if (legacyApiKey.trim() && curl) {
+          saveProviderApiKey(selected.provider, legacyApiKey, curl).catch(
+            (error) => console.error("Failed to save provider API key:", error)
+          );
+        }
+        safeLocalStorage.setItem(
+          STORAGE_KEYS.SELECTED_AI_PROVIDER,
+          JSON.stringify({ ...selected, variables })
+        );
+      }
+      setSelectedAIProvider({ ...selected, variables });
     }
 
 
@@ -234,7 +252,7 @@ export const AppProvider = ({ children }: { children: ReactNode }) => {
     return () => window.removeEventListener("storage", handleStorageChange);
   }, []);
 
-  // Sync selected AI to localStorage
+  // Sync selected AI to localStorage; its API key is saved from settings straight to the secret store
   useEffect(() => {
     if (selectedAIProvider.provider) {
       safeLocalStorage.setItem(
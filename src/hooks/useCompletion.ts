@@ -1,12 +1,15 @@
 import { useState, useCallback, useRef, useEffect } from "react";
 import { useWindowResize } from "./useWindow";
-import { useGlobalShortcuts } from "@/hooks";
+import { useGlobalShortcuts, PushToTalkSegment } from "@/hooks";
 import { MAX_FILES } from "@/config";
 import { useApp } from "@/contexts";
-import { fetchAIResponse, safeLocalStorage } from "@/lib";
+import { fetchAIResponse, safeLocalStorage, toChatAttachments } from "@/lib";
+import { AttachmentSource, ChatAttachment } from "@/types";
 import { STORAGE_KEYS } from "@/config";
 import { invoke } from "@tauri-apps/api/core";
 import { shouldUsePluelyAPI } from "@/lib/functions/pluely.api";
+import { voskLocal } from "@/lib/vosk-local";
+import { getErrorMessage } from "@/lib/utils";
 
 // Types for completion
 interface AttachedFile {
@@ -15,6 +18,8 @@ interface AttachedFile {
   type: string;
   base64: string;
   size: number;
+  source?: AttachmentSource;
+  caption?: string;
 }
 
 interface ChatMessage {
@@ -66,6 +71,7 @@ export const useCompletion = () => {
   const [messageHistoryOpen, setMessageHistoryOpen] = useState(false);
   const [isFilesPopoverOpen, setIsFilesPopoverOpen] = useState(false);
   const [isScreenshotLoading, setIsScreenshotLoading] = useState(false);
+  const [isPushToTalkRecording, setIsPushToTalkRecording] = useState(false);
   const inputRef = useRef<HTMLInputElement | null>(null);
 
   const { resizeWindow } = useWindowResize();
@@ -87,25 +93,29 @@ export const useCompletion = () => {
     setState((prev) => ({ ...prev, response: value }));
   }, []);
 
-  const addFile = useCallback(async (file: File) => {
-    try {
-      const base64 = await fileToBase64(file);
-      const attachedFile: AttachedFile = {
-        id: Date.now().toString(),
-        name: file.name,
-        type: file.type,
-        base64,
-        size: file.size,
-      };
+  const addFile = useCallback(
+    async (file: File, source: AttachmentSource = "file") => {
+      try {
+        const base64 = await fileToBase64(file);
+        const attachedFile: AttachedFile = {
+          id: Date.now().toString(),
+          name: file.name,
+          type: file.type,
+          base64,
+          size: file.size,
+          source,
+        };
 
-      setState((prev) => ({
-        ...prev,
-        attachedFiles: [...prev.attachedFiles, attachedFile],
-      }));
-    } catch (error) {
-      console.error("Failed to process file:", error);
-    }
-  }, []);
+        setState((prev) => ({
+          ...prev,
+          attachedFiles: [...prev.attachedFiles, attachedFile],
+        }));
+      } catch (error) {
+        console.error("Failed to process file:", error);
+      }
+    },
+    []
+  );
 
   const removeFile = useCallback((fileId: string) => {
     setState((prev) => ({
@@ -140,6 +150,8 @@ export const useCompletion = () => {
 
       abortControllerRef.current = new AbortController();
 
+      const signal = abortControllerRef.current.signal;
+
       try {
         // Prepare message history for the AI
         const messageHistory = state.conversationHistory.map((msg) => ({
@@ -148,14 +160,7 @@ export const useCompletion = () => {
         }));
 
         // Handle image attachments
-        const imagesBase64: string[] = [];
-        if (state.attachedFiles.length > 0) {
-          state.attachedFiles.forEach((file) => {
-            if (file.type.startsWith("image/")) {
-              imagesBase64.push(file.base64);
-            }
-          });
-        }
+        const attachments = toChatAttachments(state.attachedFiles);
 
         let fullResponse = "";
 
@@ -194,7 +199,9 @@ export const useCompletion = () => {
             systemPrompt: systemPrompt || undefined,
             history: messageHistory,
             userMessage: input,
-            imagesBase64,
+            attachments,
+            conversationId: state.currentConversationId,
+            signal,
           })) {
             fullResponse += chunk;
             setState((prev) => ({
@@ -236,6 +243,7 @@ export const useCompletion = () => {
       allAiProviders,
       systemPrompt,
       state.conversationHistory,
+      state.currentConversationId,
     ]
   );
 
@@ -464,8 +472,11 @@ export const useCompletion = () => {
     e.target.value = "";
   };
 
-  const handleScreenshotSubmit = async (base64: string, prompt?: string) => {
-    if (state.attachedFiles.length >= MAX_FILES) {
+  const handleScreenshotSubmit = async (
+    screens: ChatAttachment[],
+    prompt?: string
+  ) => {
+    if (state.attachedFiles.length + screens.length > MAX_FILES) {
       setState((prev) => ({
         ...prev,
         error: `You can only upload ${MAX_FILES} files`,
@@ -473,16 +484,19 @@ export const useCompletion = () => {
       return;
     }
 
+    const screenshotFiles: AttachedFile[] = screens.map((screen, index) => ({
+      id: `${Date.now()}_${index}`,
+      name: `screenshot_${Date.now()}_${index + 1}.png`,
+      type: screen.mime_type,
+      base64: screen.data,
+      size: screen.data.length,
+      source: screen.source,
+      caption: screen.caption,
+    }));
+
     try {
       if (prompt) {
-        // Auto mode: Submit directly to AI with screenshot
-        const attachedFile: AttachedFile = {
-          id: Date.now().toString(),
-          name: `screenshot_${Date.now()}.png`,
-          type: "image/png",
-          base64: base64,
-          size: base64.length,
-        };
+        // Auto mode: Submit directly to AI with the screenshots
 
         // Cancel any existing request
         if (abortControllerRef.current) {
@@ -491,6 +505,8 @@ export const useCompletion = () => {
 
         abortControllerRef.current = new AbortController();
 
+        const signal = abortControllerRef.current.signal;
+
         try {
           // Prepare message history for the AI
           const messageHistory = state.conversationHistory.map((msg) => ({
@@ -529,14 +545,16 @@ export const useCompletion = () => {
             response: "",
           }));
 
-          // Use the fetchAIResponse function with image
+          // Use the fetchAIResponse function with the screenshots
           for await (const chunk of fetchAIResponse({
             provider: usePluelyAPI ? undefined : provider,
             selectedProvider: selectedAIProvider,
             systemPrompt: systemPrompt || undefined,
             history: messageHistory,
             userMessage: prompt,
-            imagesBase64: [base64],
+            attachments: screens,
+            conversationId: state.currentConversationId,
+            signal,
           })) {
             fullResponse += chunk;
             setState((prev) => ({
@@ -549,7 +567,7 @@ export const useCompletion = () => {
 
           // Save the conversation after successful completion
           if (fullResponse) {
-            saveCurrentConversation(prompt, fullResponse, [attachedFile]);
+            saveCurrentConversation(prompt, fullResponse, screenshotFiles);
             // Clear input after saving
             setState((prev) => ({
               ...prev,
@@ -566,17 +584,9 @@ export const useCompletion = () => {
         }
       } else {
         // Manual mode: Add to attached files
-        const attachedFile: AttachedFile = {
-          id: Date.now().toString(),
-          name: `screenshot_${Date.now()}.png`,
-          type: "image/png",
-          base64: base64,
-          size: base64.length,
-        };
-
         setState((prev) => ({
           ...prev,
-          attachedFiles: [...prev.attachedFiles, attachedFile],
+          attachedFiles: [...prev.attachedFiles, ...screenshotFiles],
         }));
       }
     } catch (error) {
@@ -635,7 +645,9 @@ export const useCompletion = () => {
         });
 
         // Process all files
-        await Promise.all(processedFiles.map((file) => addFile(file)));
+        await Promise.all(
+          processedFiles.map((file) => addFile(file, "clipboard"))
+        );
       }
     },
     [state.attachedFiles.length, addFile]
@@ -675,17 +687,15 @@ export const useCompletion = () => {
     if (!screenshotConfiguration.enabled || !handleScreenshotSubmit) return;
     setIsScreenshotLoading(true);
     try {
-      const base64 = await invoke("capture_to_base64");
+      // One screenshot per monitor, primary first
+      const screens = await invoke<ChatAttachment[]>("capture_monitors");
 
       if (screenshotConfiguration.mode === "auto") {
         // Auto mode: Submit directly to AI with the configured prompt
-        handleScreenshotSubmit(
-          base64 as string,
-          screenshotConfiguration.autoPrompt
-        );
+        handleScreenshotSubmit(screens, screenshotConfiguration.autoPrompt);
       } else if (screenshotConfiguration.mode === "manual") {
         // Manual mode: Add to attached files without prompt
-        handleScreenshotSubmit(base64 as string);
+        handleScreenshotSubmit(screens);
       }
     } catch (error) {
       console.error("Failed to capture screenshot:", error);
@@ -699,16 +709,71 @@ export const useCompletion = () => {
     setMicOpen(!micOpen);
   };
 
+  // Push-to-talk segments skip VAD and go straight to transcription and chat
+  const transcribePushToTalk = async (audioBase64: string): Promise<string> => {
+    if (await shouldUsePluelyAPI()) {
+      const response = await invoke<{
+        success: boolean;
+        transcription?: string;
+        error?: string;
+      }>("transcribe_audio", { audioBase64 });
+      if (!response.success) {
+        throw new Error(response.error || "Transcription failed");
+      }
+      return response.transcription || "";
+    }
+
+    const bytes = Uint8Array.from(atob(audioBase64), (c) => c.charCodeAt(0));
+    return voskLocal.transcribe(new Blob([bytes], { type: "audio/wav" }));
+  };
+
+  const handlePushToTalkEnd = async (segment: PushToTalkSegment) => {
+    setIsPushToTalkRecording(false);
+    if (segment.error) {
+      setState((prev) => ({ ...prev, error: segment.error }));
+      return;
+    }
+    // Only tapped, nothing to send
+    if (!segment.audio_base64) {
+      return;
+    }
+
+    try {
+      setState((prev) => ({ ...prev, error: null }));
+      const transcription = await transcribePushToTalk(segment.audio_base64);
+      if (transcription.trim()) {
+        await submit(transcription);
+      } else {
+        setState((prev) => ({
+          ...prev,
+          error: "No speech detected. Please try speaking more clearly.",
+        }));
+      }
+    } catch (error) {
+      console.error("Failed to transcribe push-to-talk audio:", error);
+      setState((prev) => ({
+        ...prev,
+        error: getErrorMessage(error),
+      }));
+    }
+  };
+
   // register callbacks for global shortcuts
   useEffect(() => {
     globalShortcuts.registerAudioCallback(toggleRecording);
     globalShortcuts.registerInputRef(inputRef.current);
     globalShortcuts.registerScreenshotCallback(captureScreenshot);
+    globalShortcuts.registerPushToTalkCallbacks(
+      () => setIsPushToTalkRecording(true),
+      handlePushToTalkEnd
+    );
   }, [
     globalShortcuts.registerAudioCallback,
     globalShortcuts.registerInputRef,
     globalShortcuts.registerScreenshotCallback,
+    globalShortcuts.registerPushToTalkCallbacks,
     toggleRecording,
+    handlePushToTalkEnd,
     captureScreenshot,
     inputRef,
   ]);
@@ -732,6 +797,7 @@ export const useCompletion = () => {
     setEnableVAD,
     micOpen,
     setMicOpen,
+    isPushToTalkRecording,
     currentConversationId: state.currentConversationId,
     conversationHistory: state.conversationHistory,
     loadConversation,
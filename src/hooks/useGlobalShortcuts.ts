@@ -7,6 +7,27 @@ interface Shortcuts {
   audio: string;
   screenshot: string;
   systemAudio: string;
+  pushToTalk: string;
+}
+
+export type ShortcutAction = keyof Shortcuts;
+
+export interface ShortcutStatus {
+  accelerator: string;
+  // taken: another app or the OS owns the keys; conflict: another action uses them
+  state: "registered" | "taken" | "invalid" | "conflict";
+  error: string | null;
+}
+
+export type PushToTalkSource = "microphone" | "system";
+
+// Sent when the push-to-talk key is released
+export interface PushToTalkSegment {
+  source: PushToTalkSource;
+  // null when the key was only tapped or capture failed
+  audio_base64: string | null;
+  duration_ms: number;
+  error: string | null;
 }
 
 // Global singleton to prevent multiple event listeners in StrictMode
@@ -15,6 +36,8 @@ let globalEventListeners: {
   audio?: UnlistenFn;
   screenshot?: UnlistenFn;
   systemAudio?: UnlistenFn;
+  pushToTalkStart?: UnlistenFn;
+  pushToTalkEnd?: UnlistenFn;
 } = {};
 
 // Global debounce for screenshot events to prevent duplicates
@@ -25,6 +48,12 @@ export const useGlobalShortcuts = () => {
   const audioCallbackRef = useRef<(() => void) | null>(null);
   const screenshotCallbackRef = useRef<(() => void) | null>(null);
   const systemAudioCallbackRef = useRef<(() => void) | null>(null);
+  const pushToTalkStartCallbackRef = useRef<
+    ((source: PushToTalkSource) => void) | null
+  >(null);
+  const pushToTalkEndCallbackRef = useRef<
+    ((segment: PushToTalkSegment) => void) | null
+  >(null);
 
   const checkShortcutsRegistered = useCallback(async (): Promise<boolean> => {
     try {
@@ -46,6 +75,27 @@ export const useGlobalShortcuts = () => {
     }
   }, []);
 
+  const getShortcutStatus = useCallback(async (): Promise<Partial<
+    Record<ShortcutAction, ShortcutStatus>
+  > | null> => {
+    try {
+      return await invoke<Partial<Record<ShortcutAction, ShortcutStatus>>>(
+        "get_shortcut_status"
+      );
+    } catch (error) {
+      console.error("Failed to get shortcut status:", error);
+      return null;
+    }
+  }, []);
+
+  // Rebinds an action; rejects with the reason if the accelerator is invalid or can't be registered
+  const setShortcut = useCallback(
+    async (action: ShortcutAction, accelerator: string): Promise<Shortcuts> => {
+      return invoke<Shortcuts>("set_shortcut", { action, accelerator });
+    },
+    []
+  );
+
   // Register input element for auto-focus
   const registerInputRef = useCallback((input: HTMLInputElement | null) => {
     inputRef.current = input;
@@ -66,6 +116,34 @@ export const useGlobalShortcuts = () => {
     systemAudioCallbackRef.current = callback;
   }, []);
 
+  // Register push-to-talk callbacks, for the key going down and coming back up
+  const registerPushToTalkCallbacks = useCallback(
+    (
+      onStart: (source: PushToTalkSource) => void,
+      onEnd: (segment: PushToTalkSegment) => void
+    ) => {
+      pushToTalkStartCallbackRef.current = onStart;
+      pushToTalkEndCallbackRef.current = onEnd;
+    },
+    []
+  );
+
+  const getPushToTalkSource = useCallback(async (): Promise<PushToTalkSource> => {
+    try {
+      return await invoke<PushToTalkSource>("get_push_to_talk_source");
+    } catch (error) {
+      console.error("Failed to get push-to-talk source:", error);
+      return "microphone";
+    }
+  }, []);
+
+  const setPushToTalkSource = useCallback(
+    async (source: PushToTalkSource): Promise<void> => {
+      await invoke("set_push_to_talk_source", { source });
+    },
+    []
+  );
+
   // Setup event listeners using global singleton
   useEffect(() => {
     const setupEventListeners = async () => {
@@ -99,6 +177,20 @@ export const useGlobalShortcuts = () => {
             console.warn("Error cleaning up system audio listener:", error);
           }
         }
+        if (globalEventListeners.pushToTalkStart) {
+          try {
+            globalEventListeners.pushToTalkStart();
+          } catch (error) {
+            console.warn("Error cleaning up push-to-talk start listener:", error);
+          }
+        }
+        if (globalEventListeners.pushToTalkEnd) {
+          try {
+            globalEventListeners.pushToTalkEnd();
+          } catch (error) {
+            console.warn("Error cleaning up push-to-talk end listener:", error);
+          }
+        }
 
         // Listen for focus text input event
         const unlistenFocus = await listen("focus-text-input", () => {
@@ -144,6 +236,27 @@ export const useGlobalShortcuts = () => {
           }
         });
         globalEventListeners.systemAudio = unlistenSystemAudio;
+
+        // Listen for push-to-talk key down and up
+        const unlistenPushToTalkStart = await listen<PushToTalkSource>(
+          "push-to-talk-start",
+          (event) => {
+            if (pushToTalkStartCallbackRef.current) {
+              pushToTalkStartCallbackRef.current(event.payload);
+            }
+          }
+        );
+        globalEventListeners.pushToTalkStart = unlistenPushToTalkStart;
+
+        const unlistenPushToTalkEnd = await listen<PushToTalkSegment>(
+          "push-to-talk-end",
+          (event) => {
+            if (pushToTalkEndCallbackRef.current) {
+              pushToTalkEndCallbackRef.current(event.payload);
+            }
+          }
+        );
+        globalEventListeners.pushToTalkEnd = unlistenPushToTalkEnd;
       } catch (error) {
         console.error("Failed to setup event listeners:", error);
       }
@@ -155,9 +268,14 @@ export const useGlobalShortcuts = () => {
   return {
     checkShortcutsRegistered,
     getShortcuts,
+    getShortcutStatus,
+    setShortcut,
     registerInputRef,
     registerAudioCallback,
     registerScreenshotCallback,
     registerSystemAudioCallback,
+    registerPushToTalkCallbacks,
+    getPushToTalkSource,
+    setPushToTalkSource,
   };
 };
@@ -15,6 +15,7 @@ import {
   saveConversation,
 } from "@/lib";
 import { shouldUsePluelyAPI } from "@/lib/functions/pluely.api";
+import { getErrorMessage } from "@/lib/utils";
 import { Message } from "@/types/completion";
 
 // Chat message interface (reusing from useCompletion)
@@ -262,6 +263,8 @@ export function useSystemAudio() {
 
       abortControllerRef.current = new AbortController();
 
+      const signal = abortControllerRef.current.signal;
+
       try {
         setIsAIProcessing(true);
         setLastAIResponse("");
@@ -290,7 +293,7 @@ export function useSystemAudio() {
             systemPrompt: prompt,
             history: previousMessages,
             userMessage: transcription,
-            imagesBase64: [],
+            signal,
           })) {
             fullResponse += chunk;
             setLastAIResponse((prev) => prev + chunk);
@@ -356,8 +359,7 @@ export function useSystemAudio() {
         updatedAt: 0,
       });
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err);
-      setError(errorMessage);
+      setError(getErrorMessage(err));
     }
   }, []);
 
@@ -1,3 +1,4 @@
+import { invoke } from "@tauri-apps/api/core";
 import { safeLocalStorage } from "./storage/helper";
 import { STORAGE_KEYS } from "@/config";
 import { ChatConversation } from "@/types/completion";
@@ -15,6 +16,20 @@ export function loadChatHistory(): ChatConversation[] {
   }
 }
 
+// Mirrors history to the app so the model's conversation search tool can read it.
+// Attachments are left out to keep the index small.
+function syncConversationIndex(conversations: ChatConversation[]): void {
+  const index = conversations.map((c) => ({
+    id: c.id,
+    title: c.title,
+    updatedAt: c.updatedAt,
+    messages: c.messages.map(({ role, content }) => ({ role, content })),
+  }));
+  invoke("sync_conversations", { conversations: index }).catch((error) =>
+    console.error("Failed to sync conversation index:", error)
+  );
+}
+
 export function saveConversation(conversation: ChatConversation): void {
   try {
     if (!conversation.id || !conversation.updatedAt) {
@@ -41,6 +56,7 @@ export function saveConversation(conversation: ChatConversation): void {
       STORAGE_KEYS.CHAT_HISTORY,
       JSON.stringify(conversations)
     );
+    syncConversationIndex(conversations);
   } catch (error) {
     console.error("Failed to save conversation:", error);
   }
@@ -77,6 +93,7 @@ export function deleteConversation(conversationId: string): boolean {
       STORAGE_KEYS.CHAT_HISTORY,
       JSON.stringify(filteredConversations)
     );
+    syncConversationIndex(filteredConversations);
     return true;
   } catch (error) {
     console.error("Failed to delete conversation:", error);
@@ -1,97 +1,180 @@
-import {
-  buildDynamicMessages,
-  deepVariableReplacer,
-  extractVariables,
-  getByPath,
-  getStreamingContent,
-} from "./common.function";
-import { Message, TYPE_PROVIDER } from "@/types";
-import { fetch as tauriFetch } from "@tauri-apps/plugin-http";
+import { AttachedFile, ChatAttachment, Message, TYPE_PROVIDER } from "@/types";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import curl2Json from "@bany/curl-to-json";
 import { shouldUsePluelyAPI } from "./pluely.api";
+import { getErrorMessage } from "@/lib/utils";
+
+// Converts history to typed chat messages, oldest first
+function toChatHistory(history: Message[]) {
+  return [...history].reverse().map((msg) => {
+    if (typeof msg.content === "string") {
+      return { role: msg.role, content: msg.content, attachments: [] };
+    }
+    const text = msg.content
+      .filter((part) => part.type === "text" && part.text)
+      .map((part) => part.text)
+      .join("\n");
+    const attachments = msg.content.flatMap((part) => {
+      const match = part.image_url?.url?.match(/^data:([^;]+);base64,(.*)$/);
+      return match ? [{ mime_type: match[1], data: match[2] }] : [];
+    });
+    return { role: msg.role, content: text, attachments };
+  });
+}
+
+// Runs a streaming chat command and yields the chunks it emits for this request
+async function* streamChatCommand(
+  command: string,
+  args: Record<string, unknown>,
+  signal?: AbortSignal
+): AsyncIterable<string> {
+  if (signal?.aborted) return;
+  const requestId = crypto.randomUUID();
+  let streamComplete = false;
+  const streamChunks: string[] = [];
+
+  const unlisten = await listen<{ request_id: string; content: string }>(
+    "chat_stream_chunk",
+    (event) => {
+      if (event.payload.request_id !== requestId) return;
+      streamChunks.push(event.payload.content);
+    }
+  );
+
+  const unlistenComplete = await listen<{ request_id: string }>(
+    "chat_stream_complete",
+    (event) => {
+      if (event.payload.request_id !== requestId) return;
+      streamComplete = true;
+    }
+  );
+
+  // Aborting also stops the request in Rust, which closes its connection
+  const onAbort = () => {
+    invoke("cancel_chat_stream", { requestId }).catch((error) =>
+      console.warn("Failed to cancel chat stream:", error)
+    );
+  };
+  signal?.addEventListener("abort", onAbort, { once: true });
+
+  try {
+    // Start the streaming request; a cancelled one rejects, which isn't an error here
+    try {
+      await invoke(command, { ...args, requestId });
+    } catch (error) {
+      if (signal?.aborted) return;
+      throw error;
+    }
+
+    // Yield chunks as they come in
+    let lastIndex = 0;
+    while (!streamComplete && !signal?.aborted) {
+      // Wait a bit for chunks to accumulate
+      await new Promise((resolve) => setTimeout(resolve, 50));
+
+      // Yield any new chunks
+      for (let i = lastIndex; i < streamChunks.length; i++) {
+        yield streamChunks[i];
+      }
+      lastIndex = streamChunks.length;
+    }
+
+    // Yield any remaining chunks
+    if (signal?.aborted) return;
+    for (let i = lastIndex; i < streamChunks.length; i++) {
+      yield streamChunks[i];
+    }
+  } finally {
+    signal?.removeEventListener("abort", onAbort);
+    unlisten();
+    unlistenComplete();
+  }
+}
+
+// Attached images in the shape the chat commands expect
+export function toChatAttachments(files: AttachedFile[]): ChatAttachment[] {
+  return files
+    .filter((file) => file.type.startsWith("image/"))
+    .map((file) => ({
+      mime_type: file.type,
+      data: file.base64,
+      source: file.source ?? "file",
+      caption: file.caption,
+    }));
+}
 
 // Pluely AI streaming function
 async function* fetchPluelyAIResponse(params: {
   systemPrompt?: string;
   userMessage: string;
-  imagesBase64?: string[];
+  attachments?: ChatAttachment[];
   history?: Message[];
+  conversationId?: string | null;
+  signal?: AbortSignal;
 }): AsyncIterable<string> {
   try {
     const {
       systemPrompt,
       userMessage,
-      imagesBase64 = [],
+      attachments = [],
       history = [],
+      conversationId,
+      signal,
     } = params;
 
-    // Convert history to the expected format
-    let historyString: string | undefined;
-    if (history.length > 0) {
-      const formattedHistory = history?.reverse()?.map((msg) => ({
-        role: msg.role,
-        content: [{ type: "text", text: msg.content }],
-      }));
-      historyString = JSON.stringify(formattedHistory);
-    }
-
-    // Handle images - can be string or array
-    let imageBase64: any = undefined;
-    if (imagesBase64.length > 0) {
-      imageBase64 = imagesBase64.length === 1 ? imagesBase64[0] : imagesBase64;
-    }
-
-    // Set up streaming event listener
-    let streamComplete = false;
-    const streamChunks: string[] = [];
-
-    const unlisten = await listen("chat_stream_chunk", (event) => {
-      const chunk = event.payload as string;
-      streamChunks.push(chunk);
-    });
-
-    const unlistenComplete = await listen("chat_stream_complete", () => {
-      streamComplete = true;
-    });
-
-    try {
-      // Start the streaming request
-      await invoke("chat_stream", {
+    yield* streamChatCommand(
+      "chat_stream",
+      {
         userMessage,
         systemPrompt,
-        imageBase64,
-        history: historyString,
-      });
-
-      // Yield chunks as they come in
-      let lastIndex = 0;
-      while (!streamComplete) {
-        // Wait a bit for chunks to accumulate
-        await new Promise((resolve) => setTimeout(resolve, 50));
-
-        // Yield any new chunks
-        for (let i = lastIndex; i < streamChunks.length; i++) {
-          yield streamChunks[i];
-        }
-        lastIndex = streamChunks.length;
-      }
-
-      // Yield any remaining chunks
-      for (let i = lastIndex; i < streamChunks.length; i++) {
-        yield streamChunks[i];
-      }
-    } finally {
-      unlisten();
-      unlistenComplete();
-    }
+        attachments,
+        history: toChatHistory(history),
+        conversationId: conversationId ?? undefined,
+      },
+      signal
+    );
   } catch (error) {
-    const errorMessage = error instanceof Error ? error.message : String(error);
+    const errorMessage = getErrorMessage(error);
     yield `Pluely API Error: ${errorMessage}`;
   }
 }
 
+// JSON answer matching `schema`, validated (and repaired once if needed) by the app. Goes to the
+// direct provider saved as `providerId` when given, the Pluely API otherwise.
+export async function fetchStructuredResponse<T = unknown>(params: {
+  schema: Record<string, unknown>;
+  schemaName?: string;
+  providerId?: string;
+  systemPrompt?: string;
+  userMessage: string;
+  attachments?: ChatAttachment[];
+  history?: Message[];
+  conversationId?: string | null;
+}): Promise<T> {
+  const {
+    schema,
+    schemaName,
+    providerId,
+    systemPrompt,
+    userMessage,
+    attachments = [],
+    history = [],
+    conversationId,
+  } = params;
+
+  return invoke<T>("chat_structured", {
+    requestId: crypto.randomUUID(),
+    providerId,
+    userMessage,
+    systemPrompt,
+    attachments,
+    history: toChatHistory(history),
+    conversationId: conversationId ?? undefined,
+    schema,
+    schemaName,
+  });
+}
+
 export async function* fetchAIResponse(params: {
   provider: TYPE_PROVIDER | undefined;
   selectedProvider: {
@@ -101,7 +184,9 @@ export async function* fetchAIResponse(params: {
   systemPrompt?: string;
   history?: Message[];
   userMessage: string;
-  imagesBase64?: string[];
+  attachments?: ChatAttachment[];
+  conversationId?: string | null;
+  signal?: AbortSignal;
 }): AsyncIterable<string> {
   try {
     const {
@@ -110,7 +195,9 @@ export async function* fetchAIResponse(params: {
       systemPrompt,
       history = [],
       userMessage,
-      imagesBase64 = [],
+      attachments = [],
+      conversationId,
+      signal,
     } = params;
 
     // Check if we should use Pluely API instead
@@ -119,8 +206,10 @@ export async function* fetchAIResponse(params: {
       yield* fetchPluelyAIResponse({
         systemPrompt,
         userMessage,
-        imagesBase64,
+        attachments,
         history,
+        conversationId,
+        signal,
       });
       return;
     }
@@ -130,174 +219,32 @@ export async function* fetchAIResponse(params: {
     if (!selectedProvider) {
       throw new Error(`Selected provider not provided`);
     }
-
-    let curlJson;
-    try {
-      curlJson = curl2Json(provider.curl);
-    } catch (error) {
-      throw new Error(
-        `Failed to parse curl: ${
-          error instanceof Error ? error.message : "Unknown error"
-        }`
-      );
-    }
-
-    const extractedVariables = extractVariables(provider.curl);
-    const requiredVars = extractedVariables.filter(
-      ({ key }) => key !== "SYSTEM_PROMPT" && key !== "TEXT" && key !== "IMAGE"
-    );
-    for (const { key } of requiredVars) {
-      if (
-        !selectedProvider.variables?.[key] ||
-        selectedProvider.variables[key].trim() === ""
-      ) {
-        throw new Error(
-          `Missing required variable: ${key}. Please configure it in settings.`
-        );
-      }
-    }
-
     if (!userMessage) {
       throw new Error("User message is required");
     }
-    if (imagesBase64.length > 0 && !provider.curl.includes("{{IMAGE}}")) {
-      throw new Error(
-        `Provider ${provider?.id ?? "unknown"} does not support image input`
-      );
-    }
-
-    let bodyObj: any = curlJson.data
-      ? JSON.parse(JSON.stringify(curlJson.data))
-      : {};
-    const messagesKey = Object.keys(bodyObj).find((key) =>
-      ["messages", "contents", "conversation", "history"].includes(key)
-    );
-
-    if (messagesKey && Array.isArray(bodyObj[messagesKey])) {
-      const finalMessages = buildDynamicMessages(
-        bodyObj[messagesKey],
-        history,
-        userMessage,
-        imagesBase64
-      );
-      bodyObj[messagesKey] = finalMessages;
-    }
-
-    const allVariables = {
-      ...Object.fromEntries(
-        Object.entries(selectedProvider.variables).map(([key, value]) => [
-          key.toUpperCase(),
-          value,
-        ])
-      ),
-      SYSTEM_PROMPT: systemPrompt || "",
-    };
 
-    bodyObj = deepVariableReplacer(bodyObj, allVariables);
-    let url = deepVariableReplacer(curlJson.url || "", allVariables);
+    // The API key isn't among the variables, Rust loads it from the secret store by provider id
+    const providerId = provider.id ?? selectedProvider.provider;
+    const variables = selectedProvider.variables ?? {};
 
-    const headers = deepVariableReplacer(curlJson.header || {}, allVariables);
-    headers["Content-Type"] = "application/json";
-
-    if (provider?.streaming) {
-      if (typeof bodyObj === "object" && bodyObj !== null) {
-        const streamKey = Object.keys(bodyObj).find(
-          (k) => k.toLowerCase() === "stream"
-        );
-        if (streamKey) {
-          bodyObj[streamKey] = true;
-        } else {
-          bodyObj.stream = true;
-        }
-      }
-    }
-
-    const fetchFunction = url?.includes("http") ? fetch : tauriFetch;
-
-    let response;
     try {
-      response = await fetchFunction(url, {
-        method: curlJson.method || "POST",
-        headers,
-        body: curlJson.method === "GET" ? undefined : JSON.stringify(bodyObj),
-      });
-    } catch (fetchError) {
-      yield `Network error during API request: ${
-        fetchError instanceof Error ? fetchError.message : "Unknown error"
-      }`;
-      return;
-    }
-
-    if (!response.ok) {
-      let errorText = "";
-      try {
-        errorText = await response.text();
-      } catch {}
-      yield `API request failed: ${response.status} ${response.statusText}${
-        errorText ? ` - ${errorText}` : ""
-      }`;
-      return;
-    }
-
-    if (!provider?.streaming) {
-      let json;
-      try {
-        json = await response.json();
-      } catch (parseError) {
-        yield `Failed to parse non-streaming response: ${
-          parseError instanceof Error ? parseError.message : "Unknown error"
-        }`;
-        return;
-      }
-      const content =
-        getByPath(json, provider?.responseContentPath || "") || "";
-      yield content;
-      return;
-    }
-
-    if (!response.body) {
-      yield "Streaming not supported or response body missing";
-      return;
-    }
-
-    const reader = response.body.getReader();
-    const decoder = new TextDecoder();
-    let buffer = "";
-
-    while (true) {
-      let readResult;
-      try {
-        readResult = await reader.read();
-      } catch (readError) {
-        yield `Error reading stream: ${
-          readError instanceof Error ? readError.message : "Unknown error"
-        }`;
-        return;
-      }
-      const { done, value } = readResult;
-      if (done) break;
-      buffer += decoder.decode(value, { stream: true });
-
-      const lines = buffer.split("\n");
-      buffer = lines.pop() || "";
-      for (const line of lines) {
-        if (line.startsWith("data:")) {
-          const trimmed = line.substring(5).trim();
-          if (!trimmed || trimmed === "[DONE]") continue;
-          try {
-            const parsed = JSON.parse(trimmed);
-            const delta = getStreamingContent(
-              parsed,
-              provider?.responseContentPath || ""
-            );
-            if (delta) {
-              yield delta;
-            }
-          } catch (e) {
-            // Ignore parsing errors for partial JSON chunks
-          }
-        }
-      }
+      yield* streamChatCommand(
+        "custom_provider_chat_stream",
+        {
+          providerId,
+          curl: provider.curl,
+          responseContentPath: provider.responseContentPath,
+          streaming: !!provider.streaming,
+          variables,
+          userMessage,
+          systemPrompt,
+          attachments,
+          history: toChatHistory(history),
+        },
+        signal
+      );
+    } catch (error) {
+      yield `API request failed: ${getErrorMessage(error)}`;
     }
   } catch (error) {
     throw new Error(
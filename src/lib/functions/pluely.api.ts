@@ -2,6 +2,39 @@ import { invoke } from "@tauri-apps/api/core";
 import { safeLocalStorage } from "../storage";
 import { STORAGE_KEYS } from "@/config";
 
+export type LicenseState =
+  | "missing"
+  | "unverified"
+  | "active"
+  | "expired"
+  | "needs_validation"
+  | "invalid";
+
+// Checked offline against the signed license token
+export interface LicenseStatus {
+  state: LicenseState;
+  active: boolean;
+  days_remaining: number | null;
+  offline_days_remaining: number | null;
+  expires_at: number | null;
+  entitlements: Record<string, boolean>;
+  error: string | null;
+}
+
+export async function getLicenseStatus(): Promise<LicenseStatus> {
+  const status = await invoke<LicenseStatus>("check_license_status");
+  if (status.state !== "needs_validation") return status;
+
+  // The offline grace period is over: a successful validation brings a fresh token
+  try {
+    await invoke("validate_license");
+  } catch (error) {
+    console.warn("Failed to validate license:", error);
+    return status;
+  }
+  return invoke<LicenseStatus>("check_license_status");
+}
+
 // Helper function to check if Pluely API should be used
 export async function shouldUsePluelyAPI(): Promise<boolean> {
   try {
@@ -10,9 +43,9 @@ export async function shouldUsePluelyAPI(): Promise<boolean> {
       safeLocalStorage.getItem(STORAGE_KEYS.PLUELY_API_ENABLED) === "true";
     if (!pluelyApiEnabled) return false;
 
-    // Check if license is available
-    const hasLicense = await invoke<boolean>("check_license_status");
-    return hasLicense;
+    // Check if the license is active
+    const status = await getLicenseStatus();
+    return status.active;
   } catch (error) {
     console.warn("Failed to check Pluely API availability:", error);
     return false;
@@ -9,6 +9,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { TYPE_PROVIDER } from "@/types";
 import curl2Json from "@bany/curl-to-json";
 import { shouldUsePluelyAPI } from "./pluely.api";
+import { getErrorMessage } from "@/lib/utils";
 
 // Pluely STT function
 async function fetchPluelySTT(audio: File | Blob): Promise<string> {
@@ -31,7 +32,7 @@ async function fetchPluelySTT(audio: File | Blob): Promise<string> {
       return response.error || "Transcription failed";
     }
   } catch (error) {
-    const errorMessage = error instanceof Error ? error.message : String(error);
+    const errorMessage = getErrorMessage(error);
     return `Pluely STT Error: ${errorMessage}`;
   }
 }
@@ -231,6 +232,9 @@ export async function fetchSTT(params: STTParams): Promise<string> {
       return [...warnings, "No transcription found"].join("; ");
     }
 
+    // Keep it available to the model's transcript tool
+    invoke("append_transcript", { text: transcription }).catch(() => {});
+
     // Return transcription with any warnings
     return [...warnings, transcription].filter(Boolean).join("; ");
   } catch (err) {
@@ -2,3 +2,4 @@ export * from "./ai-providers";
 export * from "./stt-providers";
 export * from "./helper";
 export * from "./customizable.storage";
+export * from "./secrets";
@@ -0,0 +1,69 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Namespaces of the app's secret store, see secret_store/mod.rs for their schemas
+export type SecretNamespace =
+  | "license"
+  | "provider_keys"
+  | "endpoint_keys";
+
+// The only namespace whose values come back to the webview; API keys are used from Rust
+export type ReadableSecretNamespace = "license";
+
+export async function getSecrets(
+  namespace: ReadableSecretNamespace,
+  keys?: string[]
+): Promise<Record<string, string>> {
+  return invoke<Record<string, string>>("secure_storage_get", {
+    namespace,
+    keys,
+  });
+}
+
+export async function getSecret(
+  namespace: ReadableSecretNamespace,
+  key: string
+): Promise<string | undefined> {
+  const values = await getSecrets(namespace, [key]);
+  return values[key];
+}
+
+export async function setSecrets(
+  namespace: SecretNamespace,
+  values: Record<string, string>
+): Promise<void> {
+  const items = Object.entries(values).map(([key, value]) => ({ key, value }));
+  await invoke("secure_storage_save", { namespace, items });
+}
+
+export async function removeSecrets(
+  namespace: SecretNamespace,
+  keys: string[]
+): Promise<void> {
+  await invoke("secure_storage_remove", { namespace, keys });
+}
+
+// Key names only; values never leave the store unless asked for
+export async function listSecrets(
+  namespace: SecretNamespace
+): Promise<string[]> {
+  return invoke<string[]>("secure_storage_list", { namespace });
+}
+
+// Saved once from the settings input; chat requests load it in Rust by provider id, and only
+// send it to the host of the `curl` template it was saved with
+export async function saveProviderApiKey(
+  providerId: string,
+  apiKey: string,
+  curl: string
+): Promise<void> {
+  await invoke("save_provider_api_key", { providerId, apiKey, curl });
+}
+
+// Whether a key is saved for an AI provider, without the key itself
+export async function hasProviderApiKey(providerId: string): Promise<boolean> {
+  return invoke<boolean>("has_provider_api_key", { providerId });
+}
+
+export async function removeProviderApiKey(providerId: string): Promise<void> {
+  await invoke("remove_provider_api_key", { providerId });
+}
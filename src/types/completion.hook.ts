@@ -6,6 +6,7 @@ import {
   ChangeEvent,
   ClipboardEvent,
 } from "react";
+import { ChatAttachment } from "./completion";
 // import {
 //   AttachedFile,
 //   ChatMessage,
@@ -68,6 +69,8 @@ export interface UseCompletionReturn {
   micOpen: boolean;
   /** Function to control microphone state */
   setMicOpen: Dispatch<SetStateAction<boolean>>;
+  /** Whether the push-to-talk key is held and audio is being recorded */
+  isPushToTalkRecording: boolean;
 
   // Conversation management
   /** ID of the currently active conversation, null for new conversation */
@@ -90,8 +93,11 @@ export interface UseCompletionReturn {
   screenshotConfiguration: any;
   /** Function to update screenshot configuration */
   setScreenshotConfiguration: Dispatch<SetStateAction<any>>;
-  /** Function to handle screenshot submission with optional prompt */
-  handleScreenshotSubmit: (base64: string, prompt?: string) => Promise<void>;
+  /** Function to handle screenshots (one per monitor) with optional prompt */
+  handleScreenshotSubmit: (
+    screens: ChatAttachment[],
+    prompt?: string
+  ) => Promise<void>;
 
   // File selection and keyboard handling
   /** Event handler for file input changes */
@@ -1,10 +1,22 @@
 // Completion-related types
+export type AttachmentSource = "screen" | "region" | "file" | "clipboard";
+
 export interface AttachedFile {
   id: string;
   name: string;
   type: string;
   base64: string;
   size: number;
+  source?: AttachmentSource;
+  caption?: string;
+}
+
+// Image sent with a chat request; the app checks format and size before sending it
+export interface ChatAttachment {
+  mime_type: string;
+  data: string;
+  source: AttachmentSource;
+  caption?: string;
 }
 
 export interface ChatMessage {